@@ -0,0 +1,29 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Expose the resolved versions of the core dependencies to `version_info()`
+fn main() {
+    let lock_path = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+
+    let lock = fs::read_to_string(&lock_path).unwrap_or_default();
+    for (package, variable) in [
+        ("pubky", "PUBKY_VERSION"),
+        ("pkarr", "PKARR_VERSION"),
+        ("pubky-common", "PUBKY_COMMON_VERSION"),
+        ("uniffi", "UNIFFI_VERSION"),
+    ] {
+        let version = locked_version(&lock, package).unwrap_or_else(|| "unknown".to_string());
+        println!("cargo:rustc-env={}={}", variable, version);
+    }
+}
+
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let needle = format!("name = \"{}\"\n", package);
+    let start = lock.find(&needle)? + needle.len();
+    let line = lock[start..].lines().next()?;
+    line.strip_prefix("version = \"")?
+        .strip_suffix('"')
+        .map(str::to_string)
+}
@@ -0,0 +1,118 @@
+use once_cell::sync::Lazy;
+use pubky_common::capabilities::{Action, Capability};
+use pubky_common::session::Session;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/**
+ * Metadata about an account that is signed in through this library
+ */
+#[derive(Clone)]
+pub struct AccountSession {
+    pub capabilities: Vec<String>,
+    pub granted: Vec<Capability>,
+    pub homeserver: Option<String>,
+    pub signed_in_at: u64,
+}
+
+// Accounts signed in with `sign_in`/`sign_up`, keyed by pubky
+static ACTIVE_ACCOUNTS: Lazy<Mutex<HashMap<String, AccountSession>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/**
+ * Record an account as signed in, replacing any previous entry for the same pubky
+ */
+pub fn track_account(session: &Session, homeserver: Option<String>) {
+    let signed_in_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let account = AccountSession {
+        capabilities: session.capabilities().iter().map(|c| c.to_string()).collect(),
+        granted: session.capabilities().clone(),
+        homeserver,
+        signed_in_at,
+    };
+    ACTIVE_ACCOUNTS.lock().unwrap().insert(session.pubky().to_string(), account);
+}
+
+/**
+ * Whether an account is signed in through this library
+ */
+pub fn is_account_tracked(pubky: &str) -> bool {
+    ACTIVE_ACCOUNTS.lock().unwrap().contains_key(pubky)
+}
+
+/**
+ * Whether an account is signed in with a session allowing `action` on `path`, e.g. a write under `/pub/`.
+ * Scoped sessions from `sign_in_scoped` only cover their own capabilities.
+ */
+pub fn account_has_capability(pubky: &str, path: &str, action: Action) -> bool {
+    // A directory path covers a capability scoped to that directory, with or without its trailing slash
+    let path = format!("{}/", path.trim_end_matches('/'));
+    ACTIVE_ACCOUNTS
+        .lock()
+        .unwrap()
+        .get(pubky)
+        .is_some_and(|account| {
+            account
+                .granted
+                .iter()
+                .any(|capability| path.starts_with(&capability.scope) && capability.actions.contains(&action))
+        })
+}
+
+/**
+ * Whether an account is signed in with a session granting more than `capabilities`, e.g. a full session
+ * that a scoped sign in would replace
+ */
+pub fn account_has_broader_session(pubky: &str, capabilities: &[Capability]) -> bool {
+    ACTIVE_ACCOUNTS
+        .lock()
+        .unwrap()
+        .get(pubky)
+        .is_some_and(|account| {
+            account.granted.iter().any(|granted| {
+                !capabilities.iter().any(|capability| {
+                    granted.scope.starts_with(&capability.scope)
+                        && granted.actions.iter().all(|action| capability.actions.contains(action))
+                })
+            })
+        })
+}
+
+/**
+ * Forget a signed out account
+ */
+pub fn untrack_account(pubky: &str) {
+    ACTIVE_ACCOUNTS.lock().unwrap().remove(pubky);
+}
+
+/**
+ * Forget every account, e.g. when the client holding their sessions is replaced
+ */
+pub fn clear_tracked_accounts() {
+    ACTIVE_ACCOUNTS.lock().unwrap().clear();
+}
+
+/**
+ * The signed in accounts as JSON objects, sorted by pubky
+ */
+pub fn active_accounts_json() -> Vec<serde_json::Value> {
+    let accounts = ACTIVE_ACCOUNTS.lock().unwrap();
+    let mut pubkys: Vec<&String> = accounts.keys().collect();
+    pubkys.sort();
+    pubkys
+        .into_iter()
+        .map(|pubky| {
+            let account = &accounts[pubky];
+            json!({
+                "pubky": pubky,
+                "capabilities": account.capabilities,
+                "homeserver": account.homeserver,
+                "signed_in_at": account.signed_in_at,
+            })
+        })
+        .collect()
+}
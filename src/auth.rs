@@ -2,7 +2,10 @@ use crate::keypair::get_keypair_from_secret_key;
 use crate::{PubkyAuthDetails, Capability};
 use crate::utils::create_response_vector;
 use std::collections::HashMap;
+use base64::engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use base64::Engine;
 use pubky::PubkyClient;
+use pubky_common::capabilities::Capability as PubkyCapability;
 use serde_json;
 use url::Url;
 
@@ -28,6 +31,70 @@ pub fn pubky_auth_details_to_json(details: &PubkyAuthDetails) -> Result<String,
     serde_json::to_string(details).map_err(|_| "Error serializing to JSON".to_string())
 }
 
+/**
+ * Decode an auth secret that may be base64url or standard base64, padded or not.
+ * Returns the secret re-encoded as unpadded base64url (the form pubky expects) and its decoded length.
+ */
+pub fn normalize_auth_secret(secret: &str) -> Result<(String, usize), String> {
+    // A `+` in a query string is decoded as a space
+    let secret = secret.replace(' ', "+");
+    let secret = secret.trim_end_matches('=');
+    let is_standard = secret.contains('+') || secret.contains('/');
+    let is_url_safe = secret.contains('-') || secret.contains('_');
+    if is_standard && is_url_safe {
+        return Err("Invalid secret: mixes base64 and base64url characters".to_string());
+    }
+
+    let engine = if is_standard { STANDARD_NO_PAD } else { URL_SAFE_NO_PAD };
+    let bytes = engine
+        .decode(secret)
+        .map_err(|e| format!("Invalid secret: {}", e))?;
+    Ok((URL_SAFE_NO_PAD.encode(&bytes), bytes.len()))
+}
+
+/**
+ * Reduce capability strings (`<scope>:<actions>`, optionally comma separated) to a minimal canonical set:
+ * actions on the same scope are merged (`r` + `w` gives `rw`), and a capability is dropped when a
+ * directory scope (ending in `/`) containing it already grants all of its actions.
+ */
+pub fn canonicalize_capabilities(capabilities: &[String]) -> Result<Vec<String>, String> {
+    let mut actions_by_scope: HashMap<String, String> = HashMap::new();
+    for capability in capabilities.iter().flat_map(|capabilities| capabilities.split(',')) {
+        let capability = capability.trim();
+        if capability.is_empty() {
+            continue;
+        }
+        let parsed = PubkyCapability::try_from(capability)
+            .map_err(|e| format!("Invalid capability '{}': {}", capability, e))?;
+        let actions = actions_by_scope.entry(parsed.scope).or_default();
+        for action in parsed.actions.iter().map(char::from) {
+            if !actions.contains(action) {
+                actions.push(action);
+            }
+        }
+    }
+
+    let covers = |scope: &str, actions: &str, other_scope: &str, other_actions: &str| {
+        scope != other_scope
+            && scope.ends_with('/')
+            && other_scope.starts_with(scope)
+            && other_actions.chars().all(|action| actions.contains(action))
+    };
+    let mut canonical: Vec<String> = actions_by_scope
+        .iter()
+        .filter(|(scope, actions)| {
+            !actions_by_scope.iter().any(|(other_scope, other_actions)| covers(other_scope, other_actions, scope, actions))
+        })
+        .map(|(scope, actions)| {
+            let mut actions: Vec<char> = actions.chars().collect();
+            actions.sort_unstable();
+            format!("{}:{}", scope, actions.into_iter().collect::<String>())
+        })
+        .collect();
+    canonical.sort();
+    Ok(canonical)
+}
+
 pub fn parse_pubky_auth_url(url_str: &str) -> Result<PubkyAuthDetails, String> {
     let url = Url::parse(url_str).map_err(|_| "Invalid URL".to_string())?;
 
@@ -47,6 +114,7 @@ pub fn parse_pubky_auth_url(url_str: &str) -> Result<PubkyAuthDetails, String> {
         .get("secret")
         .cloned()
         .ok_or_else(|| "Missing secret".to_string())?;
+    let (secret_normalized, secret_length) = normalize_auth_secret(&secret)?;
 
     let capabilities_str = query_params
         .get("capabilities")
@@ -80,5 +148,7 @@ pub fn parse_pubky_auth_url(url_str: &str) -> Result<PubkyAuthDetails, String> {
         relay,
         capabilities,
         secret,
+        secret_normalized,
+        secret_length,
     })
 }
@@ -0,0 +1,207 @@
+use crate::homeserver::{list_all, PROFILE_PATH};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use pkarr::{Keypair, PublicKey};
+use pubky::PubkyClient;
+use pubky_common::crypto::random_bytes;
+use serde_json::json;
+use std::io::{Cursor, Read, Write};
+use url::Url;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/**
+ * Spec line of account backups: `<spec line>\n<16 bytes salt><12 bytes nonce><AES-256-GCM encrypted ZIP archive>`
+ */
+pub const BACKUP_SPEC_LINE: &str = "pubky.org/backup/v1";
+
+/**
+ * Length of the Argon2id salt stored in the backup header
+ */
+const BACKUP_SALT_LENGTH: usize = 16;
+
+/**
+ * Length of the AES-GCM nonce stored after the salt
+ */
+const BACKUP_NONCE_LENGTH: usize = 12;
+
+/**
+ * Archive entry holding the passphrase-encrypted recovery file
+ */
+pub const BACKUP_RECOVERY_FILE_ENTRY: &str = "recovery.pkarr";
+
+/**
+ * Archive entry describing the backup
+ */
+pub const BACKUP_MANIFEST_ENTRY: &str = "manifest.json";
+
+/**
+ * Prefix of the archive entries holding homeserver files, followed by their path without the leading slash
+ */
+pub const BACKUP_CONTENT_PREFIX: &str = "content/";
+
+/**
+ * Derive the AES-256-GCM archive key from the passphrase with Argon2id
+ */
+fn backup_encryption_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive backup key: {}", e))?;
+    Ok(key)
+}
+
+/**
+ * Encrypt a backup archive with AES-256-GCM, keyed from the passphrase
+ */
+pub fn encrypt_backup_archive(archive: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let salt: [u8; BACKUP_SALT_LENGTH] = random_bytes();
+    let nonce: [u8; BACKUP_NONCE_LENGTH] = random_bytes();
+    let key = backup_encryption_key(passphrase, &salt)?;
+    let encrypted = Aes256Gcm::new(&key.into())
+        .encrypt(Nonce::from_slice(&nonce), archive)
+        .map_err(|e| format!("Failed to encrypt backup: {}", e))?;
+
+    let mut out = Vec::with_capacity(BACKUP_SPEC_LINE.len() + 1 + BACKUP_SALT_LENGTH + BACKUP_NONCE_LENGTH + encrypted.len());
+    out.extend_from_slice(BACKUP_SPEC_LINE.as_bytes());
+    out.push(b'\n');
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&encrypted);
+    Ok(out)
+}
+
+/**
+ * Decrypt a backup created by `encrypt_backup_archive`, returning the ZIP archive
+ */
+pub fn decrypt_backup_archive(backup: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let header_length = BACKUP_SPEC_LINE.len() + 1;
+    let ciphertext_offset = header_length + BACKUP_SALT_LENGTH + BACKUP_NONCE_LENGTH;
+    // The ciphertext ends with a 16-byte tag
+    if backup.len() < ciphertext_offset + 16
+        || !backup.starts_with(BACKUP_SPEC_LINE.as_bytes())
+        || backup[header_length - 1] != b'\n'
+    {
+        return Err("Not an account backup".to_string());
+    }
+    let salt = &backup[header_length..header_length + BACKUP_SALT_LENGTH];
+    let nonce = &backup[header_length + BACKUP_SALT_LENGTH..ciphertext_offset];
+    let key = backup_encryption_key(passphrase, salt)?;
+    Aes256Gcm::new(&key.into())
+        .decrypt(Nonce::from_slice(nonce), &backup[ciphertext_offset..])
+        .map_err(|_| "Failed to decrypt backup, wrong passphrase?".to_string())
+}
+
+/**
+ * Pack entries into a ZIP archive, stored uncompressed
+ */
+pub fn write_zip_archive(entries: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    for (name, data) in entries {
+        writer
+            .start_file(name.as_str(), options)
+            .and_then(|_| writer.write_all(data).map_err(ZipError::from))
+            .map_err(|e| format!("Failed to add {} to the backup: {}", name, e))?;
+    }
+    writer
+        .finish()
+        .map(Cursor::into_inner)
+        .map_err(|e| format!("Failed to write backup archive: {}", e))
+}
+
+/**
+ * Read the entries of a ZIP archive
+ */
+pub fn read_zip_archive(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut archive = ZipArchive::new(Cursor::new(archive)).map_err(|e| format!("Invalid backup archive: {}", e))?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(|e| format!("Invalid backup archive: {}", e))?;
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read {} from the backup: {}", file.name(), e))?;
+        entries.push((file.name().to_string(), data));
+    }
+    Ok(entries)
+}
+
+/**
+ * Build an encrypted account backup: the recovery file, the profile and, with `include_content`, every file under `/pub/`
+ */
+pub async fn create_account_backup_bytes(client: &PubkyClient, keypair: &Keypair, passphrase: &str, include_content: bool) -> Result<Vec<u8>, String> {
+    let public_key = keypair.public_key();
+    let recovery_file = PubkyClient::create_recovery_file(keypair, passphrase)
+        .map_err(|_| "Failed to create recovery file".to_string())?;
+
+    let urls = if include_content {
+        let prefix = Url::parse(&format!("pubky://{}/pub/", public_key)).map_err(|_| "Failed to parse URL".to_string())?;
+        list_all(client, &prefix).await?
+    } else {
+        vec![format!("pubky://{}{}", public_key, PROFILE_PATH)]
+    };
+
+    let mut content = Vec::with_capacity(urls.len());
+    for url in urls {
+        let parsed_url = Url::parse(&url).map_err(|_| format!("Invalid URL: {}", url))?;
+        match client.get(parsed_url.clone()).await {
+            Ok(Some(bytes)) => content.push((format!("{}{}", BACKUP_CONTENT_PREFIX, parsed_url.path().trim_start_matches('/')), bytes.to_vec())),
+            Ok(None) => {},
+            Err(e) => return Err(format!("Failed to get {}: {}", url, e)),
+        }
+    }
+
+    let manifest = json!({
+        "version": 1,
+        "public_key": public_key.to_string(),
+        "include_content": include_content,
+        "file_count": content.len(),
+    });
+    let mut entries = vec![
+        (BACKUP_MANIFEST_ENTRY.to_string(), manifest.to_string().into_bytes()),
+        (BACKUP_RECOVERY_FILE_ENTRY.to_string(), recovery_file),
+    ];
+    entries.extend(content);
+
+    let archive = write_zip_archive(&entries)?;
+    encrypt_backup_archive(&archive, passphrase)
+}
+
+/**
+ * Restore an account backup: sign up the recovered key at the target homeserver and upload the backed up files.
+ * Returns the recovered keypair and the number of files restored.
+ */
+pub async fn restore_account_backup_bytes(client: &PubkyClient, backup: &[u8], passphrase: &str, homeserver: &PublicKey) -> Result<(Keypair, usize), String> {
+    let archive = decrypt_backup_archive(backup, passphrase)?;
+    let entries = read_zip_archive(&archive)?;
+
+    let recovery_file = entries
+        .iter()
+        .find(|(name, _)| name == BACKUP_RECOVERY_FILE_ENTRY)
+        .map(|(_, data)| data)
+        .ok_or_else(|| "Backup has no recovery file".to_string())?;
+    let keypair = PubkyClient::decrypt_recovery_file(recovery_file, passphrase)
+        .map_err(|_| "Failed to decrypt recovery file".to_string())?;
+
+    client
+        .signup(&keypair, homeserver)
+        .await
+        .map_err(|e| format!("signup failure: {}", e))?;
+
+    let mut restored = 0;
+    for (name, data) in &entries {
+        let Some(path) = name.strip_prefix(BACKUP_CONTENT_PREFIX) else {
+            continue;
+        };
+        let url = Url::parse(&format!("pubky://{}/{}", keypair.public_key(), path))
+            .map_err(|_| format!("Invalid path in backup: {}", path))?;
+        client
+            .put(url, data)
+            .await
+            .map_err(|e| format!("Failed to restore {}: {}", path, e))?;
+        restored += 1;
+    }
+    Ok((keypair, restored))
+}
@@ -0,0 +1,54 @@
+use crate::utils::SUPPORTED_RDATA_TYPES;
+use once_cell::sync::Lazy;
+use pkarr::dns::TYPE;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/**
+ * Names given to record types that pkarr's DNS library doesn't know, keyed by type number
+ */
+pub struct DnsTypeRegistry {
+    types: Mutex<HashMap<u16, String>>,
+}
+
+impl DnsTypeRegistry {
+    fn new() -> Self {
+        Self {
+            types: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /**
+     * Name a record type, replacing any name previously registered for the same number.
+     * Types the DNS library already knows can't be renamed.
+     */
+    pub fn register(&self, type_number: u16, type_name: &str) -> Result<(), String> {
+        if !matches!(TYPE::from(type_number), TYPE::Unknown(_)) {
+            return Err(format!("Record type {} is already known as {:?}", type_number, TYPE::from(type_number)));
+        }
+        if type_name.is_empty() || !type_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Invalid record type name: {}", type_name));
+        }
+        let type_name = type_name.to_ascii_uppercase();
+        if SUPPORTED_RDATA_TYPES.contains(&type_name.as_str()) {
+            return Err(format!("Record type name {} is already in use", type_name));
+        }
+        self.types.lock().unwrap().insert(type_number, type_name);
+        Ok(())
+    }
+
+    pub fn name_of(&self, type_number: u16) -> Option<String> {
+        self.types.lock().unwrap().get(&type_number).cloned()
+    }
+
+    /**
+     * The registered types, sorted by number
+     */
+    pub fn all(&self) -> Vec<(u16, String)> {
+        let mut types: Vec<(u16, String)> = self.types.lock().unwrap().iter().map(|(number, name)| (*number, name.clone())).collect();
+        types.sort();
+        types
+    }
+}
+
+pub static DNS_TYPE_REGISTRY: Lazy<DnsTypeRegistry> = Lazy::new(DnsTypeRegistry::new);
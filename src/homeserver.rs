@@ -0,0 +1,188 @@
+use crate::utils::{get_svcb_target, svcb_target_to_endpoint};
+use pkarr::dns::rdata::{RData, HTTPS, SVCB};
+use pkarr::dns::{self, ResourceRecord};
+use pkarr::{Keypair, PublicKey};
+use pubky::PubkyClient;
+use pubky_common::auth::AuthToken;
+use pubky_common::capabilities::Capability;
+use url::Url;
+
+/**
+ * Name of the record pointing a public key to its homeserver, as published by pubky on signup
+ */
+pub const HOMESERVER_RECORD_NAME: &str = "_pubky";
+
+/**
+ * TTL pubky uses for the homeserver record
+ */
+pub const HOMESERVER_RECORD_TTL: u32 = 60 * 60;
+
+/**
+ * Path of the pubky.app profile of an account
+ */
+pub const PROFILE_PATH: &str = "/pub/pubky.app/profile.json";
+
+/**
+ * Name of the TXT record holding the target of an alias, see `create_link_record`
+ */
+pub fn link_record_name(alias: &str) -> String {
+    format!("_alias.{}.{}", alias, HOMESERVER_RECORD_NAME)
+}
+
+/**
+ * Build the homeserver record: an HTTPS record named `_pubky` with priority 0, targeting the homeserver
+ */
+pub fn homeserver_resource_record(homeserver: &PublicKey, ttl: u32) -> Result<ResourceRecord<'static>, String> {
+    let homeserver = homeserver.to_string();
+    let target = dns::Name::new(&homeserver).map_err(|e| format!("Invalid homeserver target: {}", e))?;
+    let name = dns::Name::new(HOMESERVER_RECORD_NAME).map_err(|e| format!("Invalid DNS name: {}", e))?;
+    Ok(ResourceRecord::new(name, dns::CLASS::IN, ttl, RData::HTTPS(HTTPS(SVCB::new(0, target)))).into_owned())
+}
+
+/**
+ * Path where homeservers describe their policies and features
+ */
+pub const HOMESERVER_INFO_PATH: &str = "/.well-known/pubky";
+
+/**
+ * Build the `get_homeserver_info` JSON from a homeserver's `/.well-known/pubky` document. Homeservers without
+ * that document (None) are reported with open signups and no known quota.
+ */
+pub fn homeserver_info_json(well_known: Option<&serde_json::Value>) -> serde_json::Value {
+    let empty = serde_json::Value::Null;
+    let well_known = well_known.unwrap_or(&empty);
+    serde_json::json!({
+        "version": well_known["version"].as_str(),
+        "signup_mode": well_known["signup_mode"].as_str().unwrap_or("open"),
+        "quota_bytes": well_known["quota_bytes"].as_u64(),
+        "terms_url": well_known["terms_url"].as_str(),
+        "features": well_known["features"].as_array().cloned().unwrap_or_default(),
+    })
+}
+
+/**
+ * Resolve the HTTP(S) endpoint of a homeserver, given the target (and port) of a `_pubky` record.
+ * A homeserver that is itself a pkarr key publishes its endpoint in its own signed packet.
+ */
+pub async fn resolve_homeserver_endpoint(client: &PubkyClient, homeserver: &str, port: Option<u16>) -> Option<String> {
+    match PublicKey::try_from(homeserver) {
+        Ok(homeserver_key) => match client.pkarr().resolve(&homeserver_key).await {
+            Ok(Some(homeserver_packet)) => get_svcb_target(&homeserver_packet, ".")
+                .map(|(target, port)| svcb_target_to_endpoint(&target, port)),
+            _ => None,
+        },
+        Err(_) => Some(svcb_target_to_endpoint(homeserver, port)),
+    }
+}
+
+/**
+ * Write content for a key on a given homeserver, talking to the homeserver directly so that the key's `_pubky`
+ * record is left alone. Signs in with a root token, signing up only when the homeserver refuses the sign in.
+ * The `http_client` must keep cookies, as the session is carried by one.
+ */
+pub async fn put_on_homeserver(
+    client: &PubkyClient,
+    http_client: &reqwest::Client,
+    keypair: &Keypair,
+    homeserver: &PublicKey,
+    path: &str,
+    content: &[u8],
+) -> Result<(), String> {
+    let endpoint = resolve_homeserver_endpoint(client, &homeserver.to_string(), None)
+        .await
+        .ok_or_else(|| format!("Could not resolve endpoint for homeserver {}", homeserver))?;
+    let endpoint = Url::parse(&endpoint).map_err(|e| format!("Invalid homeserver endpoint {}: {}", endpoint, e))?;
+    let url = |path: &str| {
+        let mut url = endpoint.clone();
+        url.set_path(path);
+        url
+    };
+
+    let token = AuthToken::sign(keypair, vec![Capability::root()]).serialize();
+    let signed_in = http_client
+        .post(url("/session"))
+        .body(token)
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success());
+    if !signed_in {
+        let token = AuthToken::sign(keypair, vec![Capability::root()]).serialize();
+        let response = http_client
+            .post(url("/signup"))
+            .body(token)
+            .send()
+            .await
+            .map_err(|e| format!("signup failure: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("signup failure: status {}", response.status()));
+        }
+    }
+
+    let response = http_client
+        .put(url(&format!("/{}{}", keypair.public_key(), path)))
+        .body(content.to_vec())
+        .send()
+        .await
+        .map_err(|e| format!("Failed to put: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to put: status {}", response.status()));
+    }
+    Ok(())
+}
+
+/**
+ * Resolve the HTTP(S) endpoint of the homeserver hosting a public key
+ */
+pub async fn resolve_pubky_endpoint(client: &PubkyClient, public_key: &PublicKey) -> Result<String, String> {
+    let signed_packet = match client.pkarr().resolve(public_key).await {
+        Ok(Some(signed_packet)) => signed_packet,
+        Ok(None) => return Err("No signed packet found".to_string()),
+        Err(e) => return Err(format!("Failed to resolve: {}", e)),
+    };
+    let (homeserver, port) = get_svcb_target(&signed_packet, "_pubky")
+        .ok_or_else(|| "No homeserver record found".to_string())?;
+    resolve_homeserver_endpoint(client, &homeserver, port)
+        .await
+        .ok_or_else(|| format!("Could not resolve endpoint for homeserver {}", homeserver))
+}
+
+/**
+ * Convert a `pubky://<public key>/<path>` URL into the `https://` URL of the homeserver hosting it.
+ * Any other URL is returned unchanged.
+ */
+pub async fn pubky_to_https_url(client: &PubkyClient, url: &Url) -> Result<Url, String> {
+    if url.scheme() != "pubky" {
+        return Ok(url.clone());
+    }
+    let host = url.host_str().ok_or_else(|| "Missing Pubky URL host".to_string())?;
+    let public_key = PublicKey::try_from(host).map_err(|e| format!("Invalid public key: {}", e))?;
+    let endpoint = resolve_pubky_endpoint(client, &public_key).await?;
+
+    let mut https_url = Url::parse(&endpoint).map_err(|e| format!("Invalid homeserver endpoint {}: {}", endpoint, e))?;
+    https_url.set_path(&format!("/{}{}", public_key, url.path()));
+    https_url.set_query(url.query());
+    Ok(https_url)
+}
+
+/**
+ * List every URL under a directory, paging through the listing from the last URL of each page
+ */
+pub async fn list_all(client: &PubkyClient, prefix: &Url) -> Result<Vec<String>, String> {
+    let mut urls: Vec<String> = Vec::new();
+    loop {
+        let list_builder = client.list(prefix.clone()).map_err(|e| format!("Failed to list: {}", e))?;
+        let cursor = urls.last().cloned();
+        let list_builder = match &cursor {
+            Some(cursor) => list_builder.cursor(cursor),
+            None => list_builder,
+        };
+        let page = list_builder
+            .send()
+            .await
+            .map_err(|e| format!("Failed to send list request: {}", e))?;
+        if page.is_empty() || page.last() == cursor.as_ref() {
+            return Ok(urls);
+        }
+        urls.extend(page);
+    }
+}
@@ -1,4 +1,7 @@
+use base64::engine::general_purpose;
+use base64::Engine;
 use pkarr::Keypair;
+use sha2::{Digest, Sha256};
 
 /**
  * Get a keypair from a secret key
@@ -31,4 +34,83 @@ pub fn get_secret_key_from_keypair(keypair: &Keypair) -> String {
  */
 pub fn generate_keypair() -> Keypair {
     Keypair::random()
-}
\ No newline at end of file
+}
+
+/**
+ * Derive a keypair deterministically from a seed, used as the secret key when it is 32 bytes
+ * and hashed to 32 bytes with SHA-256 otherwise
+ */
+pub fn keypair_from_seed(seed: &[u8]) -> Keypair {
+    let secret_key: [u8; 32] = match seed.try_into() {
+        Ok(secret_key) => secret_key,
+        Err(_) => Sha256::digest(seed).into(),
+    };
+    Keypair::from_secret_key(&secret_key)
+}
+
+/**
+ * Derive the child keypair at the SLIP-10 Ed25519 path `m/44'/{index}'`, using the master secret key as seed
+ */
+pub fn derive_child_keypair(master: &Keypair, index: u32) -> Result<Keypair, String> {
+    if index >= 0x8000_0000 {
+        return Err("Index must be lower than 2^31".to_string());
+    }
+    let child_secret_key = slip10_ed25519::derive_ed25519_private_key(&master.secret_key(), &[44, index]);
+    Ok(Keypair::from_secret_key(&child_secret_key))
+}
+
+/**
+ * Derive a keypair from a BIP-39 mnemonic: the SLIP-10 Ed25519 key at `m/44'/0'` of the mnemonic's seed,
+ * with an empty BIP-39 passphrase
+ */
+pub fn keypair_from_mnemonic(mnemonic: &str) -> Result<Keypair, String> {
+    let mnemonic = bip39::Mnemonic::parse(mnemonic).map_err(|e| format!("Invalid mnemonic: {}", e))?;
+    let seed = mnemonic.to_seed("");
+    let secret_key = slip10_ed25519::derive_ed25519_private_key(&seed, &[44, 0]);
+    Ok(Keypair::from_secret_key(&secret_key))
+}
+
+/**
+ * Decode a 32-byte secret key from "hex", "base64" or "base58"
+ */
+pub fn decode_secret_key(secret_key: &str, format: &str) -> Result<[u8; 32], String> {
+    let bytes = match format {
+        "hex" => hex::decode(secret_key).map_err(|_| "Failed to decode secret key".to_string())?,
+        "base64" => general_purpose::STANDARD.decode(secret_key).map_err(|_| "Failed to decode secret key".to_string())?,
+        "base58" => bs58::decode(secret_key).into_vec().map_err(|_| "Failed to decode secret key".to_string())?,
+        other => return Err(format!("Unsupported secret key format: {}", other)),
+    };
+    bytes.try_into().map_err(|_| "Failed to convert secret key to 32-byte array".to_string())
+}
+
+/**
+ * Encode a 32-byte secret key as "hex", "base64" or "base58"
+ */
+pub fn encode_secret_key(secret_key: &[u8; 32], format: &str) -> Result<String, String> {
+    match format {
+        "hex" => Ok(hex::encode(secret_key)),
+        "base64" => Ok(general_purpose::STANDARD.encode(secret_key)),
+        "base58" => Ok(bs58::encode(secret_key).into_string()),
+        other => Err(format!("Unsupported secret key format: {}", other)),
+    }
+}
+
+/**
+ * Create a signed revocation certificate for a keypair, as the JSON document
+ * `{ "certificate", "signature" }`: `certificate` is the JSON string `{ "public_key", "revoked_at", "reason" }`
+ * and `signature` the base64 Ed25519 signature of its bytes
+ */
+pub fn signed_revocation_certificate(keypair: &Keypair, reason: &str, revoked_at: u64) -> Result<String, String> {
+    let certificate = serde_json::json!({
+        "public_key": keypair.public_key().to_string(),
+        "revoked_at": revoked_at,
+        "reason": reason,
+    })
+    .to_string();
+    let signature = keypair.sign(certificate.as_bytes());
+    serde_json::to_string(&serde_json::json!({
+        "certificate": certificate,
+        "signature": general_purpose::STANDARD.encode(signature.to_bytes()),
+    }))
+    .map_err(|e| format!("Failed to serialize JSON: {}", e))
+}
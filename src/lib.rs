@@ -2,11 +2,21 @@ mod types;
 mod keypair;
 mod auth;
 mod utils;
+mod recovery;
+mod homeserver;
+mod accounts;
+mod dns_registry;
+mod backup;
 
 pub use types::*;
 pub use keypair::*;
 pub use auth::*;
 pub use utils::*;
+pub use recovery::*;
+pub use homeserver::*;
+pub use accounts::*;
+pub use dns_registry::*;
+pub use backup::*;
 
 uniffi::setup_scaffolding!();
 
@@ -18,43 +28,480 @@ use hex;
 use hex::ToHex;
 use url::Url;
 use tokio;
-use pkarr::{SignedPacket, dns, PublicKey};
-use pkarr::dns::rdata::{RData, HTTPS, SVCB};
+use pkarr::{SignedPacket, dns, Keypair, PublicKey, PkarrRelayClient, PkarrRelayClientAsync, RelaySettings};
+use pkarr::dns::rdata::{RData, CNAME, HTTPS, MX, NAPTR, NS, SVCB, TXT};
 use pkarr::dns::{Packet, ResourceRecord};
+use pkarr::mainline::{MutableItem, Testnet};
 use serde_json::json;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::pin;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use pkarr::bytes::Bytes;
+use pubky_common::capabilities::Action;
 use pubky_common::session::Session;
 use tokio::runtime::Runtime;
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+use tokio::io::AsyncWriteExt;
 use tokio::time;
+use zeroize::Zeroize;
+
+/**
+* A write waiting in the offline queue
+*/
+#[derive(Clone)]
+pub struct QueuedPut {
+    pub url: String,
+    pub content: String,
+    pub queued_at_unix: u64,
+}
+
+/// Default period of the internal event loop
+pub const DEFAULT_EVENT_LOOP_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Default for how long a resolved signed packet is served from the cache, in seconds
+pub const DEFAULT_MAX_RESOLVE_CACHE_TTL: u32 = 60 * 60;
+
+/// Default number of consecutive failures that open an endpoint's circuit
+pub const DEFAULT_CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+
+/// Default time an open circuit rejects requests before letting a trial request through, in seconds
+pub const DEFAULT_CIRCUIT_RESET_TIMEOUT_SECS: u64 = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct EndpointCircuit {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trial_started_at: Option<Instant>,
+}
+
+// Stops calling an endpoint after `failure_threshold` consecutive failures, for `reset_timeout`.
+// Once that has passed the circuit is half-open: a single trial request is let through, and closes the
+// circuit on success or opens it again on failure. Other requests are rejected while the trial is in flight,
+// or until `reset_timeout` has passed again, in case its outcome is never recorded.
+pub struct CircuitBreaker {
+    failure_threshold: Mutex<u32>,
+    reset_timeout: Mutex<Duration>,
+    endpoints: Mutex<HashMap<String, EndpointCircuit>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self {
+            failure_threshold: Mutex::new(DEFAULT_CIRCUIT_FAILURE_THRESHOLD),
+            reset_timeout: Mutex::new(Duration::from_secs(DEFAULT_CIRCUIT_RESET_TIMEOUT_SECS)),
+            endpoints: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn configure(&self, failure_threshold: u32, reset_timeout: Duration) {
+        *self.failure_threshold.lock().unwrap() = failure_threshold.max(1);
+        *self.reset_timeout.lock().unwrap() = reset_timeout;
+    }
+
+    pub fn state(&self, endpoint: &str) -> CircuitState {
+        let reset_timeout = *self.reset_timeout.lock().unwrap();
+        match self.endpoints.lock().unwrap().get(endpoint).and_then(|circuit| circuit.opened_at) {
+            Some(opened_at) if opened_at.elapsed() < reset_timeout => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+
+    pub fn consecutive_failures(&self, endpoint: &str) -> u32 {
+        self.endpoints.lock().unwrap().get(endpoint).map_or(0, |circuit| circuit.consecutive_failures)
+    }
+
+    // Returns a `circuit_open` error instead of letting a request to an open circuit through
+    pub fn check(&self, endpoint: &str) -> Result<(), String> {
+        let reset_timeout = *self.reset_timeout.lock().unwrap();
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let Some(circuit) = endpoints.get_mut(endpoint) else {
+            return Ok(());
+        };
+        let open = match circuit.opened_at {
+            Some(opened_at) if opened_at.elapsed() < reset_timeout => true,
+            Some(_) => circuit.trial_started_at.is_some_and(|started_at| started_at.elapsed() < reset_timeout),
+            None => false,
+        };
+        if open {
+            return Err(format!("circuit_open: too many consecutive failures for {}, not retrying yet", endpoint));
+        }
+        if circuit.opened_at.is_some() {
+            circuit.trial_started_at = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    pub fn record_success(&self, endpoint: &str) {
+        self.endpoints.lock().unwrap().remove(endpoint);
+    }
+
+    pub fn record_failure(&self, endpoint: &str) {
+        let failure_threshold = *self.failure_threshold.lock().unwrap();
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let circuit = endpoints.entry(endpoint.to_string()).or_default();
+        circuit.consecutive_failures += 1;
+        circuit.trial_started_at = None;
+        // A failed trial request in the half-open state also lands here, restarting the timeout
+        if circuit.consecutive_failures >= failure_threshold {
+            circuit.opened_at = Some(Instant::now());
+        }
+    }
+
+    // Count a request's outcome; only transient errors mean the endpoint is failing
+    pub fn record<T>(&self, endpoint: &str, result: &Result<T, pubky::Error>) {
+        match result {
+            Err(error) if is_transient_error(error) => self.record_failure(endpoint),
+            _ => self.record_success(endpoint),
+        }
+    }
+
+    pub fn reset(&self, endpoint: &str) {
+        self.endpoints.lock().unwrap().remove(endpoint);
+    }
+}
+
+// Resolve, cache and publish counters reported by `performance_stats`
+#[derive(Default)]
+pub struct PerformanceStats {
+    resolve_count: AtomicU64,
+    resolve_micros_total: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    publish_count: AtomicU64,
+    publish_failures: AtomicU64,
+}
+
+impl PerformanceStats {
+    pub fn record_resolve(&self, elapsed: Duration, cache_hit: bool) {
+        self.resolve_count.fetch_add(1, Ordering::Relaxed);
+        self.resolve_micros_total.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        let cache_counter = if cache_hit { &self.cache_hits } else { &self.cache_misses };
+        cache_counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_publish(&self, success: bool) {
+        self.publish_count.fetch_add(1, Ordering::Relaxed);
+        if !success {
+            self.publish_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let resolve_count = self.resolve_count.load(Ordering::Relaxed);
+        let cache_hits = self.cache_hits.load(Ordering::Relaxed);
+        let cache_misses = self.cache_misses.load(Ordering::Relaxed);
+        let average_resolve_latency_ms = (resolve_count > 0)
+            .then(|| self.resolve_micros_total.load(Ordering::Relaxed) as f64 / resolve_count as f64 / 1000.0);
+        let cache_hit_ratio = (cache_hits + cache_misses > 0)
+            .then(|| cache_hits as f64 / (cache_hits + cache_misses) as f64);
+        json!({
+            "resolve_count": resolve_count,
+            "average_resolve_latency_ms": average_resolve_latency_ms,
+            "cache_hits": cache_hits,
+            "cache_misses": cache_misses,
+            "cache_hit_ratio": cache_hit_ratio,
+            "publish_count": self.publish_count.load(Ordering::Relaxed),
+            "publish_failures": self.publish_failures.load(Ordering::Relaxed),
+        })
+    }
+
+    pub fn reset(&self) {
+        for counter in [
+            &self.resolve_count,
+            &self.resolve_micros_total,
+            &self.cache_hits,
+            &self.cache_misses,
+            &self.publish_count,
+            &self.publish_failures,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+// A request being made on behalf of every identical concurrent call, see `NetworkClient::deduplicate`
+#[derive(Default)]
+struct InFlightRequest {
+    notify: Notify,
+    result: Mutex<Option<Vec<String>>>,
+}
 
 pub struct NetworkClient {
     client: Mutex<Arc<PubkyClient>>,
+    use_testnet: Mutex<bool>,
+    max_resolve_cache_ttl: Mutex<u32>,
+    testnet_bootstrap_nodes: Mutex<Vec<String>>,
+    queued_puts: Mutex<VecDeque<QueuedPut>>,
+    event_loop_interval: watch::Sender<Duration>,
+    event_loop_task: Mutex<Option<JoinHandle<()>>>,
+    default_homeserver: Mutex<Option<PublicKey>>,
+    relay_priority: Mutex<Vec<String>>,
+    circuit_breaker: CircuitBreaker,
+    in_flight_requests: Mutex<HashMap<String, Arc<InFlightRequest>>>,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    performance_stats: PerformanceStats,
+}
+
+impl Default for NetworkClient {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl NetworkClient {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
-            client: Mutex::new(Arc::new(PubkyClient::default()))
+            client: Mutex::new(Arc::new(Self::build_client(false, &[]))),
+            use_testnet: Mutex::new(false),
+            max_resolve_cache_ttl: Mutex::new(DEFAULT_MAX_RESOLVE_CACHE_TTL),
+            testnet_bootstrap_nodes: Mutex::new(Vec::new()),
+            queued_puts: Mutex::new(VecDeque::new()),
+            event_loop_interval: watch::channel(DEFAULT_EVENT_LOOP_INTERVAL).0,
+            event_loop_task: Mutex::new(None),
+            default_homeserver: Mutex::new(None),
+            relay_priority: Mutex::new(Vec::new()),
+            circuit_breaker: CircuitBreaker::new(),
+            in_flight_requests: Mutex::new(HashMap::new()),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            performance_stats: PerformanceStats::default(),
+        }
+    }
+
+    // The testnet bootstraps from `testnet_bootstrap_nodes`, or the local testnet when empty
+    fn build_client(use_testnet: bool, testnet_bootstrap_nodes: &[String]) -> PubkyClient {
+        if !use_testnet {
+            return PubkyClient::default();
+        }
+        if testnet_bootstrap_nodes.is_empty() {
+            return PubkyClient::testnet();
         }
+        PubkyClient::test(&Testnet {
+            bootstrap: testnet_bootstrap_nodes.to_vec(),
+            nodes: vec![],
+        })
     }
 
+    // The new client starts without sessions, so every tracked account is signed out
     pub fn switch_network(&self, use_testnet: bool) {
-        let new_client = if use_testnet {
-            Arc::new(PubkyClient::testnet())
-        } else {
-            Arc::new(PubkyClient::default())
-        };
+        let testnet_bootstrap_nodes = self.testnet_bootstrap_nodes.lock().unwrap().clone();
+        let new_client = Arc::new(Self::build_client(use_testnet, &testnet_bootstrap_nodes));
 
+        *self.use_testnet.lock().unwrap() = use_testnet;
         let mut client = self.client.lock().unwrap();
         *client = new_client;
+        clear_tracked_accounts();
+    }
+
+    // Checked by `resolve` against the time cached packets were inserted
+    pub fn set_max_resolve_cache_ttl(&self, max_resolve_cache_ttl: u32) {
+        *self.max_resolve_cache_ttl.lock().unwrap() = max_resolve_cache_ttl;
+    }
+
+    pub fn get_max_resolve_cache_ttl(&self) -> u32 {
+        *self.max_resolve_cache_ttl.lock().unwrap()
+    }
+
+    // Rebuilds the client right away when it is on the testnet, otherwise on the next `switch_network(true)`
+    pub fn set_testnet_bootstrap_nodes(&self, nodes: Vec<String>) {
+        *self.testnet_bootstrap_nodes.lock().unwrap() = nodes;
+        let use_testnet = *self.use_testnet.lock().unwrap();
+        if use_testnet {
+            self.switch_network(true);
+        }
+    }
+
+    pub fn get_testnet_bootstrap_nodes(&self) -> Vec<String> {
+        self.testnet_bootstrap_nodes.lock().unwrap().clone()
     }
 
     pub fn get_client(&self) -> Arc<PubkyClient> {
         self.client.lock().unwrap().clone()
     }
+
+    pub fn set_event_loop_interval(&self, interval: Duration) {
+        self.event_loop_interval.send_replace(interval);
+    }
+
+    pub fn get_event_loop_interval(&self) -> Duration {
+        *self.event_loop_interval.borrow()
+    }
+
+    // Returns false if the loop was already running
+    pub fn start_event_loop(&self, event_notifier: Arc<EventNotifier>, runtime: &Runtime) -> bool {
+        let mut task = self.event_loop_task.lock().unwrap();
+        if task.as_ref().is_some_and(|task| !task.is_finished()) {
+            return false;
+        }
+        let mut interval_receiver = self.event_loop_interval.subscribe();
+        *task = Some(runtime.spawn(async move {
+            let mut interval = time::interval(*interval_receiver.borrow_and_update());
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        event_notifier.as_ref().notify_event("Internal event triggered".to_string());
+                    },
+                    changed = interval_receiver.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        let period = *interval_receiver.borrow_and_update();
+                        interval = time::interval_at(time::Instant::now() + period, period);
+                    },
+                }
+            }
+        }));
+        true
+    }
+
+    // Returns false if the loop was not running
+    pub fn stop_event_loop(&self) -> bool {
+        match self.event_loop_task.lock().unwrap().take() {
+            Some(task) => {
+                let was_running = !task.is_finished();
+                task.abort();
+                was_running
+            },
+            None => false,
+        }
+    }
+
+    pub fn set_default_homeserver(&self, homeserver: PublicKey) {
+        *self.default_homeserver.lock().unwrap() = Some(homeserver);
+    }
+
+    pub fn get_default_homeserver(&self) -> Option<PublicKey> {
+        self.default_homeserver.lock().unwrap().clone()
+    }
+
+    pub fn set_relay_priority(&self, relays: Vec<String>) {
+        *self.relay_priority.lock().unwrap() = relays;
+    }
+
+    pub fn get_relay_priority(&self) -> Vec<String> {
+        self.relay_priority.lock().unwrap().clone()
+    }
+
+    // Run a request unless an identical one (same key) is already in flight, in which case
+    // wait for that one and share its result
+    pub async fn deduplicate<F: Future<Output = Vec<String>>>(&self, key: String, request: F) -> Vec<String> {
+        let (in_flight, is_duplicate) = {
+            let mut in_flight_requests = self.in_flight_requests.lock().unwrap();
+            match in_flight_requests.get(&key) {
+                Some(in_flight) => (in_flight.clone(), true),
+                None => {
+                    let in_flight = Arc::new(InFlightRequest::default());
+                    in_flight_requests.insert(key.clone(), in_flight.clone());
+                    (in_flight, false)
+                }
+            }
+        };
+        if is_duplicate {
+            // Register for the notification before checking for a result, so it can't be missed
+            let mut notified = pin!(in_flight.notify.notified());
+            notified.as_mut().enable();
+            let finished = in_flight.result.lock().unwrap().clone();
+            if let Some(result) = finished {
+                return result;
+            }
+            notified.await;
+            let result = in_flight.result.lock().unwrap().clone();
+            return result.unwrap_or_else(|| create_response_vector(true, "Deduplicated request failed".to_string()));
+        }
+
+        let result = request.await;
+        *in_flight.result.lock().unwrap() = Some(result.clone());
+        self.in_flight_requests.lock().unwrap().remove(&key);
+        in_flight.notify.notify_waiters();
+        result
+    }
+
+    pub fn circuit_breaker(&self) -> &CircuitBreaker {
+        &self.circuit_breaker
+    }
+
+    // Content bytes uploaded to and downloaded from homeservers, reported by `get_network_stats`
+    pub fn record_bytes_sent(&self, bytes: usize) {
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_bytes_received(&self, bytes: usize) {
+        self.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    pub fn performance_stats(&self) -> &PerformanceStats {
+        &self.performance_stats
+    }
+
+    pub fn pending_request_count(&self) -> usize {
+        self.in_flight_requests.lock().unwrap().len()
+    }
+
+    // Queue a write, replacing a write to the same URL queued before
+    pub fn queue_put(&self, queued_put: QueuedPut) {
+        let mut queued_puts = self.queued_puts.lock().unwrap();
+        queued_puts.retain(|queued| queued.url != queued_put.url);
+        queued_puts.push_back(queued_put);
+    }
+
+    pub fn next_queued_put(&self) -> Option<QueuedPut> {
+        self.queued_puts.lock().unwrap().front().cloned()
+    }
+
+    // Forget the queued write to a URL, once it has been written or can't be
+    pub fn remove_queued_put(&self, url: &str) {
+        self.queued_puts.lock().unwrap().retain(|queued| queued.url != url);
+    }
+
+    pub fn get_queued_puts(&self) -> Vec<QueuedPut> {
+        self.queued_puts.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear_queued_puts(&self) -> usize {
+        let mut queued_puts = self.queued_puts.lock().unwrap();
+        let count = queued_puts.len();
+        queued_puts.clear();
+        count
+    }
 }
 
 static NETWORK_CLIENT: Lazy<NetworkClient> = Lazy::new(|| NetworkClient::new());
@@ -64,18 +511,182 @@ pub fn get_pubky_client() -> Arc<PubkyClient> {
     NETWORK_CLIENT.get_client()
 }
 
+// Build a relay client that only talks to the given relays
+pub fn get_relay_client(relays: Vec<String>) -> Result<PkarrRelayClientAsync, String> {
+    let relays = relays
+        .into_iter()
+        .map(|relay| relay.trim_end_matches('/').to_string())
+        .collect::<Vec<String>>();
+    for relay in &relays {
+        if let Err(error) = Url::parse(relay) {
+            return Err(format!("Invalid relay URL {}: {}", relay, error));
+        }
+    }
+    let settings = RelaySettings {
+        relays,
+        ..RelaySettings::default()
+    };
+    match PkarrRelayClient::new(settings) {
+        Ok(client) => Ok(client.as_async()),
+        Err(error) => Err(format!("Failed to create relay client: {}", error)),
+    }
+}
+
+/**
+* Switch between the default network and the testnet. This recreates the client, so every account is signed out.
+* @param use_testnet Whether to use the testnet
+**/
 #[uniffi::export]
 pub fn switch_network(use_testnet: bool) -> Vec<String> {
     NETWORK_CLIENT.switch_network(use_testnet);
     create_response_vector(false, format!("Switched to {} network", if use_testnet { "testnet" } else { "default" }))
 }
 
+/**
+* Use custom bootstrap nodes for the testnet, e.g. for a private testnet deployment. They apply to every
+* `switch_network(true)` until cleared, and right away when the testnet is in use, signing every account out.
+* @param nodes The bootstrap nodes, as `host:port`
+**/
+#[uniffi::export]
+pub fn set_testnet_bootstrap_nodes(nodes: Vec<String>) -> Vec<String> {
+    if nodes.is_empty() {
+        return create_response_vector(true, "At least one bootstrap node is required, use clear_testnet_bootstrap_nodes to revert to the defaults".to_string());
+    }
+    for node in &nodes {
+        let valid = node
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+        if !valid {
+            return create_response_vector(true, format!("Invalid bootstrap node {}, expected host:port", node));
+        }
+    }
+    NETWORK_CLIENT.set_testnet_bootstrap_nodes(nodes);
+    match serde_json::to_string(&NETWORK_CLIENT.get_testnet_bootstrap_nodes()) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Revert the testnet to its default bootstrap node (the local testnet)
+**/
+#[uniffi::export]
+pub fn clear_testnet_bootstrap_nodes() -> Vec<String> {
+    NETWORK_CLIENT.set_testnet_bootstrap_nodes(Vec::new());
+    create_response_vector(false, "Testnet bootstrap nodes cleared".to_string())
+}
+
+/**
+* Report runtime network activity, for monitoring
+* @returns A JSON object `{ "pending_requests", "cached_packets", "bytes_sent", "bytes_received" }`.
+* `pending_requests` counts the deduplicated requests in flight, and the byte counters the file content uploaded and
+* downloaded since start. Connection and DHT peer counts are not exposed by the underlying clients, so they aren't reported.
+**/
+#[uniffi::export]
+pub fn get_network_stats() -> Vec<String> {
+    let client = get_pubky_client();
+    let json_obj = json!({
+        "pending_requests": NETWORK_CLIENT.pending_request_count(),
+        "cached_packets": client.pkarr().cache().len(),
+        "bytes_sent": NETWORK_CLIENT.bytes_sent(),
+        "bytes_received": NETWORK_CLIENT.bytes_received(),
+    });
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Report aggregate performance counters since start or the last `reset_performance_stats`: DHT lookups made
+* by `resolve` (latency and whether a fresh cached packet answered them) and DHT publishes
+* @returns A JSON object `{ "resolve_count", "average_resolve_latency_ms", "cache_hits", "cache_misses", "cache_hit_ratio",
+* "publish_count", "publish_failures" }`; the average and ratio are null until there is something to average
+**/
+#[uniffi::export]
+pub fn performance_stats() -> Vec<String> {
+    match serde_json::to_string(&NETWORK_CLIENT.performance_stats().to_json()) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Reset the counters reported by `performance_stats`
+**/
+#[uniffi::export]
+pub fn reset_performance_stats() -> Vec<String> {
+    NETWORK_CLIENT.performance_stats().reset();
+    create_response_vector(false, "Performance stats reset".to_string())
+}
+
+/**
+* Set how long `resolve` serves a signed packet from the cache, counted from when it was cached,
+* before the DHT is queried again. The packet's own TTL can make that sooner.
+* @param secs The maximum cache TTL in seconds, between 30 and 86400 (default 3600)
+**/
+#[uniffi::export]
+pub fn set_max_resolve_cache_ttl(secs: u32) -> Vec<String> {
+    if !(30..=86400).contains(&secs) {
+        return create_response_vector(true, "secs must be between 30 and 86400".to_string());
+    }
+    NETWORK_CLIENT.set_max_resolve_cache_ttl(secs);
+    create_response_vector(false, secs.to_string())
+}
+
+/**
+* Get how long resolved signed packets are served from the cache, in seconds
+**/
+#[uniffi::export]
+pub fn get_max_resolve_cache_ttl() -> Vec<String> {
+    create_response_vector(false, NETWORK_CLIENT.get_max_resolve_cache_ttl().to_string())
+}
+
+/// Deadline of each relay tried by `resolve` when a relay priority is set, in milliseconds
+pub const RELAY_PRIORITY_TIMEOUT_MS: u64 = 3_000;
+
+/// Largest encoded DNS packet accepted in a pkarr signed packet
+pub const MAX_DNS_PACKET_SIZE: usize = 1000;
+
+/// How far in the future a caller supplied packet timestamp may be, in microseconds (1 hour)
+pub const MAX_FUTURE_TIMESTAMP_MICROS: u64 = 60 * 60 * 1_000_000;
+
+// Worker thread count set by `configure_runtime`, read once when the runtime is created
+static RUNTIME_WORKER_THREADS: OnceCell<usize> = OnceCell::new();
+
 static TOKIO_RUNTIME: Lazy<Arc<Runtime>> = Lazy::new(|| {
+    let runtime = match RUNTIME_WORKER_THREADS.get() {
+        Some(worker_threads) => tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(*worker_threads)
+            .enable_all()
+            .build(),
+        None => Runtime::new(),
+    };
     Arc::new(
-        Runtime::new().expect("Failed to create Tokio runtime")
+        runtime.expect("Failed to create Tokio runtime")
     )
 });
 
+/**
+* Set the number of worker threads of the Tokio runtime, e.g. to limit memory use on mobile devices.
+* Must be called before any other function: once the runtime has been created it can't be changed,
+* and this returns an error without affecting it.
+* @param worker_threads The number of worker threads, between 1 and 16
+**/
+#[uniffi::export]
+pub fn configure_runtime(worker_threads: u32) -> Vec<String> {
+    if !(1..=16).contains(&worker_threads) {
+        return create_response_vector(true, "worker_threads must be between 1 and 16".to_string());
+    }
+    if Lazy::get(&TOKIO_RUNTIME).is_some() {
+        return create_response_vector(true, "Runtime already started".to_string());
+    }
+    if RUNTIME_WORKER_THREADS.set(worker_threads as usize).is_err() {
+        return create_response_vector(true, "Runtime already configured".to_string());
+    }
+    create_response_vector(false, format!("Runtime configured with {} worker threads", worker_threads))
+}
+
 // Define the EventListener trait
 #[uniffi::export(callback_interface)]
 pub trait EventListener: Send + Sync {
@@ -113,6 +724,108 @@ impl EventNotifier {
     }
 }
 
+#[derive(Debug, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum AccountError {
+    InvalidSecretKey { message: String },
+    InvalidMnemonic { message: String },
+}
+
+impl std::fmt::Display for AccountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountError::InvalidSecretKey { message } => write!(f, "Invalid secret key: {}", message),
+            AccountError::InvalidMnemonic { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AccountError {}
+
+/**
+* A user's keypair and homeserver session, so the secret key (or mnemonic) is handed over once instead of to every call.
+* The methods wrap the free functions of the same name and return the same responses. `put`, `get` and `list`
+* take either a full URL or a path such as `/pub/example.com/file`, relative to the account's own `pubky://` root,
+* and `put` and `list` sign in first unless the client already holds a session of the account allowing the call.
+**/
+#[derive(uniffi::Object)]
+pub struct Account {
+    keypair: Keypair,
+}
+
+#[uniffi::export]
+impl Account {
+    #[uniffi::constructor]
+    pub fn from_secret_key(secret_key: String) -> Result<Arc<Self>, AccountError> {
+        let keypair = get_keypair_from_secret_key(&secret_key)
+            .map_err(|message| AccountError::InvalidSecretKey { message })?;
+        Ok(Arc::new(Self { keypair }))
+    }
+
+    // Derived as by `keypair_from_mnemonic`
+    #[uniffi::constructor]
+    pub fn from_mnemonic(mnemonic: String) -> Result<Arc<Self>, AccountError> {
+        let keypair = keypair_from_mnemonic(&mnemonic)
+            .map_err(|message| AccountError::InvalidMnemonic { message })?;
+        Ok(Arc::new(Self { keypair }))
+    }
+
+    pub fn pubky(&self) -> String {
+        self.keypair.public_key().to_string()
+    }
+
+    pub fn sign_in(&self) -> Vec<String> {
+        sign_in(get_secret_key_from_keypair(&self.keypair))
+    }
+
+    pub fn sign_out(&self) -> Vec<String> {
+        sign_out(get_secret_key_from_keypair(&self.keypair))
+    }
+
+    pub fn put(&self, path_or_url: String, content: String) -> Vec<String> {
+        let url = self.url(&path_or_url);
+        if let Err(result) = self.ensure_signed_in(&url, Action::Write) {
+            return result;
+        }
+        put(url, content)
+    }
+
+    pub fn get(&self, path_or_url: String) -> Vec<String> {
+        get(self.url(&path_or_url))
+    }
+
+    pub fn list(&self, path_or_url: String) -> Vec<String> {
+        let url = self.url(&path_or_url);
+        if let Err(result) = self.ensure_signed_in(&url, Action::Read) {
+            return result;
+        }
+        list(url)
+    }
+}
+
+impl Account {
+    // Resolve a path against the account's root, leaving full URLs as they are
+    fn url(&self, path_or_url: &str) -> String {
+        match path_or_url.strip_prefix('/') {
+            Some(path) => format!("pubky://{}/{}", self.pubky(), path),
+            None => path_or_url.to_string(),
+        }
+    }
+
+    // Reuse the account's session when it allows `action` on the URL, otherwise sign in for a full session
+    fn ensure_signed_in(&self, url: &str, action: Action) -> Result<(), Vec<String>> {
+        let path = Url::parse(url).map(|url| url.path().to_string()).unwrap_or_default();
+        if account_has_capability(&self.pubky(), &path, action) {
+            return Ok(());
+        }
+        let result = self.sign_in();
+        if result[0] == "error" {
+            return Err(result);
+        }
+        Ok(())
+    }
+}
+
 static EVENT_NOTIFIER: Lazy<Arc<EventNotifier>> = Lazy::new(|| {
     Arc::new(EventNotifier::new())
 });
@@ -128,33 +841,337 @@ pub fn remove_event_listener() {
 }
 
 pub fn start_internal_event_loop() {
-    let event_notifier = EVENT_NOTIFIER.clone();
-    let runtime = TOKIO_RUNTIME.clone();
-    runtime.spawn(async move {
-        let mut interval = time::interval(Duration::from_secs(2));
-        loop {
-            interval.tick().await;
-            event_notifier.as_ref().notify_event("Internal event triggered".to_string());
-        }
-    });
+    NETWORK_CLIENT.start_event_loop(EVENT_NOTIFIER.clone(), &TOKIO_RUNTIME);
 }
 
+/**
+* Start the internal event loop, which notifies the event listener periodically
+* @returns An error if the loop is already running
+**/
 #[uniffi::export]
-pub fn delete_file(url: String) -> Vec<String> {
-    let runtime = TOKIO_RUNTIME.clone();
-    runtime.block_on(async {
+pub fn start_event_loop() -> Vec<String> {
+    if !NETWORK_CLIENT.start_event_loop(EVENT_NOTIFIER.clone(), &TOKIO_RUNTIME) {
+        return create_response_vector(true, "Event loop already running".to_string());
+    }
+    create_response_vector(false, "Event loop started".to_string())
+}
+
+/**
+* Stop the internal event loop
+* @returns An error if the loop is not running
+**/
+#[uniffi::export]
+pub fn stop_event_loop() -> Vec<String> {
+    if !NETWORK_CLIENT.stop_event_loop() {
+        return create_response_vector(true, "Event loop not running".to_string());
+    }
+    create_response_vector(false, "Event loop stopped".to_string())
+}
+
+/**
+* Set the period of the internal event loop. A running loop picks up the new period immediately.
+* @param secs The period in seconds, between 1 and 3600 (default 2)
+**/
+#[uniffi::export]
+pub fn set_event_loop_interval(secs: u32) -> Vec<String> {
+    if !(1..=3600).contains(&secs) {
+        return create_response_vector(true, "secs must be between 1 and 3600".to_string());
+    }
+    NETWORK_CLIENT.set_event_loop_interval(Duration::from_secs(secs as u64));
+    create_response_vector(false, secs.to_string())
+}
+
+// The circuit breaker endpoint of a URL: its host, i.e. the public key for pubky:// URLs
+fn circuit_endpoint(url: &Url) -> String {
+    url.host_str().unwrap_or_default().to_string()
+}
+
+// Parse a URL to read or write data at: trailing slashes trimmed, only pubky:// and https:// accepted,
+// and https:// URLs with a public key host rewritten as pubky:// URLs
+fn parse_data_url(url: &str) -> Result<Url, String> {
+    let parsed_url = Url::parse(url.trim_end_matches('/')).map_err(|_| "Failed to parse URL".to_string())?;
+    validate_data_url_scheme(&parsed_url)?;
+    Ok(normalize_data_url(parsed_url))
+}
+
+// Why a request made through `get_data` or `put_data` failed
+enum DataRequestError {
+    // The endpoint's circuit is open, holding the `circuit_open` message
+    CircuitOpen(String),
+    Failed(pubky::Error),
+}
+
+impl std::fmt::Display for DataRequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataRequestError::CircuitOpen(message) => f.write_str(message),
+            DataRequestError::Failed(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+// Get the content at a URL through the circuit breaker of its endpoint, counting the bytes received
+async fn get_data(client: &PubkyClient, url: Url) -> Result<Option<Bytes>, DataRequestError> {
+    let endpoint = circuit_endpoint(&url);
+    NETWORK_CLIENT.circuit_breaker().check(&endpoint).map_err(DataRequestError::CircuitOpen)?;
+    let result = client.get(url).await;
+    NETWORK_CLIENT.circuit_breaker().record(&endpoint, &result);
+    let bytes = result.map_err(DataRequestError::Failed)?;
+    if let Some(bytes) = &bytes {
+        NETWORK_CLIENT.record_bytes_received(bytes.len());
+    }
+    Ok(bytes)
+}
+
+// Put content at a URL through the circuit breaker of its endpoint, counting the bytes sent
+async fn put_data(client: &PubkyClient, url: Url, content: &[u8]) -> Result<(), DataRequestError> {
+    let endpoint = circuit_endpoint(&url);
+    NETWORK_CLIENT.circuit_breaker().check(&endpoint).map_err(DataRequestError::CircuitOpen)?;
+    put_checked_data(client, url, &endpoint, content).await.map_err(DataRequestError::Failed)
+}
+
+// Read the body of a data request made with reqwest rather than `PubkyClient`, counting the bytes received
+async fn read_data_response(response: reqwest::Response) -> Result<Bytes, String> {
+    let bytes = response.bytes().await.map_err(|e| format!("Failed to read response body: {}", e))?;
+    NETWORK_CLIENT.record_bytes_received(bytes.len());
+    Ok(bytes)
+}
+
+// Like `put_data`, for callers that already checked the circuit of the endpoint for this request
+async fn put_checked_data(client: &PubkyClient, url: Url, endpoint: &str, content: &[u8]) -> Result<(), pubky::Error> {
+    let url_string = url.to_string();
+    let result = client.put(url, content).await;
+    NETWORK_CLIENT.circuit_breaker().record(endpoint, &result);
+    result?;
+    NETWORK_CLIENT.record_bytes_sent(content.len());
+    // A queued older write to the same URL must not overwrite this one later
+    NETWORK_CLIENT.remove_queued_put(&url_string);
+    Ok(())
+}
+
+// List a directory through the circuit breaker of its endpoint, one page of at most `limit` entries
+// after `cursor` when a limit is given
+async fn list_data(client: &PubkyClient, url: Url, limit: Option<u16>, cursor: Option<&str>) -> Result<Vec<String>, String> {
+    let endpoint = circuit_endpoint(&url);
+    let list_builder = client.list(url).map_err(|error| format!("Failed to list: {}", error))?;
+    let list_builder = match limit {
+        Some(limit) => list_builder.limit(limit),
+        None => list_builder,
+    };
+    let list_builder = match cursor {
+        Some(cursor) => list_builder.cursor(cursor),
+        None => list_builder,
+    };
+    NETWORK_CLIENT.circuit_breaker().check(&endpoint)?;
+    let result = list_builder.send().await;
+    NETWORK_CLIENT.circuit_breaker().record(&endpoint, &result);
+    result.map_err(|error| format!("Failed to send list request: {}", error))
+}
+
+/**
+* Get the circuit breaker state of an endpoint. `put`, `get`, `list` and `delete_file` fail fast with a
+* `circuit_open` error while an endpoint's circuit is open. A half-open circuit lets a single trial request through.
+* @param endpoint The host of the URLs, i.e. the public key for pubky:// URLs
+* @returns A JSON object `{ "endpoint", "state", "consecutive_failures" }`, `state` being "closed", "open" or "half_open"
+**/
+#[uniffi::export]
+pub fn get_circuit_breaker_state(endpoint: String) -> Vec<String> {
+    let circuit_breaker = NETWORK_CLIENT.circuit_breaker();
+    let json_obj = json!({
+        "endpoint": endpoint,
+        "state": circuit_breaker.state(&endpoint).as_str(),
+        "consecutive_failures": circuit_breaker.consecutive_failures(&endpoint),
+    });
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Close an endpoint's circuit, letting requests through again immediately
+* @param endpoint The host of the URLs, i.e. the public key for pubky:// URLs
+**/
+#[uniffi::export]
+pub fn reset_circuit_breaker(endpoint: String) -> Vec<String> {
+    NETWORK_CLIENT.circuit_breaker().reset(&endpoint);
+    create_response_vector(false, endpoint)
+}
+
+/**
+* Configure when circuits open and for how long
+* @param failure_threshold Consecutive failures that open a circuit, at least 1 (default 5)
+* @param reset_timeout_secs How long an open circuit rejects requests (default 60)
+**/
+#[uniffi::export]
+pub fn configure_circuit_breaker(failure_threshold: u32, reset_timeout_secs: u64) -> Vec<String> {
+    NETWORK_CLIENT.circuit_breaker().configure(failure_threshold, Duration::from_secs(reset_timeout_secs));
+    create_response_vector(false, "Circuit breaker configured".to_string())
+}
+
+#[uniffi::export]
+pub fn delete_file(url: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
         let client = get_pubky_client();
         let parsed_url = match Url::parse(&url) {
             Ok(url) => url,
             Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
         };
-        match client.delete(parsed_url).await {
+        let endpoint = circuit_endpoint(&parsed_url);
+        if let Err(error) = NETWORK_CLIENT.circuit_breaker().check(&endpoint) {
+            return create_response_vector(true, error);
+        }
+        let result = client.delete(parsed_url).await;
+        NETWORK_CLIENT.circuit_breaker().record(&endpoint, &result);
+        match result {
             Ok(_) => create_response_vector(false, "Deleted successfully".to_string()),
             Err(error) => create_response_vector(true, format!("Failed to delete: {}", error)),
         }
     })
 }
 
+/**
+* Delete a file recoverably: it is moved to `{directory}/_trash/{timestamp_ms}/{file name}` and permanently deleted
+* after `retain_secs`, unless restored with `restore_from_trash` first. The permanent deletion runs in the background
+* of this process, so a trash copy outlives the retention period if the app exits before it.
+* @param url The pubky:// URL of the file
+* @param retain_secs How long the file can be restored for
+* @returns The URL of the trash copy
+**/
+#[uniffi::export]
+pub fn soft_delete_file(url: String, retain_secs: u32) -> Vec<String> {
+    let url = url.trim_end_matches('/').to_string();
+    let deleted_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0);
+    let trash_url = match trash_url(&url, deleted_at_ms) {
+        Some(trash_url) => trash_url,
+        None => return create_response_vector(true, format!("Not a file URL: {}", url)),
+    };
+    if url.contains(&format!("/{}/", TRASH_DIRECTORY)) {
+        return create_response_vector(true, "Files in the trash can't be soft deleted".to_string());
+    }
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let (parsed_url, parsed_trash_url) = match (Url::parse(&url), Url::parse(&trash_url)) {
+            (Ok(parsed_url), Ok(parsed_trash_url)) => (parsed_url, parsed_trash_url),
+            _ => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let content = match get_data(&client, parsed_url).await {
+            Ok(Some(content)) => content,
+            Ok(None) => return create_response_vector(true, format!("No file found at {}", url)),
+            Err(e) => return create_response_vector(true, format!("Failed to get: {}", e)),
+        };
+        if let Err(e) = put_data(&client, parsed_trash_url, &content).await {
+            return create_response_vector(true, format!("Failed to copy to trash: {}", e));
+        }
+        if let Err(e) = client.delete(url.as_str()).await {
+            return create_response_vector(true, format!("Failed to delete: {}", e));
+        }
+
+        let expiring_url = trash_url.clone();
+        TOKIO_RUNTIME.spawn(async move {
+            time::sleep(Duration::from_secs(retain_secs as u64)).await;
+            let _ = get_pubky_client().delete(expiring_url.as_str()).await;
+        });
+        create_response_vector(false, trash_url)
+    })
+}
+
+// The trash copies of the files in a directory, as (deleted at in ms, trash URL, original URL)
+async fn list_trash_entries(client: &PubkyClient, directory: &str) -> Result<Vec<(u64, String, String)>, String> {
+    let trash_directory = Url::parse(&format!("{}/{}/", directory.trim_end_matches('/'), TRASH_DIRECTORY))
+        .map_err(|_| "Failed to parse URL".to_string())?;
+    let urls = list_all(client, &trash_directory).await?;
+    Ok(urls
+        .into_iter()
+        .filter_map(|url| {
+            let (deleted_at_ms, original_url) = parse_trash_url(directory, &url)?;
+            Some((deleted_at_ms, url, original_url))
+        })
+        .collect())
+}
+
+/**
+* Restore the most recent trash copy of a soft deleted file to its original URL, removing it from the trash
+* @param original_url The pubky:// URL the file was deleted from
+* @returns The URL of the trash copy that was restored
+**/
+#[uniffi::export]
+pub fn restore_from_trash(original_url: String) -> Vec<String> {
+    let original_url = original_url.trim_end_matches('/').to_string();
+    let directory = match split_file_url(&original_url) {
+        Some((directory, _)) => directory.to_string(),
+        None => return create_response_vector(true, format!("Not a file URL: {}", original_url)),
+    };
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let entries = match list_trash_entries(&client, &directory).await {
+            Ok(entries) => entries,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let trash_url = match entries
+            .into_iter()
+            .filter(|(_, _, url)| *url == original_url)
+            .max_by_key(|(deleted_at_ms, _, _)| *deleted_at_ms)
+        {
+            Some((_, trash_url, _)) => trash_url,
+            None => return create_response_vector(true, format!("No trash copy found for {}", original_url)),
+        };
+
+        let (parsed_trash_url, parsed_original_url) = match (Url::parse(&trash_url), Url::parse(&original_url)) {
+            (Ok(parsed_trash_url), Ok(parsed_original_url)) => (parsed_trash_url, parsed_original_url),
+            _ => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let content = match get_data(&client, parsed_trash_url).await {
+            Ok(Some(content)) => content,
+            Ok(None) => return create_response_vector(true, format!("Trash copy {} no longer exists", trash_url)),
+            Err(e) => return create_response_vector(true, format!("Failed to get: {}", e)),
+        };
+        if let Err(e) = put_data(&client, parsed_original_url, &content).await {
+            return create_response_vector(true, format!("Failed to restore: {}", e));
+        }
+        if let Err(e) = client.delete(trash_url.as_str()).await {
+            return create_response_vector(true, format!("Restored, but failed to remove the trash copy: {}", e));
+        }
+        create_response_vector(false, trash_url)
+    })
+}
+
+/**
+* List the soft deleted files of a directory, see `soft_delete_file`
+* @param base_url The pubky:// URL of the directory the files were deleted from
+* @returns A JSON array of `{ "url", "original_url", "deleted_at" }`, newest first, `deleted_at` in milliseconds since the Unix epoch
+**/
+#[uniffi::export]
+pub fn list_trash(base_url: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let mut entries = match list_trash_entries(&client, &base_url).await {
+            Ok(entries) => entries,
+            Err(error) => return create_response_vector(true, error),
+        };
+        entries.sort_by_key(|(deleted_at_ms, _, _)| std::cmp::Reverse(*deleted_at_ms));
+        let json_entries: Vec<serde_json::Value> = entries
+            .into_iter()
+            .map(|(deleted_at_ms, url, original_url)| json!({
+                "url": url,
+                "original_url": original_url,
+                "deleted_at": deleted_at_ms,
+            }))
+            .collect();
+        match serde_json::to_string(&json_entries) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
 #[uniffi::export]
 pub fn session(pubky: String) -> Vec<String> {
     let runtime = TOKIO_RUNTIME.clone();
@@ -177,6 +1194,155 @@ pub fn session(pubky: String) -> Vec<String> {
     })
 }
 
+/**
+* Get the versions of this library and its core dependencies, for bug reports and compatibility checks
+* @returns A JSON object `{ "ffi_version", "pubky", "pkarr", "pubky_common", "uniffi" }`
+**/
+#[uniffi::export]
+pub fn version_info() -> Vec<String> {
+    let json_obj = json!({
+        "ffi_version": env!("CARGO_PKG_VERSION"),
+        "pubky": env!("PUBKY_VERSION"),
+        "pkarr": env!("PKARR_VERSION"),
+        "pubky_common": env!("PUBKY_COMMON_VERSION"),
+        "uniffi": env!("UNIFFI_VERSION"),
+    });
+
+    let json_str = match serde_json::to_string(&json_obj) {
+        Ok(json) => json,
+        Err(e) => return create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    };
+    create_response_vector(false, json_str)
+}
+
+/**
+* Get the DNS record types whose data is decoded in resolved records
+* @returns A JSON array of record type strings
+**/
+#[uniffi::export]
+pub fn get_dns_record_types() -> Vec<String> {
+    match serde_json::to_string(&SUPPORTED_RDATA_TYPES) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Name a record type unknown to the DNS library, so resolved records of that type are reported under
+* this name instead of "UNKNOWN". Their data is still returned base64 encoded.
+* @param type_number The numeric record type
+* @param type_name The name to report, uppercased
+**/
+#[uniffi::export]
+pub fn add_custom_dns_rdata_type(type_number: u16, type_name: String) -> Vec<String> {
+    match DNS_TYPE_REGISTRY.register(type_number, &type_name) {
+        Ok(()) => create_response_vector(false, type_name.to_ascii_uppercase()),
+        Err(error) => create_response_vector(true, error),
+    }
+}
+
+/**
+* List the record types registered with `add_custom_dns_rdata_type`
+* @returns A JSON array of `{ "type_number", "type_name" }`, sorted by number
+**/
+#[uniffi::export]
+pub fn get_registered_dns_types() -> Vec<String> {
+    let types: Vec<serde_json::Value> = DNS_TYPE_REGISTRY.all()
+        .into_iter()
+        .map(|(type_number, type_name)| json!({ "type_number": type_number, "type_name": type_name }))
+        .collect();
+    match serde_json::to_string(&types) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Get the number of writes waiting in the offline queue, i.e. `put` calls that couldn't reach the homeserver
+* @returns The count as a JSON integer
+**/
+#[uniffi::export]
+pub fn get_pending_queue_count() -> Vec<String> {
+    create_response_vector(false, NETWORK_CLIENT.get_queued_puts().len().to_string())
+}
+
+// The `get_queue_summary` JSON of the queued writes
+pub fn queue_summary_json(queued_puts: &[QueuedPut]) -> serde_json::Value {
+    json!({
+        "count": queued_puts.len(),
+        "total_bytes": queued_puts.iter().map(|queued| queued.content.len()).sum::<usize>(),
+        "oldest_queued_at_unix": queued_puts.iter().map(|queued| queued.queued_at_unix).min(),
+        "newest_queued_at_unix": queued_puts.iter().map(|queued| queued.queued_at_unix).max(),
+    })
+}
+
+/**
+* Summarize the offline queue without flushing it, see `get_pending_queue_count`
+* @returns A JSON object `{ "count", "total_bytes", "oldest_queued_at_unix", "newest_queued_at_unix" }`,
+* where the timestamps are null when the queue is empty
+**/
+#[uniffi::export]
+pub fn get_queue_summary() -> Vec<String> {
+    let json_obj = queue_summary_json(&NETWORK_CLIENT.get_queued_puts());
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+// Serializes flushing the offline queue, so no queued write is sent twice at once
+static QUEUE_FLUSH: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/**
+* Write the writes waiting in the offline queue, in the order they were queued. Stops at the first write that still
+* can't reach its homeserver, which stays queued with those after it; writes failing for other reasons are dropped.
+* Call it when connectivity is back.
+* @returns A JSON object `{ "written", "dropped", "remaining" }`
+**/
+#[uniffi::export]
+pub fn flush_queue() -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let _flush = QUEUE_FLUSH.lock().await;
+        let client = get_pubky_client();
+        let (mut written, mut dropped) = (0, 0);
+        while let Some(queued_put) = NETWORK_CLIENT.next_queued_put() {
+            let Ok(url) = Url::parse(&queued_put.url) else {
+                NETWORK_CLIENT.remove_queued_put(&queued_put.url);
+                dropped += 1;
+                continue;
+            };
+            match put_data(&client, url, queued_put.content.as_bytes()).await {
+                Ok(()) => written += 1,
+                Err(DataRequestError::Failed(error)) if !is_connectivity_error(&error) => {
+                    NETWORK_CLIENT.remove_queued_put(&queued_put.url);
+                    dropped += 1;
+                },
+                Err(_) => break,
+            }
+        }
+
+        let json_obj = json!({
+            "written": written,
+            "dropped": dropped,
+            "remaining": NETWORK_CLIENT.get_queued_puts().len(),
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Discard all writes waiting in the offline queue
+* @returns The number of discarded writes
+**/
+#[uniffi::export]
+pub fn clear_queue() -> Vec<String> {
+    create_response_vector(false, NETWORK_CLIENT.clear_queued_puts().to_string())
+}
+
 #[uniffi::export]
 pub fn generate_secret_key() -> Vec<String> {
     let keypair = generate_keypair();
@@ -197,6 +1363,30 @@ pub fn generate_secret_key() -> Vec<String> {
     create_response_vector(false, json_str)
 }
 
+/**
+* Derive a keypair deterministically from seed bytes, for reproducible tests.
+* A 32-byte seed is used as the secret key, any other length is first hashed to 32 bytes with SHA-256.
+* @param seed The non-empty seed
+* @returns A JSON object `{ "secret_key", "public_key", "uri" }`, like `generate_secret_key`
+**/
+#[uniffi::export]
+pub fn keypair_from_seed_bytes(seed: Vec<u8>) -> Vec<String> {
+    if seed.is_empty() {
+        return create_response_vector(true, "Seed must not be empty".to_string());
+    }
+    let keypair = keypair_from_seed(&seed);
+    let public_key = keypair.public_key();
+    let json_obj = json!({
+        "secret_key": get_secret_key_from_keypair(&keypair),
+        "public_key": public_key.to_string(),
+        "uri": public_key.to_uri_string(),
+    });
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
 #[uniffi::export]
 pub fn get_public_key_from_secret_key(secret_key: String) -> Vec<String> {
     let keypair = match get_keypair_from_secret_key(&secret_key) {
@@ -217,61 +1407,336 @@ pub fn get_public_key_from_secret_key(secret_key: String) -> Vec<String> {
     create_response_vector(false, json_str)
 }
 
+/**
+* Get the URI of a public key, without the JSON of `get_public_key_from_secret_key`
+* @param public_key_zbase32 The z-base32 encoded public key
+* @returns The URI, the same as the `uri` field of `get_public_key_from_secret_key` (`pk:<public key>`)
+**/
 #[uniffi::export]
-pub fn publish_https(record_name: String, target: String, secret_key: String) -> Vec<String> {
-    let runtime = TOKIO_RUNTIME.clone();
-    runtime.block_on(async {
-        let client = get_pubky_client();
-
-        let keypair = match get_keypair_from_secret_key(&secret_key) {
-            Ok(keypair) => keypair,
-            Err(error) => return create_response_vector(true, error),
-        };
-
-        // Create SVCB record with the target domain
-        let target = match target.as_str().try_into() {
-            Ok(target) => target,
-            Err(e) => return create_response_vector(true, format!("Invalid target: {}", e)),
-        };
-        let svcb = SVCB::new(0, target);
+pub fn get_pubky_uri_from_public_key(public_key_zbase32: String) -> Vec<String> {
+    match PublicKey::try_from(public_key_zbase32.as_str()) {
+        Ok(public_key) => create_response_vector(false, public_key.to_uri_string()),
+        Err(e) => create_response_vector(true, format!("Invalid public key: {}", e)),
+    }
+}
 
-        // Create HTTPS record
-        let https_record = HTTPS(svcb);
+/**
+* Sign several payloads with the same key, decoding the secret key only once
+* @param payloads_json A JSON array of hex encoded payloads
+* @param secret_key The hex encoded secret key
+* @returns A JSON array of hex encoded Ed25519 signatures, in the order of the payloads
+**/
+#[uniffi::export]
+pub fn batch_sign_bytes(payloads_json: String, secret_key: String) -> Vec<String> {
+    let payloads: Vec<String> = match serde_json::from_str(&payloads_json) {
+        Ok(payloads) => payloads,
+        Err(e) => return create_response_vector(true, format!("Failed to parse payloads JSON: {}", e)),
+    };
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
 
-        // Create DNS packet
-        let mut packet = Packet::new_reply(0);
-        let dns_name = match dns::Name::new(&record_name) {
-            Ok(name) => name,
-            Err(e) => return create_response_vector(true, format!("Invalid DNS name: {}", e)),
+    let mut signatures = Vec::with_capacity(payloads.len());
+    for (index, payload) in payloads.iter().enumerate() {
+        let bytes = match hex::decode(payload) {
+            Ok(bytes) => bytes,
+            Err(e) => return create_response_vector(true, format!("Failed to decode payload {}: {}", index, e)),
         };
+        signatures.push(hex::encode(keypair.sign(&bytes).to_bytes()));
+    }
 
-        packet.answers.push(ResourceRecord::new(
-            dns_name,
-            dns::CLASS::IN,
-            3600, // TTL in seconds
-            dns::rdata::RData::HTTPS(https_record),
-        ));
+    match serde_json::to_string(&signatures) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
 
-        let signed_packet = match SignedPacket::from_packet(&keypair, &packet) {
-            Ok(signed_packet) => signed_packet,
-            Err(e) => return create_response_vector(true, format!("Failed to create signed packet: {}", e)),
-        };
+// A one-time keypair's secret key, zeroized when the entry is dropped
+struct EphemeralKeyEntry {
+    secret_key: [u8; 32],
+    expires_at_unix: u64,
+}
 
-        match client.pkarr().publish(&signed_packet).await {
-            Ok(()) => create_response_vector(false, keypair.public_key().to_string()),
-            Err(e) => create_response_vector(true, format!("Failed to publish: {}", e)),
-        }
-    })
+impl Drop for EphemeralKeyEntry {
+    fn drop(&mut self) {
+        self.secret_key.zeroize();
+    }
 }
 
+// Ephemeral keypairs that have neither expired nor been used, keyed by public key
+static EPHEMERAL_KEYS: Lazy<Mutex<HashMap<String, EphemeralKeyEntry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/**
+* Generate a keypair for a one-time flow. Its secret key is kept in memory until it is retrieved
+* with `use_ephemeral_keypair` or `ttl_seconds` have passed, whichever comes first, then zeroized.
+* @param ttl_seconds How long the secret key can be retrieved for
+* @returns A JSON object `{ "public_key", "uri", "expires_at" }`
+**/
 #[uniffi::export]
-pub fn resolve_https(public_key: String) -> Vec<String> {
-    let runtime = TOKIO_RUNTIME.clone();
-    runtime.block_on(async {
-        let public_key = match public_key.as_str().try_into() {
-            Ok(key) => key,
-            Err(e) => return create_response_vector(true, format!("Invalid public key: {}", e)),
-        };
+pub fn generate_ephemeral_keypair(ttl_seconds: u64) -> Vec<String> {
+    let keypair = generate_keypair();
+    let public_key = keypair.public_key().to_string();
+    let expires_at_unix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+        .saturating_add(ttl_seconds);
+    EPHEMERAL_KEYS.lock().unwrap().insert(public_key.clone(), EphemeralKeyEntry {
+        secret_key: keypair.secret_key(),
+        expires_at_unix,
+    });
+
+    let expiring_key = public_key.clone();
+    TOKIO_RUNTIME.spawn(async move {
+        time::sleep(Duration::from_secs(ttl_seconds)).await;
+        EPHEMERAL_KEYS.lock().unwrap().remove(&expiring_key);
+    });
+
+    let json_obj = json!({
+        "public_key": public_key,
+        "uri": keypair.public_key().to_uri_string(),
+        "expires_at": expires_at_unix,
+    });
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Retrieve the secret key of an ephemeral keypair. It is removed on retrieval, so this succeeds at most once.
+* @param public_key The public key returned by `generate_ephemeral_keypair`
+* @returns The hex encoded secret key
+**/
+#[uniffi::export]
+pub fn use_ephemeral_keypair(public_key: String) -> Vec<String> {
+    let entry = EPHEMERAL_KEYS.lock().unwrap().remove(&public_key);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    match entry {
+        Some(entry) if entry.expires_at_unix > now => create_response_vector(false, hex::encode(entry.secret_key)),
+        _ => create_response_vector(true, "No ephemeral keypair found, it may have expired or been used".to_string()),
+    }
+}
+
+/**
+* Convert a secret key between encodings
+* @param secret_key The encoded secret key
+* @param from_format The encoding of `secret_key`: "hex", "base64" or "base58"
+* @param to_format The encoding to convert to: "hex", "base64" or "base58"
+* @returns The re-encoded secret key
+**/
+#[uniffi::export]
+pub fn convert_secret_key_format(secret_key: String, from_format: String, to_format: String) -> Vec<String> {
+    let bytes = match decode_secret_key(&secret_key, &from_format) {
+        Ok(bytes) => bytes,
+        Err(error) => return create_response_vector(true, error),
+    };
+    match encode_secret_key(&bytes, &to_format) {
+        Ok(encoded) => create_response_vector(false, encoded),
+        Err(error) => create_response_vector(true, error),
+    }
+}
+
+/**
+* Derive a child key from a master secret key, for managing several identities from one root key
+* @param master_secret_key The hex encoded master secret key, used as the SLIP-10 seed
+* @param index The account index, lower than 2^31
+* @returns A JSON object `{ "secret_key", "public_key", "uri", "derivation_path" }` for the key at `m/44'/{index}'`
+**/
+#[uniffi::export]
+pub fn derive_key_from_master(master_secret_key: String, index: u32) -> Vec<String> {
+    let master = match get_keypair_from_secret_key(&master_secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let child = match derive_child_keypair(&master, index) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let public_key = child.public_key();
+    let json_obj = json!({
+        "secret_key": get_secret_key_from_keypair(&child),
+        "public_key": public_key.to_string(),
+        "uri": public_key.to_uri_string(),
+        "derivation_path": format!("m/44'/{}'", index),
+    });
+
+    let json_str = match serde_json::to_string(&json_obj) {
+        Ok(json) => json,
+        Err(e) => return create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    };
+    create_response_vector(false, json_str)
+}
+
+/**
+* Extract the public key from a pubky:// URL
+* @param url A URL such as `pubky://<public key>/pub/example.com/file`
+* @returns The z-base32 encoded public key
+**/
+#[uniffi::export]
+pub fn extract_pubky_from_url(url: String) -> Vec<String> {
+    let parsed_url = match Url::parse(&url) {
+        Ok(url) => url,
+        Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+    };
+    if parsed_url.scheme() != "pubky" {
+        return create_response_vector(true, format!("Not a pubky URL: {}", url));
+    }
+    let host = match parsed_url.host_str() {
+        Some(host) => host,
+        None => return create_response_vector(true, "Missing Pubky URL host".to_string()),
+    };
+    match PublicKey::try_from(host) {
+        Ok(public_key) => create_response_vector(false, public_key.to_string()),
+        Err(e) => create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+    }
+}
+
+/**
+* Compare two public keys by their underlying 32 bytes
+* @param a The first public key, in any textual form accepted by `PublicKey::try_from`
+* @param b The second public key, in any textual form accepted by `PublicKey::try_from`
+* @returns A JSON object `{ "equal": bool }` in the data element
+**/
+#[uniffi::export]
+pub fn pubky_equals(a: String, b: String) -> Vec<String> {
+    let first = match PublicKey::try_from(a) {
+        Ok(key) => key,
+        Err(error) => return create_response_vector(true, format!("Invalid public key: {}", error)),
+    };
+    let second = match PublicKey::try_from(b) {
+        Ok(key) => key,
+        Err(error) => return create_response_vector(true, format!("Invalid public key: {}", error)),
+    };
+    let json_obj = json!({
+        "equal": first.as_bytes() == second.as_bytes(),
+    });
+
+    let json_str = match serde_json::to_string(&json_obj) {
+        Ok(json) => json,
+        Err(e) => return create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    };
+    create_response_vector(false, json_str)
+}
+
+/**
+* Get the DHT key pkarr looks up for a public key: the SHA-1 hash of the key,
+* used as the BEP44 mutable item target (no salt)
+* @param public_key The z-base32 encoded public key
+* @returns The 20-byte DHT key, hex encoded
+**/
+#[uniffi::export]
+pub fn dht_key(public_key: String) -> Vec<String> {
+    let public_key = match PublicKey::try_from(public_key.as_str()) {
+        Ok(key) => key,
+        Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+    };
+    let target = MutableItem::target_from_key(public_key.as_bytes(), &None);
+    create_response_vector(false, hex::encode(target.to_vec()))
+}
+
+#[uniffi::export]
+pub fn publish_https(record_name: String, target: String, secret_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        // Create SVCB record with the target domain
+        let target = match target.as_str().try_into() {
+            Ok(target) => target,
+            Err(e) => return create_response_vector(true, format!("Invalid target: {}", e)),
+        };
+        let svcb = SVCB::new(0, target);
+
+        // Create HTTPS record
+        let https_record = HTTPS(svcb);
+
+        // Create DNS packet
+        let mut packet = Packet::new_reply(0);
+        let dns_name = match dns::Name::new(&record_name) {
+            Ok(name) => name,
+            Err(e) => return create_response_vector(true, format!("Invalid DNS name: {}", e)),
+        };
+
+        packet.answers.push(ResourceRecord::new(
+            dns_name,
+            dns::CLASS::IN,
+            3600, // TTL in seconds
+            dns::rdata::RData::HTTPS(https_record),
+        ));
+
+        let signed_packet = match SignedPacket::from_packet(&keypair, &packet) {
+            Ok(signed_packet) => signed_packet,
+            Err(e) => return create_response_vector(true, format!("Failed to create signed packet: {}", e)),
+        };
+
+        match publish_signed_packet(&client, &signed_packet).await {
+            Ok(()) => create_response_vector(false, keypair.public_key().to_string()),
+            Err(e) => create_response_vector(true, publish_error_message(&e)),
+        }
+    })
+}
+
+// An HTTPS record as returned by `resolve_https`
+fn https_record_to_json(record: &ResourceRecord, https: &HTTPS) -> serde_json::Value {
+    // Create a JSON object
+    let mut https_json = serde_json::json!({
+        "name": record.name.to_string(),
+        "class": format!("{:?}", record.class),
+        "ttl": record.ttl,
+        "priority": https.0.priority,
+        "target": https.0.target.to_string(),
+    });
+
+    // Access specific parameters using the constants from SVCB
+    if let Some(port_param) = https.0.get_param(SVCB::PORT) {
+        if port_param.len() == 2 {
+            let port = u16::from_be_bytes([port_param[0], port_param[1]]);
+            https_json["port"] = serde_json::json!(port);
+        }
+    }
+
+    // Access ALPN parameter if needed
+    if let Some(alpn_param) = https.0.get_param(SVCB::ALPN) {
+        // Parse ALPN protocols (list of character strings)
+        let mut position = 0;
+        let mut alpn_protocols = Vec::new();
+        while position < alpn_param.len() {
+            let length = alpn_param[position] as usize;
+            position += 1;
+            if position + length <= alpn_param.len() {
+                let protocol = String::from_utf8_lossy(
+                    &alpn_param[position..position + length],
+                );
+                alpn_protocols.push(protocol.to_string());
+                position += length;
+            } else {
+                break; // Malformed ALPN parameter
+            }
+        }
+        https_json["alpn"] = serde_json::json!(alpn_protocols);
+    }
+    // TODO: Add other parameters as needed.
+    https_json
+}
+
+#[uniffi::export]
+pub fn resolve_https(public_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let public_key = match public_key.as_str().try_into() {
+            Ok(key) => key,
+            Err(e) => return create_response_vector(true, format!("Invalid public key: {}", e)),
+        };
 
         let client = get_pubky_client();
 
@@ -279,50 +1744,9 @@ pub fn resolve_https(public_key: String) -> Vec<String> {
             Ok(Some(signed_packet)) => {
                 // Extract HTTPS records from the signed packet
                 let https_records: Vec<serde_json::Value> = signed_packet.packet().answers.iter()
-                    .filter_map(|record| {
-                        if let dns::rdata::RData::HTTPS(https) = &record.rdata {
-                            // Create a JSON object
-                            let mut https_json = serde_json::json!({
-                                "name": record.name.to_string(),
-                                "class": format!("{:?}", record.class),
-                                "ttl": record.ttl,
-                                "priority": https.0.priority,
-                                "target": https.0.target.to_string(),
-                            });
-
-                            // Access specific parameters using the constants from SVCB
-                            if let Some(port_param) = https.0.get_param(SVCB::PORT) {
-                                if port_param.len() == 2 {
-                                    let port = u16::from_be_bytes([port_param[0], port_param[1]]);
-                                    https_json["port"] = serde_json::json!(port);
-                                }
-                            }
-
-                            // Access ALPN parameter if needed
-                            if let Some(alpn_param) = https.0.get_param(SVCB::ALPN) {
-                                // Parse ALPN protocols (list of character strings)
-                                let mut position = 0;
-                                let mut alpn_protocols = Vec::new();
-                                while position < alpn_param.len() {
-                                    let length = alpn_param[position] as usize;
-                                    position += 1;
-                                    if position + length <= alpn_param.len() {
-                                        let protocol = String::from_utf8_lossy(
-                                            &alpn_param[position..position + length],
-                                        );
-                                        alpn_protocols.push(protocol.to_string());
-                                        position += length;
-                                    } else {
-                                        break; // Malformed ALPN parameter
-                                    }
-                                }
-                                https_json["alpn"] = serde_json::json!(alpn_protocols);
-                            }
-                            // TODO: Add other parameters as needed.
-                            Some(https_json)
-                        } else {
-                            None
-                        }
+                    .filter_map(|record| match &record.rdata {
+                        dns::rdata::RData::HTTPS(https) => Some(https_record_to_json(record, https)),
+                        _ => None,
                     })
                     .collect();
 
@@ -351,8 +1775,109 @@ pub fn resolve_https(public_key: String) -> Vec<String> {
     })
 }
 
+/**
+* Discover the endpoints of a public key: its HTTPS records when it has any, otherwise its A and AAAA records
+* @param public_key The zbase32 encoded public key
+* @returns A JSON object `{ "public_key", "endpoints" }`. Each endpoint has a `type`: "HTTPS" endpoints have the
+* fields returned by `resolve_https`, "A" and "AAAA" endpoints have `name`, `ttl` and `address`.
+**/
 #[uniffi::export]
-pub fn sign_up(secret_key: String, homeserver: String) -> Vec<String> {
+pub fn resolve_endpoints(public_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let public_key: PublicKey = match public_key.as_str().try_into() {
+            Ok(key) => key,
+            Err(e) => return create_response_vector(true, format!("Invalid public key: {}", e)),
+        };
+        let client = get_pubky_client();
+
+        let signed_packet = match client.pkarr().resolve(&public_key).await {
+            Ok(Some(signed_packet)) => signed_packet,
+            Ok(None) => return create_response_vector(true, "No signed packet found".to_string()),
+            Err(e) => return create_response_vector(true, format!("Failed to resolve: {}", e)),
+        };
+        let answers = &signed_packet.packet().answers;
+
+        let mut endpoints: Vec<serde_json::Value> = answers
+            .iter()
+            .filter_map(|record| match &record.rdata {
+                RData::HTTPS(https) => {
+                    let mut https_json = https_record_to_json(record, https);
+                    https_json["type"] = json!("HTTPS");
+                    Some(https_json)
+                },
+                _ => None,
+            })
+            .collect();
+        if endpoints.is_empty() {
+            endpoints = answers
+                .iter()
+                .filter_map(|record| {
+                    let (record_type, address) = match &record.rdata {
+                        RData::A(a) => ("A", Ipv4Addr::from(a.address).to_string()),
+                        RData::AAAA(aaaa) => ("AAAA", Ipv6Addr::from(aaaa.address).to_string()),
+                        _ => return None,
+                    };
+                    Some(json!({
+                        "type": record_type,
+                        "name": record.name.to_string(),
+                        "ttl": record.ttl,
+                        "address": address,
+                    }))
+                })
+                .collect();
+        }
+        if endpoints.is_empty() {
+            return create_response_vector(true, "No HTTPS, A or AAAA records found".to_string());
+        }
+
+        let json_obj = json!({
+            "public_key": public_key.to_string(),
+            "endpoints": endpoints,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Build the record pubky publishes to point a public key to its homeserver
+* @param homeserver_pubky The public key of the homeserver
+* @returns The record in the same JSON format as the `records` returned by `resolve`:
+* an HTTPS record named `_pubky` with priority 0 whose target is the homeserver public key
+**/
+#[uniffi::export]
+pub fn build_homeserver_record(homeserver_pubky: String) -> Vec<String> {
+    let homeserver = match PublicKey::try_from(homeserver_pubky) {
+        Ok(key) => key,
+        Err(error) => return create_response_vector(true, format!("Invalid homeserver public key: {}", error)),
+    };
+    let record = match homeserver_resource_record(&homeserver, HOMESERVER_RECORD_TTL) {
+        Ok(record) => record,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let json_obj = match resource_record_to_json(&record) {
+        Ok(json_obj) => json_obj,
+        Err(e) => return create_response_vector(true, format!("Failed to convert record to JSON: {}", e)),
+    };
+
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Publish the homeserver record of a key, keeping its other published records
+* @param secret_key The secret key of the identity
+* @param homeserver_pubky The public key of the homeserver
+* @param ttl The TTL of the record in seconds
+* @returns The public key of the identity
+**/
+#[uniffi::export]
+pub fn publish_homeserver_record(secret_key: String, homeserver_pubky: String, ttl: u32) -> Vec<String> {
     let runtime = TOKIO_RUNTIME.clone();
     runtime.block_on(async {
         let client = get_pubky_client();
@@ -360,23 +1885,60 @@ pub fn sign_up(secret_key: String, homeserver: String) -> Vec<String> {
             Ok(keypair) => keypair,
             Err(error) => return create_response_vector(true, error),
         };
-
-        let homeserver_public_key = match PublicKey::try_from(homeserver) {
+        let homeserver = match PublicKey::try_from(homeserver_pubky) {
             Ok(key) => key,
             Err(error) => return create_response_vector(true, format!("Invalid homeserver public key: {}", error)),
         };
+        let record = match homeserver_resource_record(&homeserver, ttl) {
+            Ok(record) => record,
+            Err(error) => return create_response_vector(true, error),
+        };
 
-        match client.signup(&keypair, &homeserver_public_key).await {
-            Ok(session) => {
-                create_response_vector(false, session_to_json(&session))
-            },
-            Err(error) => create_response_vector(true, format!("signup failure: {}", error)),
+        match publish_replacing_record(&client, &keypair, record).await {
+            Ok(()) => create_response_vector(false, keypair.public_key().to_string()),
+            Err(error) => create_response_vector(true, error),
         }
     })
 }
 
+// Publish a record, keeping the key's other published records but replacing those with the same name
+async fn publish_replacing_record(client: &PubkyClient, keypair: &Keypair, record: ResourceRecord<'static>) -> Result<(), String> {
+    let public_key = keypair.public_key();
+    let relative_name = record.name.to_string();
+    let full_name = format!("{}.{}", relative_name, public_key);
+
+    let mut packet = Packet::new_reply(0);
+    match client.pkarr().resolve(&public_key).await {
+        Ok(Some(existing)) => {
+            for answer in existing.packet().answers.iter().cloned() {
+                let name = answer.name.to_string();
+                if name != relative_name && name != full_name {
+                    packet.answers.push(answer.into_owned());
+                }
+            }
+        },
+        Ok(None) => {},
+        Err(e) => return Err(format!("Failed to resolve existing records: {}", e)),
+    }
+    packet.answers.push(record);
+
+    let signed_packet = SignedPacket::from_packet(keypair, &packet)
+        .map_err(|e| format!("Failed to create signed packet: {}", e))?;
+    publish_signed_packet(client, &signed_packet)
+        .await
+        .map_err(|e| publish_error_message(&e))
+}
+
+/**
+* Publish an alias pointing to another public key, as a TXT record named `_alias.<alias>._pubky`.
+* The key's other published records are kept.
+* @param secret_key The secret key of the alias owner
+* @param alias The alias, a single DNS label
+* @param target_pubky The public key the alias points to
+* @returns The public key of the owner
+**/
 #[uniffi::export]
-pub fn sign_in(secret_key: String) -> Vec<String> {
+pub fn create_link_record(secret_key: String, alias: String, target_pubky: String) -> Vec<String> {
     let runtime = TOKIO_RUNTIME.clone();
     runtime.block_on(async {
         let client = get_pubky_client();
@@ -384,223 +1946,3802 @@ pub fn sign_in(secret_key: String) -> Vec<String> {
             Ok(keypair) => keypair,
             Err(error) => return create_response_vector(true, error),
         };
-        match client.signin(&keypair).await {
-            Ok(session) => {
-                create_response_vector(false, session_to_json(&session))
-            },
-            Err(error) => {
-                create_response_vector(true, format!("Failed to sign in: {}", error))
-            }
+        if alias.is_empty() || alias.contains('.') {
+            return create_response_vector(true, "Alias must be a single, non-empty DNS label".to_string());
+        }
+        let target = match PublicKey::try_from(target_pubky.as_str()) {
+            Ok(key) => key.to_string(),
+            Err(e) => return create_response_vector(true, format!("Invalid target public key: {}", e)),
+        };
+
+        let record_name = link_record_name(&alias);
+        let dns_name = match dns::Name::new(&record_name) {
+            Ok(name) => name,
+            Err(e) => return create_response_vector(true, format!("Failed to create DNS name: {}", e)),
+        };
+        let txt_record = match TXT::try_from(target.as_str()) {
+            Ok(txt) => txt,
+            Err(e) => return create_response_vector(true, format!("Failed to convert string to TXT record: {}", e)),
+        };
+        let record = ResourceRecord::new(dns_name, dns::CLASS::IN, 3600, RData::TXT(txt_record)).into_owned();
+
+        match publish_replacing_record(&client, &keypair, record).await {
+            Ok(()) => create_response_vector(false, keypair.public_key().to_string()),
+            Err(error) => create_response_vector(true, error),
         }
     })
 }
 
+/**
+* Resolve an alias published with `create_link_record`
+* @param owner_pubky The public key of the alias owner
+* @param alias The alias
+* @returns The public key the alias points to
+**/
 #[uniffi::export]
-pub fn sign_out(secret_key: String) -> Vec<String> {
+pub fn resolve_link(owner_pubky: String, alias: String) -> Vec<String> {
     let runtime = TOKIO_RUNTIME.clone();
     runtime.block_on(async {
         let client = get_pubky_client();
-        let keypair = match get_keypair_from_secret_key(&secret_key) {
+        let owner = match PublicKey::try_from(owner_pubky.as_str()) {
+            Ok(key) => key,
+            Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+        };
+        let signed_packet = match client.pkarr().resolve(&owner).await {
+            Ok(Some(signed_packet)) => signed_packet,
+            Ok(None) => return create_response_vector(true, "No signed packet found".to_string()),
+            Err(e) => return create_response_vector(true, format!("Failed to resolve: {}", e)),
+        };
+
+        let target = signed_packet
+            .resource_records(&link_record_name(&alias))
+            .find_map(|record| match &record.rdata {
+                RData::TXT(txt) => String::try_from(txt.clone()).ok(),
+                _ => None,
+            });
+        let target = match target {
+            Some(target) => target,
+            None => return create_response_vector(true, format!("No link found for alias {}", alias)),
+        };
+
+        match PublicKey::try_from(target.as_str()) {
+            Ok(key) => create_response_vector(false, key.to_string()),
+            Err(e) => create_response_vector(true, format!("Link target is not a valid public key: {}", e)),
+        }
+    })
+}
+
+// Name of the TXT record a rotated key publishes, holding its replacement public key
+const ROTATION_RECORD_NAME: &str = "_rotation";
+
+// Move a resolved record from one key's zone to another's, keeping its relative name
+fn rebase_resource_record(record: &ResourceRecord, old_origin: &PublicKey) -> Result<ResourceRecord<'static>, String> {
+    let name = record.name.to_string();
+    let old_origin = old_origin.to_string();
+    let relative_name = if name == old_origin {
+        "@".to_string()
+    } else {
+        name.strip_suffix(&format!(".{}", old_origin)).unwrap_or(&name).to_string()
+    };
+    let dns_name = dns::Name::new(&relative_name).map_err(|e| format!("Invalid DNS name {}: {}", relative_name, e))?;
+    Ok(ResourceRecord::new(dns_name, record.class, record.ttl, record.rdata.clone()).into_owned())
+}
+
+/**
+* Migrate a compromised key's DNS records to a new key. The old key's records are published under the new key,
+* and the old key is republished with a `_rotation` TXT record holding the new public key, so anyone resolving
+* the old key can find its replacement.
+* @param old_secret_key The secret key being retired
+* @param new_secret_key The secret key replacing it
+* @returns A JSON object `{ "new_public_key", "records_migrated" }`
+**/
+#[uniffi::export]
+pub fn rotate_key(old_secret_key: String, new_secret_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let old_keypair = match get_keypair_from_secret_key(&old_secret_key) {
             Ok(keypair) => keypair,
             Err(error) => return create_response_vector(true, error),
         };
-        match client.signout(&keypair.public_key()).await {
-            Ok(_) => create_response_vector(false, "Sign out success".to_string()),
-            Err(error) => {
-                create_response_vector(true, format!("Failed to sign out: {}", error))
+        let new_keypair = match get_keypair_from_secret_key(&new_secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let old_public_key = old_keypair.public_key();
+        let new_public_key = new_keypair.public_key();
+        if old_public_key == new_public_key {
+            return create_response_vector(true, "The old and new secret keys must differ".to_string());
+        }
+
+        let old_packet = match client.pkarr().resolve(&old_public_key).await {
+            Ok(Some(signed_packet)) => signed_packet,
+            Ok(None) => return create_response_vector(true, "No signed packet found".to_string()),
+            Err(e) => return create_response_vector(true, format!("Failed to resolve: {}", e)),
+        };
+        let rotation_name = format!("{}.{}", ROTATION_RECORD_NAME, old_public_key);
+
+        let mut new_packet = Packet::new_reply(0);
+        let mut retired_packet = Packet::new_reply(0);
+        for answer in old_packet.packet().answers.iter() {
+            if answer.name.to_string() == rotation_name {
+                continue;
+            }
+            match rebase_resource_record(answer, &old_public_key) {
+                Ok(record) => new_packet.answers.push(record),
+                Err(error) => return create_response_vector(true, error),
+            }
+            retired_packet.answers.push(answer.clone().into_owned());
+        }
+        let records_migrated = new_packet.answers.len();
+
+        let new_public_key_str = new_public_key.to_string();
+        let rotation_txt = match TXT::try_from(new_public_key_str.as_str()) {
+            Ok(txt) => txt,
+            Err(e) => return create_response_vector(true, format!("Failed to convert string to TXT record: {}", e)),
+        };
+        let rotation_dns_name = match dns::Name::new(ROTATION_RECORD_NAME) {
+            Ok(name) => name,
+            Err(e) => return create_response_vector(true, format!("Failed to create DNS name: {}", e)),
+        };
+        retired_packet.answers.push(ResourceRecord::new(rotation_dns_name, dns::CLASS::IN, HOMESERVER_RECORD_TTL, RData::TXT(rotation_txt)).into_owned());
+
+        // Publish the new key first, so the rotation record never points to a key that doesn't resolve
+        for (keypair, packet) in [(&new_keypair, &new_packet), (&old_keypair, &retired_packet)] {
+            let signed_packet = match SignedPacket::from_packet(keypair, packet) {
+                Ok(signed_packet) => signed_packet,
+                Err(e) => return create_response_vector(true, format!("Failed to create signed packet: {}", e)),
+            };
+            if let Err(e) = publish_signed_packet(&client, &signed_packet).await {
+                return create_response_vector(true, publish_error_message(&e));
             }
         }
+
+        let json_obj = json!({
+            "new_public_key": new_public_key_str,
+            "records_migrated": records_migrated,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
     })
 }
 
+// Path, relative to a key's homeserver root, where `publish_revocation` stores the revocation certificate
+const REVOCATION_CERTIFICATE_PATH: &str = "/pub/revocation.json";
+
+// Name of the TXT record announcing that a key is revoked
+const REVOCATION_RECORD_NAME: &str = "_revoked";
+
+/**
+* Create a revocation certificate, signed by the key it revokes
+* @param secret_key The secret key being revoked
+* @param reason Why the key is revoked
+* @returns The base64 encoded signed document `{ "certificate", "signature" }`, where `certificate` is
+* the JSON string `{ "public_key", "revoked_at", "reason" }` and `signature` its base64 Ed25519 signature
+**/
+#[uniffi::export]
+pub fn create_revocation_certificate(secret_key: String, reason: String) -> Vec<String> {
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let revoked_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    match signed_revocation_certificate(&keypair, &reason, revoked_at) {
+        Ok(document) => create_response_vector(false, general_purpose::STANDARD.encode(document)),
+        Err(error) => create_response_vector(true, error),
+    }
+}
+
+/**
+* Revoke a key: store a signed revocation certificate at `pubky://<key>/pub/revocation.json` and publish
+* a `_revoked` TXT record (`revoked_at=<unix timestamp>`). The key's other published records are kept.
+* The key must be signed in to its homeserver for the certificate to be stored.
+* @param secret_key The secret key being revoked
+* @param reason Why the key is revoked
+* @returns The URL of the stored certificate
+**/
+#[uniffi::export]
+pub fn publish_revocation(secret_key: String, reason: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let revoked_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let document = match signed_revocation_certificate(&keypair, &reason, revoked_at) {
+            Ok(document) => document,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let certificate_url = format!("pubky://{}{}", keypair.public_key(), REVOCATION_CERTIFICATE_PATH);
+        let parsed_url = match Url::parse(&certificate_url) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        if let Err(error) = put_data(&client, parsed_url, document.as_bytes()).await {
+            return create_response_vector(true, format!("Failed to put: {}", error));
+        }
+
+        let dns_name = match dns::Name::new(REVOCATION_RECORD_NAME) {
+            Ok(name) => name,
+            Err(e) => return create_response_vector(true, format!("Failed to create DNS name: {}", e)),
+        };
+        let revoked_at_value = format!("revoked_at={}", revoked_at);
+        let txt_record = match TXT::try_from(revoked_at_value.as_str()) {
+            Ok(txt) => txt,
+            Err(e) => return create_response_vector(true, format!("Failed to convert string to TXT record: {}", e)),
+        };
+        let record = ResourceRecord::new(dns_name, dns::CLASS::IN, HOMESERVER_RECORD_TTL, RData::TXT(txt_record)).into_owned();
+
+        match publish_replacing_record(&client, &keypair, record).await {
+            Ok(()) => create_response_vector(false, certificate_url),
+            Err(error) => create_response_vector(true, error),
+        }
+    })
+}
+
+/**
+* Publish a NAPTR record, e.g. for SIP or ENUM service routing. The key's other published records are kept.
+* @param name The record name
+* @param order The order in which NAPTR records must be processed
+* @param preference The order of records with equal `order`
+* @param flags The flags, e.g. "U" or "S"
+* @param services The service parameters, e.g. "E2U+sip"
+* @param regexp The substitution expression applied to the original string
+* @param replacement The next domain name to query, "." for none
+* @param ttl The TTL of the record in seconds
+* @param secret_key The secret key of the identity
+* @returns The public key of the identity
+**/
+#[uniffi::export]
+#[allow(clippy::too_many_arguments)]
+pub fn publish_naptr_record(name: String, order: u16, preference: u16, flags: String, services: String, regexp: String, replacement: String, ttl: u32, secret_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let dns_name = match dns::Name::new(&name) {
+            Ok(name) => name,
+            Err(e) => return create_response_vector(true, format!("Failed to create DNS name: {}", e)),
+        };
+        let replacement = match dns::Name::new(&replacement) {
+            Ok(replacement) => replacement,
+            Err(e) => return create_response_vector(true, format!("Invalid replacement: {}", e)),
+        };
+        let (flags, services, regexp) = match (
+            flags.as_str().try_into(),
+            services.as_str().try_into(),
+            regexp.as_str().try_into(),
+        ) {
+            (Ok(flags), Ok(services), Ok(regexp)) => (flags, services, regexp),
+            _ => return create_response_vector(true, "Flags, services and regexp must each be at most 255 bytes".to_string()),
+        };
+        let naptr = NAPTR {
+            order,
+            preference,
+            flags,
+            services,
+            regexp,
+            replacement,
+        };
+        let record = ResourceRecord::new(dns_name, dns::CLASS::IN, ttl, RData::NAPTR(naptr)).into_owned();
+
+        match publish_replacing_record(&client, &keypair, record).await {
+            Ok(()) => create_response_vector(false, keypair.public_key().to_string()),
+            Err(error) => create_response_vector(true, error),
+        }
+    })
+}
+
+/**
+* Publish an NS record delegating a sub-name to another nameserver or public key.
+* The key's other published records are kept.
+* @param name The delegated record name
+* @param nameserver The nameserver domain or public key
+* @param ttl The TTL of the record in seconds
+* @param secret_key The secret key of the identity
+* @returns The public key of the identity
+**/
+#[uniffi::export]
+pub fn publish_ns_record(name: String, nameserver: String, ttl: u32, secret_key: String) -> Vec<String> {
+    publish_name_record(name, nameserver, ttl, secret_key, |target| RData::NS(NS(target)))
+}
+
+/**
+* Publish a CNAME record aliasing a name to another domain. The key's other published records are kept.
+* @param name The alias record name
+* @param cname_target The canonical domain name
+* @param ttl The TTL of the record in seconds
+* @param secret_key The secret key of the identity
+* @returns The public key of the identity
+**/
+#[uniffi::export]
+pub fn publish_cname_record(name: String, cname_target: String, ttl: u32, secret_key: String) -> Vec<String> {
+    publish_name_record(name, cname_target, ttl, secret_key, |target| RData::CNAME(CNAME(target)))
+}
+
+/**
+* Publish an MX record announcing a mail exchange. The key's other published records are kept.
+* @param name The record name
+* @param preference The preference of the exchange, lower is preferred
+* @param exchange The domain name of the mail exchange
+* @param ttl The TTL of the record in seconds
+* @param secret_key The secret key of the identity
+* @returns The public key of the identity
+**/
+#[uniffi::export]
+pub fn publish_mx_record(name: String, preference: u16, exchange: String, ttl: u32, secret_key: String) -> Vec<String> {
+    publish_name_record(name, exchange, ttl, secret_key, move |exchange| RData::MX(MX { preference, exchange }))
+}
+
+// Publish a record whose data is built around a single domain name, such as NS, CNAME or MX
+fn publish_name_record(name: String, target: String, ttl: u32, secret_key: String, to_rdata: impl FnOnce(dns::Name) -> RData) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let dns_name = match dns::Name::new(&name) {
+            Ok(name) => name,
+            Err(e) => return create_response_vector(true, format!("Failed to create DNS name: {}", e)),
+        };
+        let target = match dns::Name::new(&target) {
+            Ok(target) => target,
+            Err(e) => return create_response_vector(true, format!("Invalid target: {}", e)),
+        };
+        let record = ResourceRecord::new(dns_name, dns::CLASS::IN, ttl, to_rdata(target)).into_owned();
+
+        match publish_replacing_record(&client, &keypair, record).await {
+            Ok(()) => create_response_vector(false, keypair.public_key().to_string()),
+            Err(error) => create_response_vector(true, error),
+        }
+    })
+}
+
+/**
+* Parse a TXT record string, detecting `key1=value1;key2=value2` records
+* @param txt_raw The TXT record string
+* @returns A JSON object `{ "format", "pairs", "raw" }` where `format` is "kv" or "raw"
+* and `pairs` is null for raw records
+**/
+#[uniffi::export]
+pub fn parse_txt_record(txt_raw: String) -> Vec<String> {
+    let pairs = parse_txt_pairs(&txt_raw);
+    let json_obj = json!({
+        "format": if pairs.is_some() { "kv" } else { "raw" },
+        "pairs": pairs,
+        "raw": txt_raw,
+    });
+
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Encode string pairs into a TXT record string that can be passed to `publish`
+* @param kv_pairs_json A JSON object of string keys and values
+* @returns The `key1=value1;key2=value2` record string
+**/
+#[uniffi::export]
+pub fn create_txt_record_string(kv_pairs_json: String) -> Vec<String> {
+    let pairs: serde_json::Map<String, serde_json::Value> = match serde_json::from_str(&kv_pairs_json) {
+        Ok(pairs) => pairs,
+        Err(e) => return create_response_vector(true, format!("Failed to parse pairs JSON: {}", e)),
+    };
+
+    match txt_pairs_to_string(&pairs) {
+        Ok(txt) => create_response_vector(false, txt),
+        Err(error) => create_response_vector(true, error),
+    }
+}
+
+/**
+* Sign up to a homeserver and publish it as the homeserver of the key
+* @param secret_key The secret key of the identity
+* @param homeserver The public key of the homeserver
+* @returns The session JSON `{ "pubky", "capabilities", "capabilities_detailed", "token_required", "token_consumed" }`.
+* `token_required` follows the homeserver's `signup_mode` (see `get_homeserver_info`), null when its policies can't be
+* fetched. pubky 0.3.0 can't send signup tokens, so `token_consumed` is always false and signing up to a homeserver
+* that requires a token fails with an error saying so.
+**/
+#[uniffi::export]
+pub fn sign_up(secret_key: String, homeserver: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let homeserver_public_key = match PublicKey::try_from(homeserver) {
+            Ok(key) => key,
+            Err(error) => return create_response_vector(true, format!("Invalid homeserver public key: {}", error)),
+        };
+
+        let result = client.signup(&keypair, &homeserver_public_key).await;
+        let token_required = fetch_homeserver_info(&client, &homeserver_public_key)
+            .await
+            .ok()
+            .and_then(|info| info["signup_mode"].as_str().map(|signup_mode| signup_mode != "open"));
+        match result {
+            Ok(session) => {
+                track_account(&session, Some(homeserver_public_key.to_string()));
+                let mut json_obj = session_to_json_value(&session);
+                json_obj["token_required"] = json!(token_required);
+                json_obj["token_consumed"] = json!(false);
+                match serde_json::to_string(&json_obj) {
+                    Ok(json) => create_response_vector(false, json),
+                    Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+                }
+            },
+            Err(error) if token_required == Some(true) => {
+                create_response_vector(true, format!("signup failure: {}: the homeserver requires a signup token", error))
+            },
+            Err(error) => create_response_vector(true, format!("signup failure: {}", error)),
+        }
+    })
+}
+
+/// Delay between lookups while `sign_up_and_wait` waits for the homeserver record
+const SIGN_UP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/**
+* Sign up, then wait until the homeserver record published by the signup can be resolved from the network,
+* so follow-up requests don't race its propagation
+* @param secret_key The secret key of the identity
+* @param homeserver The public key of the homeserver
+* @param signup_token Must be empty: signup tokens are not supported yet, see `sign_up`
+* @param max_wait_seconds How long to wait for the record
+* @returns A JSON object `{ "session", "record_propagated" }`, `session` being `{ "pubky", "capabilities", "capabilities_detailed" }`.
+* The signup succeeded even when `record_propagated` is false.
+**/
+#[uniffi::export]
+pub fn sign_up_and_wait(secret_key: String, homeserver: String, signup_token: Option<String>, max_wait_seconds: u64) -> Vec<String> {
+    if signup_token.is_some_and(|token| !token.is_empty()) {
+        return create_response_vector(true, "Signup tokens are not supported".to_string());
+    }
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let homeserver_public_key = match PublicKey::try_from(homeserver) {
+            Ok(key) => key,
+            Err(error) => return create_response_vector(true, format!("Invalid homeserver public key: {}", error)),
+        };
+
+        let session = match client.signup(&keypair, &homeserver_public_key).await {
+            Ok(session) => session,
+            Err(error) => return create_response_vector(true, format!("signup failure: {}", error)),
+        };
+        track_account(&session, Some(homeserver_public_key.to_string()));
+
+        // The published packet is cached locally, so expire it before each lookup to actually query the network
+        let public_key = keypair.public_key();
+        let deadline = time::Instant::now() + Duration::from_secs(max_wait_seconds);
+        let mut record_propagated = false;
+        loop {
+            expire_cached_packet(&client, &public_key);
+            if let Ok(Some(signed_packet)) = client.pkarr().resolve(&public_key).await {
+                if get_svcb_target(&signed_packet, HOMESERVER_RECORD_NAME)
+                    .is_some_and(|(target, _)| target == homeserver_public_key.to_string())
+                {
+                    record_propagated = true;
+                    break;
+                }
+            }
+            if time::Instant::now() + SIGN_UP_POLL_INTERVAL > deadline {
+                break;
+            }
+            time::sleep(SIGN_UP_POLL_INTERVAL).await;
+        }
+
+        let json_obj = json!({
+            "session": session_to_json_value(&session),
+            "record_propagated": record_propagated,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/// Default timeout of homeserver admin requests, in seconds
+pub const DEFAULT_ADMIN_REQUEST_TIMEOUT_SECS: u64 = 10;
+
+/**
+* Generate a signup token using a homeserver's admin API
+* @param homeserver_pubky The public key of the homeserver
+* @param admin_password The admin password of the homeserver
+* @param base_url The base URL of the admin server, e.g. `http://localhost:6288` for a local testnet.
+* When omitted, the homeserver endpoint published in its pkarr packet is used.
+* @param timeout_secs The request timeout in seconds, 0 for the default (10 seconds)
+* @returns The signup token
+**/
+#[uniffi::export]
+pub fn get_signup_token(homeserver_pubky: String, admin_password: String, base_url: Option<String>, timeout_secs: u64) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let homeserver = match PublicKey::try_from(homeserver_pubky.as_str()) {
+            Ok(key) => key,
+            Err(error) => return create_response_vector(true, format!("Invalid homeserver public key: {}", error)),
+        };
+        let base_url = match base_url {
+            Some(base_url) => base_url,
+            None => match resolve_homeserver_endpoint(&get_pubky_client(), &homeserver.to_string(), None).await {
+                Some(endpoint) => endpoint,
+                None => return create_response_vector(true, format!("Could not resolve endpoint for homeserver {}", homeserver)),
+            },
+        };
+        let url = match Url::parse(&format!("{}/admin/generate_signup_token", base_url.trim_end_matches('/'))) {
+            Ok(url) if url.scheme() == "https" || url.scheme() == "http" => url,
+            _ => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+
+        let timeout = Duration::from_secs(if timeout_secs == 0 { DEFAULT_ADMIN_REQUEST_TIMEOUT_SECS } else { timeout_secs });
+        let http_client = match reqwest::Client::builder().timeout(timeout).build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+        let response = match http_client.get(url).header("X-Admin-Password", admin_password).send().await {
+            Ok(response) => response,
+            Err(error) => return create_response_vector(true, format!("Request failed: {}", error)),
+        };
+        if !response.status().is_success() {
+            return create_response_vector(true, format!("Request failed with status {}", response.status()));
+        }
+        match response.text().await {
+            Ok(token) => create_response_vector(false, token.trim().to_string()),
+            Err(error) => create_response_vector(true, format!("Failed to read response body: {}", error)),
+        }
+    })
+}
+
+// Fetch the `get_homeserver_info` JSON of a homeserver
+async fn fetch_homeserver_info(client: &PubkyClient, homeserver: &PublicKey) -> Result<serde_json::Value, String> {
+    let endpoint = resolve_homeserver_endpoint(client, &homeserver.to_string(), None)
+        .await
+        .ok_or_else(|| format!("Could not resolve endpoint for homeserver {}", homeserver))?;
+    let url = Url::parse(&format!("{}{}", endpoint.trim_end_matches('/'), HOMESERVER_INFO_PATH))
+        .map_err(|_| "Failed to parse URL".to_string())?;
+
+    let response = reqwest::get(url).await.map_err(|error| format!("Request failed: {}", error))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(homeserver_info_json(None));
+    }
+    if !response.status().is_success() {
+        return Err(format!("Request failed with status {}", response.status()));
+    }
+    let body = response.text().await.map_err(|error| format!("Failed to read response body: {}", error))?;
+    let well_known: serde_json::Value = serde_json::from_str(&body).map_err(|error| format!("Invalid homeserver info: {}", error))?;
+    Ok(homeserver_info_json(Some(&well_known)))
+}
+
+/**
+* Get a homeserver's policies before signing up, from its `/.well-known/pubky` document
+* @param homeserver_pubky The public key of the homeserver
+* @returns A JSON object `{ "version", "signup_mode", "quota_bytes", "terms_url", "features" }`, `signup_mode`
+* being `"open"` or `"invite"`. Homeservers without the document are reported with an `"open"` signup mode
+* and a null `quota_bytes`.
+**/
+#[uniffi::export]
+pub fn get_homeserver_info(homeserver_pubky: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let homeserver = match PublicKey::try_from(homeserver_pubky.as_str()) {
+            Ok(key) => key,
+            Err(error) => return create_response_vector(true, format!("Invalid homeserver public key: {}", error)),
+        };
+        let json_obj = match fetch_homeserver_info(&get_pubky_client(), &homeserver).await {
+            Ok(json_obj) => json_obj,
+            Err(error) => return create_response_vector(true, error),
+        };
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Set the homeserver used by `sign_up_default`
+* @param pubky The public key of the homeserver
+**/
+#[uniffi::export]
+pub fn set_default_homeserver(pubky: String) -> Vec<String> {
+    match PublicKey::try_from(pubky.as_str()) {
+        Ok(homeserver) => {
+            NETWORK_CLIENT.set_default_homeserver(homeserver.clone());
+            create_response_vector(false, homeserver.to_string())
+        },
+        Err(error) => create_response_vector(true, format!("Invalid homeserver public key: {}", error)),
+    }
+}
+
+/**
+* Sign up to the homeserver set with `set_default_homeserver`, see `sign_up`
+* @param secret_key The secret key of the identity
+* @param signup_token Must be empty: signup tokens are not supported yet
+**/
+#[uniffi::export]
+pub fn sign_up_default(secret_key: String, signup_token: Option<String>) -> Vec<String> {
+    if signup_token.is_some_and(|token| !token.is_empty()) {
+        return create_response_vector(true, "Signup tokens are not supported".to_string());
+    }
+    match NETWORK_CLIENT.get_default_homeserver() {
+        Some(homeserver) => sign_up(secret_key, homeserver.to_string()),
+        None => create_response_vector(true, "No default homeserver set".to_string()),
+    }
+}
+
+#[uniffi::export]
+pub fn sign_in(secret_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+        match client.signin(&keypair).await {
+            Ok(session) => {
+                track_account(&session, None);
+                create_response_vector(false, session_to_json(&session))
+            },
+            Err(error) => {
+                create_response_vector(true, format!("Failed to sign in: {}", error))
+            }
+        }
+    })
+}
+
+/// How long `sign_in_scoped` waits for the homeserver to grant the session
+const SCOPED_SIGN_IN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The HTTP relay channel `sign_in_scoped` exchanges the auth token through
+const DEFAULT_AUTH_RELAY: &str = "https://demo.httprelay.io/link";
+
+/**
+* Sign in with a session limited to the given capabilities, e.g. read-only for a background service.
+* The homeserver only grants scoped sessions through the pubkyauth flow, so this runs both sides of it in process,
+* exchanging the auth token through the public `https://demo.httprelay.io/link` relay.
+* The scoped session would replace the key's session in this client, so this fails while the key is signed in with
+* a session granting more than `capabilities_json`; sign out first to switch to the scoped session.
+* @param secret_key The secret key to sign in with
+* @param capabilities_json A JSON array of capabilities, e.g. `["/pub/pubky.app/:r"]`
+* @returns The granted session, as for `sign_in`
+**/
+#[uniffi::export]
+pub fn sign_in_scoped(secret_key: String, capabilities_json: String) -> Vec<String> {
+    sign_in_scoped_with_relay(secret_key, capabilities_json, DEFAULT_AUTH_RELAY.to_string())
+}
+
+/**
+* Same as `sign_in_scoped`, exchanging the auth token through the given HTTP relay instead of the public one
+* @param secret_key The secret key to sign in with
+* @param capabilities_json A JSON array of capabilities, e.g. `["/pub/pubky.app/:r"]`
+* @param relay_url The HTTP relay channel URL carrying the auth token, e.g. a self-hosted `https://<relay>/link`
+* @returns The granted session, as for `sign_in`
+**/
+#[uniffi::export]
+pub fn sign_in_scoped_with_relay(secret_key: String, capabilities_json: String, relay_url: String) -> Vec<String> {
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let relay_url = match Url::parse(&relay_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        _ => return create_response_vector(true, format!("Invalid relay URL: {}", relay_url)),
+    };
+    let capabilities: Vec<String> = match serde_json::from_str(&capabilities_json) {
+        Ok(capabilities) => capabilities,
+        Err(e) => return create_response_vector(true, format!("Failed to parse capabilities JSON: {}", e)),
+    };
+    let capabilities = match canonicalize_capabilities(&capabilities) {
+        Ok(capabilities) if capabilities.is_empty() => return create_response_vector(true, "At least one capability is required".to_string()),
+        Ok(capabilities) => capabilities,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let capabilities = match pubky_common::capabilities::Capabilities::try_from(capabilities.join(",").as_str()) {
+        Ok(capabilities) => capabilities,
+        Err(e) => return create_response_vector(true, format!("Invalid capabilities: {}", e)),
+    };
+    if account_has_broader_session(&keypair.public_key().to_string(), &capabilities.0) {
+        return create_response_vector(true, "Already signed in with a broader session, sign out first".to_string());
+    }
+
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let (pubkyauth_url, session_receiver) = match client.auth_request(relay_url.as_str(), &capabilities) {
+            Ok(request) => request,
+            Err(e) => return create_response_vector(true, format!("Failed to create auth request: {}", e)),
+        };
+        if let Err(e) = client.send_auth_token(&keypair, pubkyauth_url).await {
+            return create_response_vector(true, format!("Failed to send auth token: {}", e));
+        }
+        let pubky = match time::timeout(SCOPED_SIGN_IN_TIMEOUT, session_receiver).await {
+            Ok(Ok(pubky)) => pubky,
+            Ok(Err(_)) => return create_response_vector(true, "Failed to sign in: the homeserver did not grant a session".to_string()),
+            Err(_) => return create_response_vector(true, format!("Failed to sign in: timed out after {} seconds", SCOPED_SIGN_IN_TIMEOUT.as_secs())),
+        };
+        match client.session(&pubky).await {
+            Ok(Some(session)) => {
+                track_account(&session, None);
+                create_response_vector(false, session_to_json(&session))
+            },
+            Ok(None) => create_response_vector(true, "Failed to sign in: no session found".to_string()),
+            Err(e) => create_response_vector(true, format!("Failed to get session: {}", e)),
+        }
+    })
+}
+
+#[uniffi::export]
+pub fn sign_out(secret_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+        match client.signout(&keypair.public_key()).await {
+            Ok(_) => {
+                untrack_account(&keypair.public_key().to_string());
+                create_response_vector(false, "Sign out success".to_string())
+            },
+            Err(error) => {
+                create_response_vector(true, format!("Failed to sign out: {}", error))
+            }
+        }
+    })
+}
+
+/**
+* List the accounts currently signed in through `sign_in` or `sign_up`
+* @returns A JSON array of `{ "pubky", "capabilities", "homeserver", "signed_in_at" }` objects,
+* where `homeserver` is only known for accounts that signed up in this session
+**/
+#[uniffi::export]
+pub fn active_accounts() -> Vec<String> {
+    match serde_json::to_string(&active_accounts_json()) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Put content at a URL. Both `pubky://<public key>/<path>` URLs and `https://` URLs of the homeserver, as resolved from them,
+* are accepted; requests to the homeserver carry the session of a signed in key either way.
+* When the homeserver can't be reached (a timeout or connection failure), the write is added to the offline queue and
+* a `QUEUED: ` error is returned; `flush_queue` writes it once connectivity is back.
+**/
+#[uniffi::export]
+pub fn put(url: String, content: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match parse_data_url(trimmed_url) {
+            Ok(url) => url,
+            Err(error) => return create_response_vector(true, error),
+        };
+        match put_data(&client, parsed_url.clone(), content.as_bytes()).await {
+            Ok(()) => create_response_vector(false, trimmed_url.to_string()),
+            Err(DataRequestError::CircuitOpen(error)) => create_response_vector(true, error),
+            Err(DataRequestError::Failed(error)) if is_connectivity_error(&error) => {
+                NETWORK_CLIENT.queue_put(QueuedPut {
+                    url: parsed_url.to_string(),
+                    content,
+                    queued_at_unix: SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0),
+                });
+                create_response_vector(true, format!("QUEUED: Failed to put: {}, the write is queued", error))
+            },
+            Err(DataRequestError::Failed(error)) => {
+                create_response_vector(true, format!("Failed to put: {}", error))
+            }
+        }
+    })
+}
+
+/**
+* Put the contents of a file at a URL, reading it on the runtime so the content never crosses the FFI boundary.
+* The upload is not streamed: pubky 0.3.0's client only accepts a complete body and doesn't let callers set
+* headers, so the whole file is read into memory first (Content-Length follows from it) and no content type is sent.
+* Otherwise the file is written like `put` writes content.
+* @param url The pubky:// or https:// URL to write to
+* @param file_path The file to upload
+* @returns A JSON object `{ "url", "bytes" }`
+**/
+#[uniffi::export]
+pub fn put_from_file(url: String, file_path: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match parse_data_url(trimmed_url) {
+            Ok(url) => url,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let content = match tokio::fs::read(&file_path).await {
+            Ok(content) => content,
+            Err(error) => return create_response_vector(true, format!("Failed to read file {}: {}", file_path, error)),
+        };
+        match put_data(&client, parsed_url, &content).await {
+            Ok(()) => {},
+            Err(DataRequestError::CircuitOpen(error)) => return create_response_vector(true, error),
+            Err(DataRequestError::Failed(error)) => return create_response_vector(true, format!("Failed to put: {}", error)),
+        }
+
+        let json_obj = json!({
+            "url": trimmed_url,
+            "bytes": content.len(),
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+// Number of attempts `put_idempotent` makes before giving up on transient errors
+const PUT_MAX_ATTEMPTS: u32 = 3;
+
+// How long `put_idempotent` remembers a completed idempotency key
+const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Most idempotency keys remembered at once, the oldest completed ones are forgotten first
+const MAX_IDEMPOTENT_PUTS: usize = 10_000;
+
+// A write made by `put_idempotent`, reserved under its idempotency key before it starts
+struct IdempotentPut {
+    url: String,
+    content_sha256: String,
+    // None while the write is in flight
+    completed_at: Option<Instant>,
+}
+
+impl IdempotentPut {
+    fn is_expired(&self) -> bool {
+        self.completed_at.is_some_and(|completed_at| completed_at.elapsed() >= IDEMPOTENCY_KEY_TTL)
+    }
+}
+
+// Idempotency keys of `put_idempotent` calls, mapped to the write they made or are making
+static IDEMPOTENT_PUTS: Lazy<Mutex<HashMap<String, IdempotentPut>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Reserve an idempotency key for a write, forgetting expired keys and, past `MAX_IDEMPOTENT_PUTS`, the oldest
+// completed ones. Returns whether the write already completed, or an error when the key belongs to another
+// write or its write is still in flight.
+fn reserve_idempotent_put(idempotency_key: &str, url: &str, content_sha256: &str) -> Result<bool, String> {
+    let mut idempotent_puts = IDEMPOTENT_PUTS.lock().unwrap();
+    idempotent_puts.retain(|_, idempotent_put| !idempotent_put.is_expired());
+    if let Some(existing) = idempotent_puts.get(idempotency_key) {
+        if existing.url != url {
+            return Err(format!("Idempotency key already used for {}", existing.url));
+        }
+        if existing.content_sha256 != content_sha256 {
+            return Err("Idempotency key already used with different content".to_string());
+        }
+        if existing.completed_at.is_none() {
+            return Err("Idempotency key is used by a write still in flight".to_string());
+        }
+        return Ok(true);
+    }
+    while idempotent_puts.len() >= MAX_IDEMPOTENT_PUTS {
+        let oldest_key = idempotent_puts
+            .iter()
+            .filter_map(|(key, idempotent_put)| idempotent_put.completed_at.map(|completed_at| (key, completed_at)))
+            .min_by_key(|(_, completed_at)| *completed_at)
+            .map(|(key, _)| key.clone());
+        match oldest_key {
+            Some(key) => idempotent_puts.remove(&key),
+            None => break,
+        };
+    }
+    idempotent_puts.insert(idempotency_key.to_string(), IdempotentPut {
+        url: url.to_string(),
+        content_sha256: content_sha256.to_string(),
+        completed_at: None,
+    });
+    Ok(false)
+}
+
+/**
+* Put content at a URL, retrying transient failures, at most once per idempotency key.
+* pubky 0.3.0 gives no way to add headers to its homeserver requests, so no `Idempotency-Key` header is sent
+* and keys are tracked locally, for 24 hours: once a key has succeeded, calling again with the same URL and
+* content returns success without writing, and reusing it for another URL or other content is an error.
+* A call made while another one with the same key is still writing fails instead of writing twice.
+* URLs are validated and normalized, and writes go through the circuit breaker, like `put`.
+* Retrying is safe because a PUT of the same content overwrites the same file.
+* @param url The pubky:// or https:// URL to write to
+* @param content The content to write
+* @param idempotency_key A caller chosen key identifying this write
+* @returns The URL written to
+**/
+#[uniffi::export]
+pub fn put_idempotent(url: String, content: String, idempotency_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match parse_data_url(trimmed_url) {
+            Ok(url) => url,
+            Err(error) => return create_response_vector(true, error),
+        };
+        if idempotency_key.is_empty() {
+            return create_response_vector(true, "Idempotency key must not be empty".to_string());
+        }
+        let (_, content_sha256) = content_id(content.as_bytes());
+        match reserve_idempotent_put(&idempotency_key, parsed_url.as_str(), &content_sha256) {
+            Ok(true) => return create_response_vector(false, trimmed_url.to_string()),
+            Ok(false) => {},
+            Err(error) => return create_response_vector(true, error),
+        }
+
+        let mut attempt = 1;
+        let result = loop {
+            match put_data(&client, parsed_url.clone(), content.as_bytes()).await {
+                Ok(()) => break Ok(()),
+                Err(DataRequestError::Failed(error)) if attempt < PUT_MAX_ATTEMPTS && is_transient_error(&error) => {
+                    time::sleep(Duration::from_millis(500 * attempt as u64)).await;
+                    attempt += 1;
+                },
+                Err(DataRequestError::CircuitOpen(error)) => break Err(error),
+                Err(DataRequestError::Failed(error)) => {
+                    break Err(format!("Failed to put after {} attempt(s): {}", attempt, error))
+                }
+            }
+        };
+
+        let mut idempotent_puts = IDEMPOTENT_PUTS.lock().unwrap();
+        match result {
+            Ok(()) => {
+                if let Some(idempotent_put) = idempotent_puts.get_mut(&idempotency_key) {
+                    idempotent_put.completed_at = Some(Instant::now());
+                }
+                create_response_vector(false, trimmed_url.to_string())
+            },
+            Err(error) => {
+                idempotent_puts.remove(&idempotency_key);
+                create_response_vector(true, error)
+            }
+        }
+    })
+}
+
+/**
+* Put content at a URL, reporting whether it replaced existing content. The file is checked with a HEAD request
+* right before the put; a write by another client between the two requests goes unnoticed, so treat the result
+* as a conflict hint for sync engines rather than a guarantee. Like `put`, fails fast while the endpoint's circuit is open.
+* @param url The pubky:// or https:// URL to write to
+* @param content The content to write
+* @returns A JSON object `{ "url", "overwritten", "previous_etag" }`, `previous_etag` being null when there was
+* no previous content or the homeserver sent no ETag
+**/
+#[uniffi::export]
+pub fn put_detect_overwrite(url: String, content: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match parse_data_url(trimmed_url) {
+            Ok(url) => url,
+            Err(error) => return create_response_vector(true, error),
+        };
+        // The HEAD request and the put count as one request to the endpoint
+        let endpoint = circuit_endpoint(&parsed_url);
+        if let Err(error) = NETWORK_CLIENT.circuit_breaker().check(&endpoint) {
+            return create_response_vector(true, error);
+        }
+
+        let https_url = match pubky_to_https_url(&client, &parsed_url).await {
+            Ok(https_url) => https_url,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let http_client = match reqwest::Client::builder().build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+        let (overwritten, previous_etag) = match http_client.head(https_url).send().await {
+            Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => (false, None),
+            Ok(response) if response.status().is_success() => {
+                let etag = response
+                    .headers()
+                    .get(reqwest::header::ETAG)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string());
+                (true, etag)
+            },
+            Ok(response) => {
+                if response.status().is_server_error() {
+                    NETWORK_CLIENT.circuit_breaker().record_failure(&endpoint);
+                } else {
+                    NETWORK_CLIENT.circuit_breaker().record_success(&endpoint);
+                }
+                return create_response_vector(true, format!("Failed to check existing content: status {}", response.status()))
+            },
+            Err(error) => {
+                NETWORK_CLIENT.circuit_breaker().record_failure(&endpoint);
+                return create_response_vector(true, format!("Failed to check existing content: {}", error))
+            },
+        };
+
+        if let Err(error) = put_checked_data(&client, parsed_url, &endpoint, content.as_bytes()).await {
+            return create_response_vector(true, format!("Failed to put: {}", error));
+        }
+
+        let json_obj = json!({
+            "url": trimmed_url,
+            "overwritten": overwritten,
+            "previous_etag": previous_etag,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Compute a stable content identifier for content-addressed storage
+* @param content The content
+* @returns A JSON object `{ "cid", "sha256_hex" }` where `cid` is `cid:sha2-256:<base58btc hash>`
+**/
+#[uniffi::export]
+pub fn create_content_id(content: String) -> Vec<String> {
+    let (cid, sha256_hex) = content_id(content.as_bytes());
+    let json_obj = json!({
+        "cid": cid,
+        "sha256_hex": sha256_hex,
+    });
+
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Store content at `{base_url}/{cid}`, see `create_content_id`
+* @param base_url The pubky:// URL of the directory to store the content in
+* @param content The content
+* @returns The full URL the content was stored at
+**/
+#[uniffi::export]
+pub fn put_addressable(base_url: String, content: String) -> Vec<String> {
+    let (cid, _) = content_id(content.as_bytes());
+    let url = format!("{}/{}", base_url.trim_end_matches('/'), cid);
+    put(url, content)
+}
+
+// Suffix of the sidecar file holding the expiry of content put with `put_with_expiry`
+const EXPIRY_SIDECAR_SUFFIX: &str = ".expires";
+
+/**
+* Put content that expires: it is stored like `put`, with its expiry in a `{url}.expires` sidecar file.
+* Nothing deletes it on its own; call `cleanup_expired` to delete expired content.
+* @param url The pubky:// URL to put the content at
+* @param content The content
+* @param expires_at_unix When the content expires, in seconds since the Unix epoch
+* @returns The URL of the content
+**/
+#[uniffi::export]
+pub fn put_with_expiry(url: String, content: String, expires_at_unix: u64) -> Vec<String> {
+    let url = url.trim_end_matches('/').to_string();
+    let result = put(url.clone(), content);
+    if result[0] == "error" {
+        return result;
+    }
+    let sidecar_result = put(format!("{}{}", url, EXPIRY_SIDECAR_SUFFIX), expires_at_unix.to_string());
+    if sidecar_result[0] == "error" {
+        return create_response_vector(true, format!("Content was put, but its expiry was not: {}", sidecar_result[1]));
+    }
+    result
+}
+
+/**
+* Get the expiry of content put with `put_with_expiry`
+* @param url The pubky:// URL of the content
+* @returns A JSON object `{ "expires_at", "expired" }`, `expires_at` in seconds since the Unix epoch
+**/
+#[uniffi::export]
+pub fn get_expiry(url: String) -> Vec<String> {
+    let sidecar_url = format!("{}{}", url.trim_end_matches('/'), EXPIRY_SIDECAR_SUFFIX);
+    let result = get(sidecar_url);
+    if result[0] == "error" {
+        return result;
+    }
+    let expires_at: u64 = match result[1].trim().parse() {
+        Ok(expires_at) => expires_at,
+        Err(_) => return create_response_vector(true, format!("Invalid expiry: {}", result[1])),
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0);
+    let json_obj = json!({
+        "expires_at": expires_at,
+        "expired": expires_at <= now,
+    });
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Delete the expired content under a prefix: every `.expires` sidecar with a past expiry is deleted along with its content
+* @param base_url The pubky:// URL of the directory to clean up, subdirectories included
+* @returns A JSON object `{ "checked", "deleted", "errors" }`, `deleted` listing the URLs of the deleted content
+* and `errors` holding `{ "url", "error" }` for each sidecar that could not be read or deleted
+**/
+#[uniffi::export]
+pub fn cleanup_expired(base_url: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let prefix = match Url::parse(&format!("{}/", base_url.trim_end_matches('/'))) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let urls = match list_all(&client, &prefix).await {
+            Ok(urls) => urls,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0);
+
+        let sidecars: Vec<&String> = urls.iter().filter(|url| url.ends_with(EXPIRY_SIDECAR_SUFFIX)).collect();
+        let mut deleted = Vec::new();
+        let mut errors = Vec::new();
+        for sidecar_url in &sidecars {
+            let content_url = sidecar_url.strip_suffix(EXPIRY_SIDECAR_SUFFIX).unwrap_or(sidecar_url.as_str());
+            let Ok(parsed_sidecar_url) = Url::parse(sidecar_url) else {
+                errors.push(json!({ "url": content_url, "error": "Failed to parse URL" }));
+                continue;
+            };
+            let expires_at = match get_data(&client, parsed_sidecar_url).await {
+                Ok(Some(bytes)) => str::from_utf8(&bytes).ok().and_then(|expiry| expiry.trim().parse::<u64>().ok()),
+                Ok(None) => continue,
+                Err(e) => {
+                    errors.push(json!({ "url": content_url, "error": format!("Failed to get expiry: {}", e) }));
+                    continue;
+                },
+            };
+            match expires_at {
+                Some(expires_at) if expires_at <= now => {},
+                Some(_) => continue,
+                None => {
+                    errors.push(json!({ "url": content_url, "error": "Invalid expiry" }));
+                    continue;
+                },
+            }
+            // Delete the content first, so a failure leaves the sidecar for the next cleanup to retry
+            if let Err(e) = client.delete(content_url).await {
+                errors.push(json!({ "url": content_url, "error": format!("Failed to delete: {}", e) }));
+                continue;
+            }
+            if let Err(e) = client.delete(sidecar_url.as_str()).await {
+                errors.push(json!({ "url": content_url, "error": format!("Failed to delete expiry: {}", e) }));
+            }
+            deleted.push(content_url.to_string());
+        }
+
+        let json_obj = json!({
+            "checked": sidecars.len(),
+            "deleted": deleted,
+            "errors": errors,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Get the content at a URL. Both `pubky://<public key>/<path>` URLs and `https://` URLs of the homeserver, as resolved from them,
+* are accepted; requests to the homeserver carry the session of a signed in key either way.
+**/
+#[uniffi::export]
+pub fn get(url: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let parsed_url = match parse_data_url(&url) {
+            Ok(url) => url,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let bytes = match get_data(&client, parsed_url).await {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return create_response_vector(true, "No data returned".to_string()),
+            Err(DataRequestError::CircuitOpen(error)) => return create_response_vector(true, error),
+            Err(DataRequestError::Failed(_)) => return create_response_vector(true, "Request failed".to_string()),
+        };
+        let string = match str::from_utf8(&bytes) {
+            Ok(s) => s.to_string(),
+            Err(_) => return create_response_vector(true, "Invalid UTF-8 sequence".to_string()),
+        };
+        create_response_vector(false, string)
+    })
+}
+
+/**
+* Get the content at a URL and parse it as JSON, so every binding reports invalid JSON the same way
+* @param url The pubky:// or https:// URL to fetch, as for `get`
+* @returns A JSON object `{ "valid_json": true, "json" }`, or an `INVALID_JSON` error with the line and column of the parse error
+**/
+#[uniffi::export]
+pub fn get_json(url: String) -> Vec<String> {
+    let result = get(url);
+    if result[0] == "error" {
+        return result;
+    }
+    let value: serde_json::Value = match serde_json::from_str(&result[1]) {
+        Ok(value) => value,
+        Err(e) => return create_response_vector(true, format!("INVALID_JSON: line {} column {}: {}", e.line(), e.column(), e)),
+    };
+    let json_obj = json!({
+        "valid_json": true,
+        "json": value,
+    });
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Get the content at a URL, writing a default first when there is none, e.g. to initialize an app's config file.
+* Two clients initializing the same URL at once may both write their default; the last write wins.
+* @param url The pubky:// or https:// URL, as for `get`
+* @param default_content The content to write when the URL has no content
+* @returns A JSON object `{ "content", "created" }`, `created` being true when the default was written.
+* Errors other than missing content are returned as they are.
+**/
+#[uniffi::export]
+pub fn get_or_put(url: String, default_content: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match Url::parse(trimmed_url) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        if let Err(error) = validate_data_url_scheme(&parsed_url) {
+            return create_response_vector(true, error);
+        }
+        let parsed_url = normalize_data_url(parsed_url);
+
+        let (content, created) = match get_data(&client, parsed_url.clone()).await {
+            Ok(Some(bytes)) => match str::from_utf8(&bytes) {
+                Ok(content) => (content.to_string(), false),
+                Err(_) => return create_response_vector(true, "Invalid UTF-8 sequence".to_string()),
+            },
+            Ok(None) => {
+                match put_data(&client, parsed_url, default_content.as_bytes()).await {
+                    Ok(()) => {},
+                    Err(DataRequestError::CircuitOpen(error)) => return create_response_vector(true, error),
+                    Err(DataRequestError::Failed(error)) => return create_response_vector(true, format!("Failed to put: {}", error)),
+                }
+                (default_content, true)
+            },
+            Err(DataRequestError::CircuitOpen(error)) => return create_response_vector(true, error),
+            Err(DataRequestError::Failed(error)) => return create_response_vector(true, format!("Failed to get: {}", error)),
+        };
+
+        let json_obj = json!({
+            "content": content,
+            "created": created,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Publish a profile on several homeservers for redundancy, signing up on those that don't know the key yet.
+* The homeservers are written to concurrently and directly, so the key's `_pubky` record is left unchanged:
+* readers keep resolving the profile from the key's current homeserver, and the others hold replicas to fail
+* over to with `publish_homeserver_record`.
+* @param secret_key The secret key of the identity
+* @param profile_json The pubky.app profile, a JSON object
+* @param homeservers The public keys of the homeservers
+* @returns A JSON object mapping each homeserver to `"success"` or the error it failed with
+**/
+#[uniffi::export]
+pub fn create_federated_profile(secret_key: String, profile_json: String, homeservers: Vec<String>) -> Vec<String> {
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    match serde_json::from_str::<serde_json::Value>(&profile_json) {
+        Ok(profile) if profile.is_object() => {},
+        Ok(_) => return create_response_vector(true, "Profile must be a JSON object".to_string()),
+        Err(e) => return create_response_vector(true, format!("Failed to parse profile JSON: {}", e)),
+    }
+    if homeservers.is_empty() {
+        return create_response_vector(true, "At least one homeserver is required".to_string());
+    }
+
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let http_client = match reqwest::Client::builder().cookie_store(true).build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+        let profile = Arc::new(profile_json.into_bytes());
+        let tasks: Vec<(String, JoinHandle<Result<(), String>>)> = homeservers
+            .into_iter()
+            .map(|homeserver| {
+                let client = client.clone();
+                let http_client = http_client.clone();
+                let keypair = keypair.clone();
+                let profile = profile.clone();
+                let task_homeserver = homeserver.clone();
+                let task = tokio::spawn(async move {
+                    let homeserver = PublicKey::try_from(task_homeserver.as_str())
+                        .map_err(|e| format!("Invalid homeserver public key: {}", e))?;
+                    put_on_homeserver(&client, &http_client, &keypair, &homeserver, PROFILE_PATH, &profile).await
+                });
+                (homeserver, task)
+            })
+            .collect();
+
+        let mut results = serde_json::Map::new();
+        for (homeserver, task) in tasks {
+            let status = match task.await {
+                Ok(Ok(())) => {
+                    NETWORK_CLIENT.record_bytes_sent(profile.len());
+                    "success".to_string()
+                },
+                Ok(Err(error)) => error,
+                Err(e) => format!("Task failed: {}", e),
+            };
+            results.insert(homeserver, json!(status));
+        }
+        match serde_json::to_string(&results) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Build a contact card from a user's pubky.app profile, for import into address books
+* @param public_key The public key of the user
+* @returns A JSON object `{ "vcard", "name", "bio", "avatar_url", "url", "public_key" }`, `vcard` being a vCard 4.0 string
+* with the pubky:// URI in `URL` and the public key in `X-PUBKY-KEY`
+**/
+#[uniffi::export]
+pub fn generate_contact_card(public_key: String) -> Vec<String> {
+    let public_key = match PublicKey::try_from(public_key.as_str()) {
+        Ok(public_key) => public_key,
+        Err(e) => return create_response_vector(true, format!("Invalid public key: {}", e)),
+    };
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let profile_url = match Url::parse(&format!("pubky://{}{}", public_key, PROFILE_PATH)) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let bytes = match get_data(&client, profile_url).await {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return create_response_vector(true, format!("No profile found for {}", public_key)),
+            Err(e) => return create_response_vector(true, format!("Failed to get profile: {}", e)),
+        };
+        let profile: serde_json::Value = match serde_json::from_slice(&bytes) {
+            Ok(profile) => profile,
+            Err(e) => return create_response_vector(true, format!("Invalid profile JSON: {}", e)),
+        };
+        let field = |key: &str| profile.get(key).and_then(|value| value.as_str()).filter(|value| !value.is_empty());
+        let (name, bio, avatar_url) = (field("name"), field("bio"), field("avatar_url"));
+
+        let json_obj = json!({
+            "vcard": build_vcard(&public_key, name, bio, avatar_url),
+            "name": name,
+            "bio": bio,
+            "avatar_url": avatar_url,
+            "url": format!("pubky://{}", public_key),
+            "public_key": public_key.to_string(),
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Download the content at a URL straight to a file, without buffering the whole body in memory.
+* The body is written to `<file_path>.part` and renamed once complete, so a failed download never leaves a truncated file behind.
+* @param url The pubky:// or https:// URL to fetch
+* @param file_path Where to write the content; an existing file is replaced
+* @returns A JSON object `{ "path", "bytes" }`
+**/
+#[uniffi::export]
+pub fn get_to_file(url: String, file_path: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match Url::parse(trimmed_url) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        if let Err(error) = validate_data_url_scheme(&parsed_url) {
+            return create_response_vector(true, error);
+        }
+        let https_url = match pubky_to_https_url(&client, &parsed_url).await {
+            Ok(url) => url,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let http_client = match reqwest::Client::builder().build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+        let mut response = match http_client.get(https_url).send().await {
+            Ok(response) => response,
+            Err(error) => return create_response_vector(true, format!("Request failed: {}", error)),
+        };
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return create_response_vector(true, "No data returned".to_string());
+        }
+        if !response.status().is_success() {
+            return create_response_vector(true, format!("Request failed with status {}", response.status()));
+        }
+
+        let partial_path = format!("{}.part", file_path);
+        let mut file = match tokio::fs::File::create(&partial_path).await {
+            Ok(file) => file,
+            Err(error) => return create_response_vector(true, format!("Failed to create file {}: {}", partial_path, error)),
+        };
+        let mut bytes_written: u64 = 0;
+        let download: Result<(), String> = async {
+            while let Some(chunk) = response.chunk().await.map_err(|e| format!("Failed to read response body: {}", e))? {
+                NETWORK_CLIENT.record_bytes_received(chunk.len());
+                file.write_all(&chunk).await.map_err(|e| format!("Failed to write file: {}", e))?;
+                bytes_written += chunk.len() as u64;
+            }
+            file.flush().await.map_err(|e| format!("Failed to write file: {}", e))
+        }.await;
+        drop(file);
+        if let Err(error) = download {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return create_response_vector(true, error);
+        }
+        if let Err(error) = tokio::fs::rename(&partial_path, &file_path).await {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return create_response_vector(true, format!("Failed to move file to {}: {}", file_path, error));
+        }
+
+        let json_obj = json!({
+            "path": file_path,
+            "bytes": bytes_written,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Get the content at a URL as text, replacing invalid UTF-8 bytes with U+FFFD instead of failing
+* @param url The pubky:// or https:// URL to fetch, as for `get`
+* @returns A JSON object `{ "text", "had_invalid_bytes" }`
+**/
+#[uniffi::export]
+pub fn get_text_lossy(url: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let parsed_url = match parse_data_url(&url) {
+            Ok(url) => url,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let bytes = match get_data(&client, parsed_url).await {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return create_response_vector(true, "No data returned".to_string()),
+            Err(DataRequestError::CircuitOpen(error)) => return create_response_vector(true, error),
+            Err(DataRequestError::Failed(_)) => return create_response_vector(true, "Request failed".to_string()),
+        };
+
+        let json_obj = json!({
+            "text": String::from_utf8_lossy(&bytes),
+            "had_invalid_bytes": str::from_utf8(&bytes).is_err(),
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+// The JSON returned by `resolve` for a signed packet, without `served_by`
+fn signed_packet_to_json(signed_packet: &SignedPacket) -> serde_json::Value {
+    // Convert each ResourceRecord to a JSON value, handling errors appropriately
+    let json_records: Vec<serde_json::Value> = signed_packet.packet().answers
+        .iter()
+        .filter_map(|record| {
+            match resource_record_to_json(record) {
+                Ok(json_value) => Some(json_value),
+                Err(e) => {
+                    eprintln!("Error converting record to JSON: {}", e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let bytes = signed_packet.as_bytes();
+    let public_key = &bytes[..32];
+    let signature = &bytes[32..96];
+    let timestamp = signed_packet.timestamp();
+    let dns_packet = &bytes[104..];
+    let hex: String = signed_packet.encode_hex();
+
+    json!({
+        "signed_packet": hex,
+        "public_key": general_purpose::STANDARD.encode(public_key),
+        "signature": general_purpose::STANDARD.encode(signature),
+        "timestamp": timestamp,
+        "last_seen": signed_packet.last_seen(),
+        "dns_packet": general_purpose::STANDARD.encode(dns_packet),
+        "records": json_records
+    })
+}
+
+/**
+* Resolve a signed packet from a public key. Prefer `resolve_and_cache`, which lets the caller bound how stale the result may be.
+* @param public_key The public key to resolve
+* @returns A vector with two elements: the first element is a boolean indicating success or failure,
+* and the second element is the response data (either an error message or the resolved signed packet)
+**/
+#[uniffi::export]
+pub fn resolve(public_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    // Concurrent calls for the same key share a single lookup
+    runtime.block_on(NETWORK_CLIENT.deduplicate(format!("resolve:{}", public_key), async {
+        let public_key: PublicKey = match public_key.as_str().try_into() {
+            Ok(key) => key,
+            Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+        };
+        let client = get_pubky_client();
+
+        let relays = NETWORK_CLIENT.get_relay_priority();
+        let resolved = if relays.is_empty() {
+            // A fresh cached packet is served without querying the DHT, unless it was cached
+            // longer than `max_resolve_cache_ttl` ago
+            let target = MutableItem::target_from_key(public_key.as_bytes(), &None);
+            let max_cache_age = NETWORK_CLIENT.get_max_resolve_cache_ttl() as u64;
+            let pkarr_settings = pkarr::Settings::default();
+            let cache_hit = match client.pkarr().cache().get_read_only(&target) {
+                Some(cached) if pkarr::system_time().saturating_sub(*cached.last_seen()) / 1_000_000 > max_cache_age => {
+                    expire_cached_packet(&client, &public_key);
+                    false
+                },
+                Some(cached) => cached.expires_in(pkarr_settings.minimum_ttl, pkarr_settings.maximum_ttl) > 0,
+                None => false,
+            };
+
+            let started = Instant::now();
+            let result = client.pkarr().resolve(&public_key).await;
+            NETWORK_CLIENT.performance_stats().record_resolve(started.elapsed(), cache_hit);
+            match result {
+                Ok(signed_packet) => Ok(signed_packet.map(|signed_packet| (signed_packet, "dht".to_string()))),
+                Err(e) => Err(format!("Failed to resolve: {}", e)),
+            }
+        } else {
+            resolve_with_relay_priority(&relays, &public_key).await
+        };
+
+        match resolved {
+            Ok(Some((signed_packet, served_by))) => {
+                let mut json_obj = signed_packet_to_json(&signed_packet);
+                json_obj["served_by"] = json!(served_by);
+
+                let json_str = serde_json::to_string(&json_obj)
+                    .expect("Failed to convert JSON object to string");
+
+                create_response_vector(false, json_str)
+            },
+            Ok(None) => {
+                create_response_vector(true, "No signed packet found".to_string())
+            }
+            Err(error) => {
+                create_response_vector(true, error)
+            }
+        }
+    }))
+}
+
+/**
+* Resolve a public key like `resolve`, rendering its records as zone file text for DNS tooling
+* @param public_key The public key to resolve
+* @returns The records in presentation format, one `<name> <ttl> <class> <type> <rdata>` line each
+**/
+#[uniffi::export]
+pub fn resolve_zonefile(public_key: String) -> Vec<String> {
+    let result = resolve(public_key);
+    if result[0] == "error" {
+        return result;
+    }
+    let json_obj: serde_json::Value = match serde_json::from_str(&result[1]) {
+        Ok(json_obj) => json_obj,
+        Err(e) => return create_response_vector(true, format!("Failed to parse resolved packet: {}", e)),
+    };
+    let signed_packet = match json_obj["signed_packet"]
+        .as_str()
+        .and_then(|packet_hex| hex::decode(packet_hex).ok())
+        .and_then(|bytes| SignedPacket::from_bytes(&Bytes::from(bytes)).ok())
+    {
+        Some(signed_packet) => signed_packet,
+        None => return create_response_vector(true, "Failed to decode resolved packet".to_string()),
+    };
+
+    let mut lines = Vec::with_capacity(signed_packet.packet().answers.len());
+    for record in &signed_packet.packet().answers {
+        match resource_record_to_zone_line(record) {
+            Ok(line) => lines.push(line),
+            Err(error) => return create_response_vector(true, error),
+        }
+    }
+    create_response_vector(false, lines.join("\n"))
+}
+
+/**
+* Publish records authored as zone file text, replacing every record the key had published. Parse errors are reported
+* with their line number; see `resolve_zonefile` for the reverse.
+* @param zonefile_text Records in presentation format: A, AAAA, TXT, CNAME, NS, MX, SRV, SVCB and HTTPS, one per line,
+* with names relative to the key or qualified with it, `@` standing for the key itself
+* @param secret_key The secret key to sign with
+* @returns The public key on success
+**/
+#[uniffi::export]
+pub fn publish_zonefile(zonefile_text: String, secret_key: String) -> Vec<String> {
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let records = match records_from_zonefile(&zonefile_text) {
+        Ok(records) if records.is_empty() => return create_response_vector(true, "Zone file has no records".to_string()),
+        Ok(records) => records,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let mut packet = Packet::new_reply(0);
+    packet.answers.extend(records);
+    let signed_packet = match SignedPacket::from_packet(&keypair, &packet) {
+        Ok(signed_packet) => signed_packet,
+        Err(e) => return create_response_vector(true, format!("Failed to create signed packet: {}", e)),
+    };
+
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        match publish_signed_packet(&client, &signed_packet).await {
+            Ok(()) => create_response_vector(false, keypair.public_key().to_string()),
+            Err(e) => create_response_vector(true, publish_error_message(&e)),
+        }
+    })
+}
+
+/**
+* Resolve the records a key had published at a past time, for auditing.
+* Neither the DHT nor pkarr relays keep older packets, only the latest one, so this succeeds only when
+* the latest packet was already published at `timestamp_micros`; otherwise it fails with `HISTORY_UNAVAILABLE`.
+* @param public_key The zbase32 encoded public key
+* @param timestamp_micros The point in time, in microseconds since the Unix epoch
+* @returns A JSON object `{ "requested_timestamp", "served_timestamp", "records" }`, where `served_timestamp` is
+* the timestamp of the packet that was valid at the requested time
+**/
+#[uniffi::export]
+pub fn resolve_at(public_key: String, timestamp_micros: u64) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let public_key: PublicKey = match public_key.as_str().try_into() {
+            Ok(key) => key,
+            Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+        };
+        let client = get_pubky_client();
+
+        let signed_packet = match client.pkarr().resolve(&public_key).await {
+            Ok(Some(signed_packet)) => signed_packet,
+            Ok(None) => return create_response_vector(true, "No signed packet found".to_string()),
+            Err(e) => return create_response_vector(true, format!("Failed to resolve: {}", e)),
+        };
+        if signed_packet.timestamp() > timestamp_micros {
+            return create_response_vector(true, format!(
+                "HISTORY_UNAVAILABLE: the latest packet was published at {}, after the requested time, and older packets are not kept",
+                signed_packet.timestamp()
+            ));
+        }
+
+        let records: Vec<serde_json::Value> = signed_packet.packet().answers
+            .iter()
+            .filter_map(|record| resource_record_to_json(record).ok())
+            .collect();
+        let json_obj = json!({
+            "requested_timestamp": timestamp_micros,
+            "served_timestamp": signed_packet.timestamp(),
+            "records": records,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+// Mark a key's cached packet as expired, otherwise pkarr serves it without a lookup until its TTL runs out
+fn expire_cached_packet(client: &PubkyClient, public_key: &PublicKey) {
+    let cache = client.pkarr().cache();
+    let target = MutableItem::target_from_key(public_key.as_bytes(), &None);
+    if let Some(mut cached_packet) = cache.get_read_only(&target) {
+        cached_packet.set_last_seen(&0);
+        cache.put(&target, &cached_packet);
+    }
+}
+
+/**
+* Resolve a public key, serving it from the cache when it was fetched at most `max_age_secs` ago.
+* This is the recommended way to resolve: unlike `resolve`, which serves cached packets for as long as
+* their TTL allows, callers choose how fresh the data must be. A live lookup updates the cache.
+* @param public_key The zbase32 encoded public key
+* @param max_age_secs The maximum age of a cached packet, in seconds
+* @returns The same JSON object as `resolve`, plus `cached` (whether it was served from the cache)
+* and `age_seconds` (time since the packet was fetched)
+**/
+#[uniffi::export]
+pub fn resolve_and_cache(public_key: String, max_age_secs: u64) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let public_key: PublicKey = match public_key.as_str().try_into() {
+            Ok(key) => key,
+            Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+        };
+        let client = get_pubky_client();
+        let cache = client.pkarr().cache();
+        let target = MutableItem::target_from_key(public_key.as_bytes(), &None);
+        let age_seconds = |signed_packet: &SignedPacket| pkarr::system_time().saturating_sub(*signed_packet.last_seen()) / 1_000_000;
+
+        let (signed_packet, cached) = match cache.get(&target) {
+            Some(cached_packet) if age_seconds(&cached_packet) <= max_age_secs => (cached_packet, true),
+            cached_packet => {
+                if cached_packet.is_some() {
+                    expire_cached_packet(&client, &public_key);
+                }
+                match client.pkarr().resolve(&public_key).await {
+                    Ok(Some(signed_packet)) => (signed_packet, false),
+                    Ok(None) => return create_response_vector(true, "No signed packet found".to_string()),
+                    Err(e) => return create_response_vector(true, format!("Failed to resolve: {}", e)),
+                }
+            }
+        };
+
+        let mut json_obj = signed_packet_to_json(&signed_packet);
+        json_obj["cached"] = json!(cached);
+        json_obj["age_seconds"] = json!(age_seconds(&signed_packet));
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+// Try each relay in order, each with its own deadline, returning the first packet found and the relay that served it
+async fn resolve_with_relay_priority(relays: &[String], public_key: &PublicKey) -> Result<Option<(SignedPacket, String)>, String> {
+    let mut errors: Vec<String> = Vec::new();
+    let mut not_found = false;
+    for relay in relays {
+        let relay_client = match get_relay_client(vec![relay.clone()]) {
+            Ok(relay_client) => relay_client,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+        match time::timeout(Duration::from_millis(RELAY_PRIORITY_TIMEOUT_MS), relay_client.resolve(public_key)).await {
+            Ok(Ok(Some(signed_packet))) => return Ok(Some((signed_packet, relay.clone()))),
+            Ok(Ok(None)) => not_found = true,
+            Ok(Err(e)) => errors.push(format!("{}: {}", relay, e)),
+            Err(_) => errors.push(format!("{}: timed out", relay)),
+        }
+    }
+    if not_found {
+        return Ok(None);
+    }
+    Err(format!("Failed to resolve from relays: {}", errors.join("; ")))
+}
+
+/**
+* Resolve a key from exactly one pkarr relay, bypassing the DHT, the cache and any relay priority,
+* e.g. to diagnose relay-specific staleness or availability
+* @param public_key The zbase32 encoded public key
+* @param relay_url The base URL of the relay
+* @returns A JSON object `{ "relay", "found" }`, with the same fields as `resolve` when the relay had the packet
+**/
+#[uniffi::export]
+pub fn resolve_from_relay(public_key: String, relay_url: String) -> Vec<String> {
+    let public_key: PublicKey = match public_key.as_str().try_into() {
+        Ok(key) => key,
+        Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+    };
+    let relay_client = match get_relay_client(vec![relay_url.clone()]) {
+        Ok(relay_client) => relay_client,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let mut json_obj = match relay_client.resolve(&public_key).await {
+            Ok(Some(signed_packet)) => {
+                let mut json_obj = signed_packet_to_json(&signed_packet);
+                json_obj["found"] = json!(true);
+                json_obj
+            },
+            Ok(None) => json!({ "found": false }),
+            Err(e) => return create_response_vector(true, format!("Failed to resolve from {}: {}", relay_url, e)),
+        };
+        json_obj["relay"] = json!(relay_url.trim_end_matches('/'));
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Make `resolve` query relays in the given order instead of the DHT, falling back to the next relay
+* when one fails, times out (after 3 seconds) or doesn't know the key. The relay that served a
+* packet is reported in the `served_by` field of `resolve`.
+* @param relays_json A JSON array of relay URLs, most preferred first. An empty array resolves through the DHT again.
+**/
+#[uniffi::export]
+pub fn set_relay_priority(relays_json: String) -> Vec<String> {
+    let relays: Vec<String> = match serde_json::from_str(&relays_json) {
+        Ok(relays) => relays,
+        Err(e) => return create_response_vector(true, format!("Failed to parse relays JSON: {}", e)),
+    };
+    let relays: Vec<String> = relays.into_iter().map(|relay| relay.trim_end_matches('/').to_string()).collect();
+    for relay in &relays {
+        if let Err(error) = Url::parse(relay) {
+            return create_response_vector(true, format!("Invalid relay URL {}: {}", relay, error));
+        }
+    }
+    NETWORK_CLIENT.set_relay_priority(relays.clone());
+
+    match serde_json::to_string(&relays) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Resolve the records of a public key, grouped by record type
+* @param public_key The public key to resolve
+* @returns A JSON object mapping each record type (e.g. "TXT", "HTTPS") to its records,
+* in the same format as the `records` returned by `resolve`
+**/
+#[uniffi::export]
+pub fn resolve_grouped(public_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let public_key = match PublicKey::try_from(public_key.as_str()) {
+            Ok(key) => key,
+            Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+        };
+        let client = get_pubky_client();
+        let signed_packet = match client.pkarr().resolve(&public_key).await {
+            Ok(Some(signed_packet)) => signed_packet,
+            Ok(None) => return create_response_vector(true, "No signed packet found".to_string()),
+            Err(e) => return create_response_vector(true, format!("Failed to resolve: {}", e)),
+        };
+
+        let mut groups = serde_json::Map::new();
+        for record in signed_packet.packet().answers.iter() {
+            let json_record = match resource_record_to_json(record) {
+                Ok(json_value) => json_value,
+                Err(e) => {
+                    eprintln!("Error converting record to JSON: {}", e);
+                    continue;
+                }
+            };
+            let record_type = json_record["rdata"]["type"].as_str().unwrap_or("UNKNOWN").to_string();
+            if let Some(records) = groups
+                .entry(record_type)
+                .or_insert_with(|| json!([]))
+                .as_array_mut()
+            {
+                records.push(json_record);
+            }
+        }
+
+        match serde_json::to_string(&groups) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Deprecated: use `resolve_grouped`, which this only delegates to. Kept for bindings already calling it,
+* and not marked `#[deprecated]` because the generated scaffolding would warn on every build.
+* @param public_key The public key to resolve
+* @returns A JSON object mapping each record type present to its records
+**/
+#[uniffi::export]
+pub fn get_all_dns_records_grouped(public_key: String) -> Vec<String> {
+    resolve_grouped(public_key)
+}
+
+/// Per-item deadline of bulk operations when the caller passes a timeout of 0, in milliseconds
+pub const DEFAULT_BULK_ITEM_TIMEOUT_MS: u64 = 10_000;
+
+fn bulk_item_timeout(timeout_ms: u64) -> Duration {
+    Duration::from_millis(if timeout_ms == 0 { DEFAULT_BULK_ITEM_TIMEOUT_MS } else { timeout_ms })
+}
+
+/**
+* Resolve many public keys concurrently. A slow or unreachable key does not hold up the others:
+* each lookup has its own deadline and gets its own status.
+* @param public_keys_json A JSON array of z-base32 encoded public keys
+* @param timeout_ms The per-key deadline in milliseconds, 0 for the default (10 seconds)
+* @returns A JSON array, in input order, of `{ "public_key", "status", "timed_out" }` objects with
+* `signed_packet`, `timestamp` and `records` on success, or `error` otherwise
+**/
+#[uniffi::export]
+pub fn resolve_many(public_keys_json: String, timeout_ms: u64) -> Vec<String> {
+    let keys: Vec<String> = match serde_json::from_str(&public_keys_json) {
+        Ok(keys) => keys,
+        Err(e) => return create_response_vector(true, format!("Failed to parse public keys JSON: {}", e)),
+    };
+    let timeout = bulk_item_timeout(timeout_ms);
+
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let tasks: Vec<_> = keys
+            .into_iter()
+            .map(|key| {
+                let client = get_pubky_client();
+                tokio::spawn(async move {
+                    let public_key = match PublicKey::try_from(key.as_str()) {
+                        Ok(public_key) => public_key,
+                        Err(e) => return json!({
+                            "public_key": key,
+                            "status": "error",
+                            "timed_out": false,
+                            "error": format!("Invalid zbase32 encoded key: {}", e),
+                        }),
+                    };
+                    match time::timeout(timeout, client.pkarr().resolve(&public_key)).await {
+                        Ok(Ok(Some(signed_packet))) => {
+                            let records: Vec<serde_json::Value> = signed_packet.packet().answers
+                                .iter()
+                                .filter_map(|record| resource_record_to_json(record).ok())
+                                .collect();
+                            json!({
+                                "public_key": key,
+                                "status": "success",
+                                "timed_out": false,
+                                "signed_packet": signed_packet.encode_hex::<String>(),
+                                "timestamp": signed_packet.timestamp(),
+                                "records": records,
+                            })
+                        },
+                        Ok(Ok(None)) => json!({
+                            "public_key": key,
+                            "status": "error",
+                            "timed_out": false,
+                            "error": "No signed packet found",
+                        }),
+                        Ok(Err(e)) => json!({
+                            "public_key": key,
+                            "status": "error",
+                            "timed_out": false,
+                            "error": format!("Failed to resolve: {}", e),
+                        }),
+                        Err(_) => json!({
+                            "public_key": key,
+                            "status": "error",
+                            "timed_out": true,
+                            "error": "Timed out",
+                        }),
+                    }
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            match task.await {
+                Ok(result) => results.push(result),
+                Err(e) => results.push(json!({
+                    "status": "error",
+                    "timed_out": false,
+                    "error": format!("Task failed: {}", e),
+                })),
+            }
+        }
+
+        match serde_json::to_string(&results) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Resolve the homeserver of many public keys concurrently, e.g. for a contacts screen
+* @param public_keys_json A JSON array of z-base32 encoded public keys
+* @returns A JSON array, in input order, of `{ "pubky", "homeserver", "endpoint", "exists" }` objects,
+* where `exists` tells whether the key published a homeserver. Keys that can't be resolved within
+* the default bulk deadline have `exists: false` and `timed_out: true`.
+**/
+#[uniffi::export]
+pub fn resolve_contacts(public_keys_json: String) -> Vec<String> {
+    let keys: Vec<String> = match serde_json::from_str(&public_keys_json) {
+        Ok(keys) => keys,
+        Err(e) => return create_response_vector(true, format!("Failed to parse public keys JSON: {}", e)),
+    };
+    let timeout = bulk_item_timeout(0);
+
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let tasks: Vec<_> = keys
+            .into_iter()
+            .map(|key| {
+                let client = get_pubky_client();
+                tokio::spawn(async move {
+                    let public_key = match PublicKey::try_from(key.as_str()) {
+                        Ok(public_key) => public_key,
+                        Err(e) => return json!({
+                            "pubky": key,
+                            "homeserver": null,
+                            "endpoint": null,
+                            "exists": false,
+                            "error": format!("Invalid zbase32 encoded key: {}", e),
+                        }),
+                    };
+                    let lookup = async {
+                        let signed_packet = client.pkarr().resolve(&public_key).await.ok().flatten()?;
+                        let (homeserver, port) = get_svcb_target(&signed_packet, HOMESERVER_RECORD_NAME)?;
+                        let endpoint = resolve_homeserver_endpoint(&client, &homeserver, port).await;
+                        Some((homeserver, endpoint))
+                    };
+                    match time::timeout(timeout, lookup).await {
+                        Ok(Some((homeserver, endpoint))) => json!({
+                            "pubky": public_key.to_string(),
+                            "homeserver": homeserver,
+                            "endpoint": endpoint,
+                            "exists": true,
+                        }),
+                        Ok(None) => json!({
+                            "pubky": public_key.to_string(),
+                            "homeserver": null,
+                            "endpoint": null,
+                            "exists": false,
+                        }),
+                        Err(_) => json!({
+                            "pubky": public_key.to_string(),
+                            "homeserver": null,
+                            "endpoint": null,
+                            "exists": false,
+                            "timed_out": true,
+                        }),
+                    }
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            match task.await {
+                Ok(result) => results.push(result),
+                Err(e) => results.push(json!({
+                    "exists": false,
+                    "error": format!("Task failed: {}", e),
+                })),
+            }
+        }
+
+        match serde_json::to_string(&results) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Put many files concurrently, each with its own deadline and status, see `resolve_many`
+* @param items_json A JSON array of `{ "url", "content" }` objects
+* @param timeout_ms The per-item deadline in milliseconds, 0 for the default (10 seconds)
+* @returns A JSON array, in input order, of `{ "url", "status", "timed_out" }` objects, with `error` on failure.
+* A timed out put may still have reached the homeserver.
+**/
+#[uniffi::export]
+pub fn put_many(items_json: String, timeout_ms: u64) -> Vec<String> {
+    #[derive(serde::Deserialize)]
+    struct PutItem {
+        url: String,
+        content: String,
+    }
+    let items: Vec<PutItem> = match serde_json::from_str(&items_json) {
+        Ok(items) => items,
+        Err(e) => return create_response_vector(true, format!("Failed to parse items JSON: {}", e)),
+    };
+    let timeout = bulk_item_timeout(timeout_ms);
+
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let tasks: Vec<_> = items
+            .into_iter()
+            .map(|item| {
+                let client = get_pubky_client();
+                tokio::spawn(async move {
+                    let url = item.url.trim_end_matches('/').to_string();
+                    let parsed_url = match Url::parse(&url) {
+                        Ok(parsed_url) => parsed_url,
+                        Err(_) => return json!({ "url": url, "status": "error", "timed_out": false, "error": "Failed to parse URL" }),
+                    };
+                    if let Err(error) = validate_data_url_scheme(&parsed_url) {
+                        return json!({ "url": url, "status": "error", "timed_out": false, "error": error });
+                    }
+                    match time::timeout(timeout, put_data(&client, parsed_url, item.content.as_bytes())).await {
+                        Ok(Ok(())) => json!({ "url": url, "status": "success", "timed_out": false }),
+                        Ok(Err(e)) => json!({ "url": url, "status": "error", "timed_out": false, "error": format!("Failed to put: {}", e) }),
+                        Err(_) => json!({ "url": url, "status": "error", "timed_out": true, "error": "Timed out" }),
+                    }
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            match task.await {
+                Ok(result) => results.push(result),
+                Err(e) => results.push(json!({
+                    "status": "error",
+                    "timed_out": false,
+                    "error": format!("Task failed: {}", e),
+                })),
+            }
+        }
+
+        match serde_json::to_string(&results) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Write several related files all-or-nothing, e.g. a post with its metadata and attachment. The files are put
+* one after the other; when a put fails, the files already written are deleted again. The rollback is best-effort,
+* not atomic: other clients may see the partial write, a deletion can fail too, and files that existed before are
+* deleted rather than restored to their previous content.
+* @param operations_json A JSON array of `{ "url", "content" }` objects, written in order
+* @returns `{ "written": N }` on success. On failure the error is a JSON object `{ "error", "failed_url",
+* "rolled_back", "rollback_failed" }`, `rolled_back` being false when some written files could not be deleted.
+**/
+#[uniffi::export]
+pub fn atomic_write(operations_json: String) -> Vec<String> {
+    #[derive(serde::Deserialize)]
+    struct WriteOperation {
+        url: String,
+        content: String,
+    }
+    let operations: Vec<WriteOperation> = match serde_json::from_str(&operations_json) {
+        Ok(operations) => operations,
+        Err(e) => return create_response_vector(true, format!("Failed to parse operations JSON: {}", e)),
+    };
+
+    // Check every URL before writing anything, so invalid input never needs a rollback
+    let mut urls = Vec::with_capacity(operations.len());
+    for operation in &operations {
+        let parsed_url = match Url::parse(operation.url.trim_end_matches('/')) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, format!("Failed to parse URL: {}", operation.url)),
+        };
+        if let Err(error) = validate_data_url_scheme(&parsed_url) {
+            return create_response_vector(true, error);
+        }
+        urls.push(normalize_data_url(parsed_url));
+    }
+
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let mut written: Vec<&Url> = Vec::new();
+        for (url, operation) in urls.iter().zip(&operations) {
+            if let Err(error) = put_data(&client, url.clone(), operation.content.as_bytes()).await {
+                let mut rollback_failed = Vec::new();
+                for written_url in written.iter().rev() {
+                    if client.delete(written_url.as_str()).await.is_err() {
+                        rollback_failed.push(written_url.to_string());
+                    }
+                }
+                let json_obj = json!({
+                    "error": format!("Failed to put: {}", error),
+                    "failed_url": url.to_string(),
+                    "rolled_back": rollback_failed.is_empty(),
+                    "rollback_failed": rollback_failed,
+                });
+                return match serde_json::to_string(&json_obj) {
+                    Ok(json) => create_response_vector(true, json),
+                    Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+                };
+            }
+            written.push(url);
+        }
+
+        let json_obj = json!({ "written": written.len() });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Resolve only where a public key lives: its homeserver and the homeserver's endpoint
+* @param public_key The public key to resolve
+* @returns A JSON object `{ "pubky", "homeserver", "endpoint", "last_seen" }` where
+* `homeserver` and `endpoint` are null when they could not be found
+**/
+#[uniffi::export]
+pub fn resolve_identity(public_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let public_key: PublicKey = match public_key.as_str().try_into() {
+            Ok(key) => key,
+            Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+        };
+        let client = get_pubky_client();
+
+        let signed_packet = match client.pkarr().resolve(&public_key).await {
+            Ok(Some(signed_packet)) => signed_packet,
+            Ok(None) => return create_response_vector(true, "No signed packet found".to_string()),
+            Err(e) => return create_response_vector(true, format!("Failed to resolve: {}", e)),
+        };
+
+        let homeserver_record = get_svcb_target(&signed_packet, "_pubky");
+        let endpoint = match &homeserver_record {
+            Some((target, port)) => resolve_homeserver_endpoint(&client, target, *port).await,
+            None => None,
+        };
+
+        let json_obj = json!({
+            "pubky": public_key.to_string(),
+            "homeserver": homeserver_record.map(|(target, _)| target),
+            "endpoint": endpoint,
+            "last_seen": signed_packet.last_seen(),
+        });
+
+        let json_str = match serde_json::to_string(&json_obj) {
+            Ok(json) => json,
+            Err(e) => return create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        };
+        create_response_vector(false, json_str)
+    })
+}
+
+/**
+* Start resolving public keys in the background so later `resolve` calls hit the pkarr cache
+* @param public_keys_json A JSON array of z-base32 encoded public keys
+* @returns A JSON object `{ "prefetch_started": N }`, returned without waiting for the lookups
+**/
+#[uniffi::export]
+pub fn preload_public_keys(public_keys_json: String) -> Vec<String> {
+    let keys: Vec<String> = match serde_json::from_str(&public_keys_json) {
+        Ok(keys) => keys,
+        Err(e) => return create_response_vector(true, format!("Failed to parse public keys JSON: {}", e)),
+    };
+    let mut public_keys: Vec<PublicKey> = Vec::with_capacity(keys.len());
+    for key in &keys {
+        match PublicKey::try_from(key.as_str()) {
+            Ok(public_key) => public_keys.push(public_key),
+            Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key {}: {}", key, e)),
+        }
+    }
+
+    let runtime = TOKIO_RUNTIME.clone();
+    let prefetch_started = public_keys.len();
+    for public_key in public_keys {
+        let client = get_pubky_client();
+        // Resolved packets are kept in the client's pkarr cache
+        runtime.spawn(async move {
+            let _ = client.pkarr().resolve(&public_key).await;
+        });
+    }
+
+    let json_obj = json!({
+        "prefetch_started": prefetch_started,
+    });
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+// The signed packet `publish` publishes: a single TXT record with a 30 second TTL
+fn txt_signed_packet(record_name: &str, record_content: &str, keypair: &Keypair) -> Result<SignedPacket, String> {
+    let mut packet = dns::Packet::new_reply(0);
+
+    let dns_name = dns::Name::new(record_name)
+        .map_err(|e| format!("Failed to create DNS name: {}", e))?;
+
+    let txt_record = match record_content.try_into() {
+        Ok(value) => RData::TXT(value),
+        Err(e) => return Err(format!("Failed to convert string to TXT record: {}", e)),
+    };
+
+    packet.answers.push(dns::ResourceRecord::new(
+        dns_name,
+        dns::CLASS::IN,
+        30,
+        txt_record,
+    ));
+
+    SignedPacket::from_packet(keypair, &packet)
+        .map_err(|e| format!("Failed to create signed packet: {}", e))
+}
+
+// Publish through the DHT, counting the publish in the performance stats
+async fn publish_signed_packet(client: &PubkyClient, signed_packet: &SignedPacket) -> Result<(), pkarr::Error> {
+    let result = client.pkarr().publish(signed_packet).await;
+    NETWORK_CLIENT.performance_stats().record_publish(result.is_ok());
+    result
+}
+
+/**
+* Publish a TXT record. This replaces all of the key's published records, use `publish_merge` to keep them.
+* @param record_name The name of the TXT record
+* @param record_content The content of the TXT record
+* @param secret_key The secret key of the publisher
+* @returns The public key of the publisher
+**/
+#[uniffi::export]
+pub fn publish(record_name: String, record_content: String, secret_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let signed_packet = match txt_signed_packet(&record_name, &record_content, &keypair) {
+            Ok(signed_packet) => signed_packet,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        match publish_signed_packet(&client, &signed_packet).await {
+            Ok(()) => {
+                create_response_vector(false, keypair.public_key().to_string())
+            }
+            Err(e) => {
+                create_response_vector(true, publish_error_message(&e))
+            }
+        }
+    })
+}
+
+/// Delay between lookups while `publish_and_confirm` waits for the packet
+const PUBLISH_CONFIRM_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/**
+* Publish like `publish`, then wait until the packet can be resolved from the network, so a following `resolve`
+* doesn't race its propagation
+* @param record_name The name of the TXT record
+* @param record_content The content of the TXT record
+* @param secret_key The secret key of the publisher
+* @param timeout_seconds How long to wait for the packet
+* @returns A JSON object `{ "public_key", "confirmed", "elapsed_ms" }`. The publish succeeded even when `confirmed`
+* is false; `confirmed` is also true when a newer packet of the key was resolved instead.
+**/
+#[uniffi::export]
+pub fn publish_and_confirm(record_name: String, record_content: String, secret_key: String, timeout_seconds: u64) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let signed_packet = match txt_signed_packet(&record_name, &record_content, &keypair) {
+            Ok(signed_packet) => signed_packet,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let started = time::Instant::now();
+        if let Err(e) = publish_signed_packet(&client, &signed_packet).await {
+            return create_response_vector(true, publish_error_message(&e));
+        }
+
+        // The published packet is cached locally, so expire it before each lookup to actually query the network
+        let public_key = keypair.public_key();
+        let deadline = started + Duration::from_secs(timeout_seconds);
+        let mut confirmed = false;
+        loop {
+            expire_cached_packet(&client, &public_key);
+            if let Ok(Some(resolved)) = client.pkarr().resolve(&public_key).await {
+                if resolved.timestamp() >= signed_packet.timestamp() {
+                    confirmed = true;
+                    break;
+                }
+            }
+            if time::Instant::now() + PUBLISH_CONFIRM_POLL_INTERVAL > deadline {
+                break;
+            }
+            time::sleep(PUBLISH_CONFIRM_POLL_INTERVAL).await;
+        }
+
+        let json_obj = json!({
+            "public_key": public_key.to_string(),
+            "confirmed": confirmed,
+            "elapsed_ms": started.elapsed().as_millis() as u64,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/// Delay before the first retry of `publish_with_retry`, doubled for every further attempt
+const PUBLISH_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest delay between two `publish_with_retry` attempts
+const PUBLISH_RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/**
+* Publish like `publish`, retrying failed publishes with exponential backoff
+* (500ms, 1s, 2s, ... capped at 30 seconds between attempts)
+* @param max_attempts The maximum number of publish attempts, at least 1
+* @returns The public key on success, otherwise the error of the last attempt.
+* Waits at least as long as a rate-limiting relay's `retry_after_seconds` before retrying.
+**/
+#[uniffi::export]
+pub fn publish_with_retry(record_name: String, record_content: String, secret_key: String, max_attempts: u32) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let signed_packet = match txt_signed_packet(&record_name, &record_content, &keypair) {
+            Ok(signed_packet) => signed_packet,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let max_attempts = max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match publish_signed_packet(&client, &signed_packet).await {
+                Ok(()) => return create_response_vector(false, keypair.public_key().to_string()),
+                // Republishing the same timestamp would be rejected again
+                Err(e) if publish_clock_skew(&e) => return create_response_vector(true, publish_error_message(&e)),
+                Err(e) if attempt >= max_attempts => {
+                    if publish_rate_limit(&e).is_some() {
+                        return create_response_vector(true, publish_error_message(&e));
+                    }
+                    return create_response_vector(true, format!("Failed to publish after {} attempts: {}", attempt, e))
+                }
+                Err(e) => {
+                    let backoff = PUBLISH_RETRY_BASE_DELAY
+                        .saturating_mul(2u32.saturating_pow(attempt - 1))
+                        .min(PUBLISH_RETRY_MAX_DELAY);
+                    // Never retry sooner than a rate-limiting relay asked for
+                    let delay = match publish_rate_limit(&e) {
+                        Some(Some(retry_after_seconds)) => backoff.max(Duration::from_secs(retry_after_seconds)),
+                        _ => backoff,
+                    };
+                    time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    })
+}
+
+/**
+* Publish a TXT record, merging it into the key's published records instead of replacing them all like `publish`.
+* Existing TXT records with the same name are replaced, other records are kept.
+* @param record_name The name of the TXT record
+* @param record_content The content of the TXT record
+* @param secret_key The secret key of the publisher
+* @returns The public key of the publisher
+**/
+#[uniffi::export]
+pub fn publish_merge(record_name: String, record_content: String, secret_key: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let dns_name = match dns::Name::new(&record_name) {
+            Ok(name) => name.into_owned(),
+            Err(e) => return create_response_vector(true, format!("Failed to create DNS name: {}", e)),
+        };
+        let txt_record = match record_content.as_str().try_into() {
+            Ok(value) => RData::TXT(value).into_owned(),
+            Err(e) => {
+                return create_response_vector(true, format!("Failed to convert string to TXT record: {}", e))
+            }
+        };
+        let record = dns::ResourceRecord::new(dns_name, dns::CLASS::IN, 30, txt_record);
+
+        match publish_replacing_record(&client, &keypair, record).await {
+            Ok(()) => create_response_vector(false, keypair.public_key().to_string()),
+            Err(error) => create_response_vector(true, error),
+        }
+    })
+}
+
+/// Public pkarr relays pinged by `find_nearest_relay`
+const KNOWN_RELAYS: [&str; 3] = ["https://relay.pkarr.org", "https://pkarr.pubky.app", "https://pkarr.pubky.org"];
+
+/// Deadline of each ping made by `find_nearest_relay`
+const RELAY_PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/**
+* Find the public pkarr relay with the lowest latency, pinging each known relay concurrently with a HEAD request
+* (5 seconds timeout). Relays that don't answer in time are left out.
+* @returns A JSON array of `{ "url", "latency_ms" }` objects, fastest first. The first relay is the one to pass to
+* `relay_signed_packet` or `resolve_from_relay`; the URLs can also be given as they are to `set_relay_priority`.
+**/
+#[uniffi::export]
+pub fn find_nearest_relay() -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let http_client = match reqwest::Client::builder().timeout(RELAY_PING_TIMEOUT).build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+        let tasks: Vec<JoinHandle<Option<(String, u64)>>> = KNOWN_RELAYS
+            .iter()
+            .map(|relay| {
+                let http_client = http_client.clone();
+                let relay = relay.to_string();
+                tokio::spawn(async move {
+                    let started = Instant::now();
+                    // Any HTTP response shows the relay is up, the status doesn't matter for latency
+                    http_client.head(&relay).send().await.ok()?;
+                    Some((relay, started.elapsed().as_millis() as u64))
+                })
+            })
+            .collect();
+
+        let mut relays = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            if let Ok(Some(relay)) = task.await {
+                relays.push(relay);
+            }
+        }
+        if relays.is_empty() {
+            return create_response_vector(true, "No known relay is reachable".to_string());
+        }
+        relays.sort_by_key(|(_, latency_ms)| *latency_ms);
+
+        let json_array: Vec<serde_json::Value> = relays
+            .into_iter()
+            .map(|(url, latency_ms)| json!({ "url": url, "latency_ms": latency_ms }))
+            .collect();
+        match serde_json::to_string(&json_array) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/// Clock offsets beyond this are reported as skewed by `check_clock_skew`
+const CLOCK_SKEW_THRESHOLD: Duration = Duration::from_secs(60);
+
+/**
+* Estimate how far the device clock is off, by comparing it to the `Date` header of a relay's response.
+* Packets are timestamped with the device clock, so a clock running behind makes publishes fail with `CLOCK_SKEW`.
+* @param relay_url The base URL of the relay to compare against
+* @returns A JSON object `{ "relay", "offset_ms", "round_trip_ms", "skewed" }`, `offset_ms` being positive when the
+* device clock is behind the relay's. The `Date` header has a one-second resolution, so smaller offsets are noise;
+* `skewed` is true beyond 60 seconds.
+**/
+#[uniffi::export]
+pub fn check_clock_skew(relay_url: String) -> Vec<String> {
+    let relay_url = relay_url.trim_end_matches('/').to_string();
+    match Url::parse(&relay_url) {
+        Ok(url) if url.scheme() == "https" || url.scheme() == "http" => {},
+        _ => return create_response_vector(true, format!("Invalid relay URL: {}", relay_url)),
+    };
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let http_client = match reqwest::Client::builder().timeout(RELAY_HEALTH_CHECK_TIMEOUT).build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+
+        let sent_at = SystemTime::now();
+        let started = Instant::now();
+        let response = match http_client.head(&relay_url).send().await {
+            Ok(response) => response,
+            Err(error) => return create_response_vector(true, format!("Request failed: {}", error)),
+        };
+        let round_trip = started.elapsed();
+
+        let relay_time = match response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| httpdate::parse_http_date(value).ok())
+        {
+            Some(relay_time) => relay_time,
+            None => return create_response_vector(true, "Relay response has no valid Date header".to_string()),
+        };
+
+        // Assume the relay read its clock halfway through the round trip
+        let local_time = sent_at + round_trip / 2;
+        let offset_ms = match relay_time.duration_since(local_time) {
+            Ok(ahead) => ahead.as_millis() as i64,
+            Err(behind) => -(behind.duration().as_millis() as i64),
+        };
+
+        let json_obj = json!({
+            "relay": relay_url,
+            "offset_ms": offset_ms,
+            "round_trip_ms": round_trip.as_millis() as u64,
+            "skewed": offset_ms.unsigned_abs() > CLOCK_SKEW_THRESHOLD.as_millis() as u64,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Re-publish an existing signed packet to a specific pkarr relay without re-signing it
+* @param packet_hex The hex encoded signed packet, as returned in the `signed_packet` field of `resolve`
+* @param relay_url The base URL of the relay to seed; check it is reachable with `health_check_relay` first
+* @returns The public key of the packet on success
+**/
+#[uniffi::export]
+pub fn relay_signed_packet(packet_hex: String, relay_url: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let bytes = match hex::decode(&packet_hex) {
+            Ok(bytes) => bytes,
+            Err(error) => return create_response_vector(true, format!("Failed to decode packet hex: {}", error)),
+        };
+        if bytes.len() < 104 {
+            return create_response_vector(true, format!("Signed packet must be at least 104 bytes, got {}", bytes.len()));
+        }
+        if bytes[32..96].iter().all(|byte| *byte == 0) {
+            return create_response_vector(true, "Signed packet has an empty signature".to_string());
+        }
+        let signed_packet = match SignedPacket::from_bytes(&Bytes::from(bytes)) {
+            Ok(signed_packet) => signed_packet,
+            Err(error) => return create_response_vector(true, format!("Invalid signed packet: {}", error)),
+        };
+        let relay_client = match get_relay_client(vec![relay_url]) {
+            Ok(client) => client,
+            Err(error) => return create_response_vector(true, error),
+        };
+        match relay_client.publish(&signed_packet).await {
+            Ok(()) => create_response_vector(false, signed_packet.public_key().to_string()),
+            Err(error) if publish_rate_limit(&error).is_some() => create_response_vector(true, publish_error_message(&error)),
+            Err(error) => create_response_vector(true, format!("Failed to publish to relay: {}", error)),
+        }
+    })
+}
+
+/// Deadline of the requests made by `health_check_relay`
+const RELAY_HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/**
+* Check that a pkarr relay is reachable, e.g. before `relay_signed_packet`. Requests `{relay_url}/health`,
+* falling back to a HEAD request to the root when the relay has no health endpoint. Requests time out after 10 seconds.
+* @param relay_url The base URL of the relay
+* @returns A JSON object `{ "reachable": true, "latency_ms", "server_info": { "status", "server", "body" } }`,
+* or `{ "reachable": false, "error" }` on a timeout, connection failure or non-2xx response
+**/
+#[uniffi::export]
+pub fn health_check_relay(relay_url: String) -> Vec<String> {
+    let relay_url = relay_url.trim_end_matches('/').to_string();
+    let health_url = match Url::parse(&format!("{}/health", relay_url)) {
+        Ok(url) if url.scheme() == "https" || url.scheme() == "http" => url,
+        _ => return create_response_vector(true, format!("Invalid relay URL: {}", relay_url)),
+    };
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let http_client = match reqwest::Client::builder().timeout(RELAY_HEALTH_CHECK_TIMEOUT).build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+
+        let started = Instant::now();
+        let mut response = http_client.get(health_url).send().await;
+        if matches!(&response, Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND) {
+            response = http_client.head(&relay_url).send().await;
+        }
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let json_obj = match response {
+            Ok(response) if response.status().is_success() => {
+                let status = response.status().as_u16();
+                let server = response
+                    .headers()
+                    .get(reqwest::header::SERVER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string());
+                let body = response.text().await.unwrap_or_default();
+                let body = serde_json::from_str::<serde_json::Value>(&body).unwrap_or(serde_json::Value::String(body));
+                json!({
+                    "reachable": true,
+                    "latency_ms": latency_ms,
+                    "server_info": {
+                        "status": status,
+                        "server": server,
+                        "body": body,
+                    },
+                })
+            },
+            Ok(response) => json!({
+                "reachable": false,
+                "error": format!("Relay responded with status {}", response.status()),
+            }),
+            Err(error) if error.is_timeout() => json!({
+                "reachable": false,
+                "error": format!("Timed out after {} seconds", RELAY_HEALTH_CHECK_TIMEOUT.as_secs()),
+            }),
+            Err(error) => json!({
+                "reachable": false,
+                "error": format!("Request failed: {}", error),
+            }),
+        };
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Compute how long until a signed packet should be re-resolved: its smallest record TTL, counted from the packet's timestamp
+* @param hex The hex encoded signed packet, as returned in the `signed_packet` field of `resolve`
+* @returns A JSON object `{ "remaining_seconds", "stale", "min_ttl" }`. `remaining_seconds` is negative once the packet is stale.
+**/
+#[uniffi::export]
+pub fn packet_remaining_ttl(hex: String) -> Vec<String> {
+    let bytes = match hex::decode(&hex) {
+        Ok(bytes) => bytes,
+        Err(error) => return create_response_vector(true, format!("Failed to decode packet hex: {}", error)),
+    };
+    let signed_packet = match SignedPacket::from_bytes(&Bytes::from(bytes)) {
+        Ok(signed_packet) => signed_packet,
+        Err(error) => return create_response_vector(true, format!("Invalid signed packet: {}", error)),
+    };
+
+    let min_ttl = signed_packet.ttl(0, u32::MAX);
+    let elapsed_seconds = pkarr::system_time().saturating_sub(signed_packet.timestamp()) / 1_000_000;
+    let remaining_seconds = min_ttl as i64 - elapsed_seconds as i64;
+    let json_obj = json!({
+        "remaining_seconds": remaining_seconds,
+        "stale": remaining_seconds <= 0,
+        "min_ttl": min_ttl,
+    });
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Estimate the encoded size of a DNS packet before publishing it
+* @param records_json A JSON array of records, in the same shape as the `records` returned by `resolve`
+* @returns A JSON object `{ "packet_size_bytes", "max_allowed_bytes", "fits" }`. Nothing is signed or published.
+**/
+#[uniffi::export]
+pub fn estimate_dns_packet_size(records_json: String) -> Vec<String> {
+    let records = match records_from_json(&records_json) {
+        Ok(records) => records,
+        Err(error) => return create_response_vector(true, error),
+    };
+
+    // Pkarr appends the signer's z-base32 key (52 characters) to every record name before
+    // encoding, so use a placeholder origin of the same length to get a realistic size
+    let packet_size = match encode_records_for_origin(&records, &"y".repeat(52)) {
+        Ok(bytes) => bytes.len(),
+        Err(error) => return create_response_vector(true, error),
+    };
+
+    let json_obj = json!({
+        "packet_size_bytes": packet_size,
+        "max_allowed_bytes": MAX_DNS_PACKET_SIZE,
+        "fits": packet_size <= MAX_DNS_PACKET_SIZE,
+    });
+
+    let json_str = match serde_json::to_string(&json_obj) {
+        Ok(json) => json,
+        Err(e) => return create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    };
+    create_response_vector(false, json_str)
+}
+
+/**
+* Create a signed packet from records without publishing it, e.g. for tests, relay seeding or precomputing packets offline
+* @param records_json A JSON array of records, in the same shape as the `records` returned by `resolve`
+* @param secret_key The secret key to sign with
+* @returns The hex encoded signed packet, in the same format as the `signed_packet` field of `resolve`
+**/
+#[uniffi::export]
+pub fn create_signed_packet_json(records_json: String, secret_key: String) -> Vec<String> {
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let records = match records_from_json(&records_json) {
+        Ok(records) => records,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let mut packet = Packet::new_reply(0);
+    packet.answers.extend(records);
+
+    match SignedPacket::from_packet(&keypair, &packet) {
+        Ok(signed_packet) => create_response_vector(false, signed_packet.encode_hex()),
+        Err(e) => create_response_vector(true, format!("Failed to create signed packet: {}", e)),
+    }
+}
+
+/**
+* Build the bytes to sign for publishing records, for offline signing: sign them with the key's Ed25519
+* secret key on another device, then pass them with the signature to `publish_presigned`
+* @param records_json A JSON array of records, in the same shape as the `records` returned by `resolve`
+* @param public_key The public key the records will be published under, as record names are relative to it
+* @returns The hex encoded bytes to sign, which hold the packet timestamp and the encoded DNS packet
+**/
+#[uniffi::export]
+pub fn build_unsigned_packet(records_json: String, public_key: String) -> Vec<String> {
+    let public_key = match PublicKey::try_from(public_key.as_str()) {
+        Ok(public_key) => public_key,
+        Err(e) => return create_response_vector(true, format!("Invalid public key: {}", e)),
+    };
+    let records = match records_from_json(&records_json) {
+        Ok(records) => records,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let encoded_packet = match encode_records_for_origin(&records, &public_key.to_z32()) {
+        Ok(encoded_packet) => encoded_packet,
+        Err(error) => return create_response_vector(true, error),
+    };
+    if encoded_packet.len() > MAX_DNS_PACKET_SIZE {
+        return create_response_vector(true, format!("DNS packet is {} bytes, the maximum is {}", encoded_packet.len(), MAX_DNS_PACKET_SIZE));
+    }
+    create_response_vector(false, hex::encode(signable_bytes(pkarr::system_time(), &encoded_packet)))
+}
+
+/**
+* Publish records signed offline, see `build_unsigned_packet`
+* @param packet_bytes The hex encoded bytes returned by `build_unsigned_packet`
+* @param signature The hex encoded Ed25519 signature of those bytes
+* @param public_key The public key that signed them
+* @returns The public key on success
+**/
+#[uniffi::export]
+pub fn publish_presigned(packet_bytes: String, signature: String, public_key: String) -> Vec<String> {
+    let public_key = match PublicKey::try_from(public_key.as_str()) {
+        Ok(public_key) => public_key,
+        Err(e) => return create_response_vector(true, format!("Invalid public key: {}", e)),
+    };
+    let signable = match hex::decode(packet_bytes.trim()) {
+        Ok(signable) => signable,
+        Err(e) => return create_response_vector(true, format!("Failed to decode packet bytes: {}", e)),
+    };
+    let signature: [u8; 64] = match hex::decode(signature.trim()).ok().and_then(|bytes| bytes.try_into().ok()) {
+        Some(signature) => signature,
+        None => return create_response_vector(true, "Signature must be 64 hex encoded bytes".to_string()),
+    };
+    let (timestamp, encoded_packet) = match parse_signable_bytes(&signable) {
+        Ok(parts) => parts,
+        Err(error) => return create_response_vector(true, error),
+    };
+
+    let mut bytes = Vec::with_capacity(104 + encoded_packet.len());
+    bytes.extend_from_slice(public_key.as_bytes());
+    bytes.extend_from_slice(&signature);
+    bytes.extend_from_slice(&timestamp.to_be_bytes());
+    bytes.extend_from_slice(encoded_packet);
+    // Parsing verifies the signature against the public key
+    let signed_packet = match SignedPacket::from_bytes(&Bytes::from(bytes)) {
+        Ok(signed_packet) => signed_packet,
+        Err(e) => return create_response_vector(true, format!("Invalid signed packet: {}", e)),
+    };
+
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        match publish_signed_packet(&client, &signed_packet).await {
+            Ok(()) => create_response_vector(false, public_key.to_string()),
+            Err(e) => create_response_vector(true, publish_error_message(&e)),
+        }
+    })
+}
+
+/**
+* Publish a TXT record with a caller supplied packet timestamp
+* @param record_name The name of the TXT record
+* @param record_content The content of the TXT record
+* @param secret_key The secret key used to sign the packet
+* @param timestamp_micros The packet timestamp in microseconds since the UNIX epoch, or the current time if not set.
+* Timestamps more than one hour in the future are rejected.
+* @returns The public key on success
+**/
 #[uniffi::export]
-pub fn put(url: String, content: String) -> Vec<String> {
+pub fn publish_with_timestamp(record_name: String, record_content: String, secret_key: String, timestamp_micros: Option<u64>) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+
+        let keypair = match get_keypair_from_secret_key(&secret_key) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let now = pkarr::system_time();
+        let timestamp = timestamp_micros.unwrap_or(now);
+        if timestamp > now + MAX_FUTURE_TIMESTAMP_MICROS {
+            return create_response_vector(true, format!("Timestamp {} is too far in the future", timestamp));
+        }
+
+        let mut packet = dns::Packet::new_reply(0);
+
+        let dns_name = match dns::Name::new(&record_name) {
+            Ok(name) => name,
+            Err(e) => return create_response_vector(true, format!("Failed to create DNS name: {}", e)),
+        };
+
+        let txt_record = match record_content.as_str().try_into() {
+            Ok(value) => RData::TXT(value),
+            Err(e) => {
+                return create_response_vector(true, format!("Failed to convert string to TXT record: {}", e))
+            }
+        };
+
+        packet.answers.push(dns::ResourceRecord::new(
+            dns_name,
+            dns::CLASS::IN,
+            30,
+            txt_record,
+        ));
+
+        let signed_packet = match sign_packet_with_timestamp(&keypair, &packet, timestamp) {
+            Ok(signed_packet) => signed_packet,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        match publish_signed_packet(&client, &signed_packet).await {
+            Ok(()) => create_response_vector(false, keypair.public_key().to_string()),
+            Err(e) => create_response_vector(true, publish_error_message(&e)),
+        }
+    })
+}
+
+/**
+* Get the content at a URL, following up to `max_redirects` HTTP redirects
+* @param url The pubky:// or https:// URL to fetch. pubky:// URLs are resolved to their homeserver first.
+* @param max_redirects The maximum number of redirects to follow
+* @returns A JSON object `{ "body", "final_url", "redirect_chain", "redirected" }`
+**/
+#[uniffi::export]
+pub fn get_with_redirects(url: String, max_redirects: u32) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match Url::parse(trimmed_url) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let mut current_url = match pubky_to_https_url(&client, &parsed_url).await {
+            Ok(url) => url,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let http_client = match reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+
+        let mut redirect_chain: Vec<String> = Vec::new();
+        loop {
+            let response = match http_client.get(current_url.clone()).send().await {
+                Ok(response) => response,
+                Err(error) => return create_response_vector(true, format!("Request failed: {}", error)),
+            };
+
+            if response.status().is_redirection() {
+                if redirect_chain.len() as u32 >= max_redirects {
+                    return create_response_vector(true, format!("Too many redirects (max {})", max_redirects));
+                }
+                let location = match response.headers().get(reqwest::header::LOCATION).and_then(|value| value.to_str().ok()) {
+                    Some(location) => location.to_string(),
+                    None => return create_response_vector(true, "Redirect without a Location header".to_string()),
+                };
+                let next_url = match current_url.join(&location) {
+                    Ok(url) => url,
+                    Err(error) => return create_response_vector(true, format!("Invalid redirect location {}: {}", location, error)),
+                };
+                redirect_chain.push(current_url.to_string());
+                current_url = next_url;
+                continue;
+            }
+
+            if !response.status().is_success() {
+                return create_response_vector(true, format!("Request failed with status {}", response.status()));
+            }
+
+            let bytes = match read_data_response(response).await {
+                Ok(bytes) => bytes,
+                Err(error) => return create_response_vector(true, error),
+            };
+            let body = match str::from_utf8(&bytes) {
+                Ok(s) => s.to_string(),
+                Err(_) => return create_response_vector(true, "Invalid UTF-8 sequence".to_string()),
+            };
+
+            let json_obj = json!({
+                "body": body,
+                "final_url": current_url.to_string(),
+                "redirected": !redirect_chain.is_empty(),
+                "redirect_chain": redirect_chain,
+            });
+
+            return match serde_json::to_string(&json_obj) {
+                Ok(json) => create_response_vector(false, json),
+                Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+            };
+        }
+    })
+}
+
+/**
+* Check that a homeserver URL is served by the expected public key, using its `/.well-known/pubky` document
+* @param homeserver_url The http(s) URL of the homeserver
+* @param expected_pubky The public key the homeserver should report
+* @returns A JSON object `{ "match", "reported_pubky" }`
+**/
+#[uniffi::export]
+pub fn verify_homeserver_public_key(homeserver_url: String, expected_pubky: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let expected = match PublicKey::try_from(expected_pubky.as_str()) {
+            Ok(key) => key,
+            Err(e) => return create_response_vector(true, format!("Invalid expected public key: {}", e)),
+        };
+        let well_known_url = match Url::parse(&format!("{}/.well-known/pubky", homeserver_url.trim_end_matches('/'))) {
+            Ok(url) if url.scheme() == "https" || url.scheme() == "http" => url,
+            _ => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+
+        let http_client = match reqwest::Client::builder().build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+        let response = match http_client.get(well_known_url).send().await {
+            Ok(response) => response,
+            Err(error) => return create_response_vector(true, format!("Request failed: {}", error)),
+        };
+        if !response.status().is_success() {
+            return create_response_vector(true, format!("Request failed with status {}", response.status()));
+        }
+        let bytes = match response.bytes().await {
+            Ok(bytes) => bytes,
+            Err(error) => return create_response_vector(true, format!("Failed to read response body: {}", error)),
+        };
+        let document: serde_json::Value = match serde_json::from_slice(&bytes) {
+            Ok(document) => document,
+            Err(error) => return create_response_vector(true, format!("Invalid well-known document: {}", error)),
+        };
+        let reported = match document["public_key"].as_str().map(PublicKey::try_from) {
+            Some(Ok(key)) => key,
+            Some(Err(e)) => return create_response_vector(true, format!("Homeserver reported an invalid public key: {}", e)),
+            None => return create_response_vector(true, "Well-known document is missing public_key".to_string()),
+        };
+
+        let json_obj = json!({
+            "match": reported.as_bytes() == expected.as_bytes(),
+            "reported_pubky": reported.to_string(),
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+#[uniffi::export]
+pub fn list(url: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match Url::parse(&trimmed_url) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let send_res = match list_data(&client, parsed_url, None, None).await {
+            Ok(res) => res,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let json_string = match serde_json::to_string(&send_res) {
+            Ok(json) => json,
+            Err(error) => return create_response_vector(true, format!("Failed to serialize JSON: {}", error)),
+        };
+        create_response_vector(false, json_string)
+    })
+}
+
+/**
+* List a URL that requires a session, signing in first unless the key already has a session allowed to read it
+* @param url The pubky:// URL to list
+* @param secret_key The secret key to sign in with
+* @returns The same JSON array of URLs as `list`
+**/
+#[uniffi::export]
+pub fn list_authenticated(url: String, secret_key: String) -> Vec<String> {
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let path = Url::parse(&url).map(|url| url.path().to_string()).unwrap_or_default();
+    if !account_has_capability(&keypair.public_key().to_string(), &path, Action::Read) {
+        let sign_in_result = sign_in(secret_key);
+        if sign_in_result[0] == "error" {
+            return sign_in_result;
+        }
+    }
+    list(url)
+}
+
+/**
+* List the URLs under a prefix whose file name matches a glob pattern, e.g. `*.jpg`
+* @param url The pubky:// URL of the directory to list
+* @param pattern The glob pattern, where `*` matches any characters and `?` a single character
+* @returns A JSON array of the matching URLs
+**/
+#[uniffi::export]
+pub fn list_filtered(url: String, pattern: String) -> Vec<String> {
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match Url::parse(trimmed_url) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let urls = match list_data(&client, parsed_url, None, None).await {
+            Ok(urls) => urls,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let matches: Vec<String> = urls
+            .into_iter()
+            .filter(|url| {
+                let file_name = url.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+                glob_matches(&pattern, file_name)
+            })
+            .collect();
+
+        match serde_json::to_string(&matches) {
+            Ok(json) => create_response_vector(false, json),
+            Err(error) => create_response_vector(true, format!("Failed to serialize JSON: {}", error)),
+        }
+    })
+}
+
+/**
+* List the entries between two cursors, for syncing a window of entries. The homeserver has no end bound,
+* so pages are fetched from the start cursor and cut off client-side once past the end cursor.
+* @param url The pubky:// URL of the directory to list
+* @param start_cursor The cursor to start after (exclusive), empty to start from the first entry
+* @param end_cursor The last entry to include (inclusive); both cursors are full URLs or paths relative to `url`
+* @param limit The maximum number of entries to return, at least 1
+* @returns A JSON object `{ "items", "fully_covered" }`, where `fully_covered` is false when `limit` was reached
+* before the end of the range
+**/
+#[uniffi::export]
+pub fn list_range(url: String, start_cursor: String, end_cursor: String, limit: u16) -> Vec<String> {
+    if limit == 0 {
+        return create_response_vector(true, "Limit must be at least 1".to_string());
+    }
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let trimmed_url = url.trim_end_matches('/');
+        let parsed_url = match Url::parse(trimmed_url) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let end = list_cursor_url(trimmed_url, &end_cursor);
+
+        let mut items: Vec<String> = Vec::new();
+        let mut cursor = (!start_cursor.is_empty()).then(|| start_cursor.clone());
+        let fully_covered = 'pages: loop {
+            // One entry past the limit tells whether the range continues
+            let page = match list_data(&client, parsed_url.clone(), Some(limit.saturating_add(1)), cursor.as_deref()).await {
+                Ok(page) => page,
+                Err(error) => return create_response_vector(true, error),
+            };
+            if page.is_empty() || page.last() == cursor.as_ref() {
+                break true;
+            }
+            for entry in &page {
+                if *entry > end {
+                    break 'pages true;
+                }
+                if items.len() == limit as usize {
+                    break 'pages false;
+                }
+                items.push(entry.clone());
+            }
+            cursor = page.last().cloned();
+        };
+
+        let json_obj = json!({
+            "items": items,
+            "fully_covered": fully_covered,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(error) => create_response_vector(true, format!("Failed to serialize JSON: {}", error)),
+        }
+    })
+}
+
+/// Maximum number of concurrent deletes made by `delete_filtered`
+const DELETE_FILTERED_CONCURRENCY: usize = 16;
+
+/**
+* Delete every file under a prefix whose file name matches a glob pattern, e.g. `*.tmp`, deleting concurrently
+* @param url The pubky:// URL of the directory; files in its subdirectories are matched too
+* @param pattern The glob pattern, where `*` matches any characters and `?` a single character. Must not be empty.
+* @returns A JSON object `{ "matched", "deleted", "errors" }`, `errors` holding `{ "url", "error" }` for each failed delete
+**/
+#[uniffi::export]
+pub fn delete_filtered(url: String, pattern: String) -> Vec<String> {
+    if pattern.trim().is_empty() {
+        return create_response_vector(true, "Pattern must not be empty".to_string());
+    }
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let prefix = match Url::parse(&format!("{}/", url.trim_end_matches('/'))) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let urls = match list_all(&client, &prefix).await {
+            Ok(urls) => urls,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let matches: Vec<String> = urls
+            .into_iter()
+            .filter(|url| {
+                let file_name = url.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+                glob_matches(&pattern, file_name)
+            })
+            .collect();
+
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(DELETE_FILTERED_CONCURRENCY));
+        let tasks: Vec<(String, JoinHandle<Result<(), String>>)> = matches
+            .iter()
+            .map(|url| {
+                let client = client.clone();
+                let semaphore = semaphore.clone();
+                let task_url = url.clone();
+                let task = tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await.map_err(|e| e.to_string())?;
+                    let parsed_url = Url::parse(&task_url).map_err(|_| "Failed to parse URL".to_string())?;
+                    client.delete(parsed_url).await.map_err(|e| format!("Failed to delete: {}", e))
+                });
+                (url.clone(), task)
+            })
+            .collect();
+
+        let mut deleted = 0;
+        let mut errors = Vec::new();
+        for (url, task) in tasks {
+            match task.await {
+                Ok(Ok(())) => deleted += 1,
+                Ok(Err(error)) => errors.push(json!({ "url": url, "error": error })),
+                Err(error) => errors.push(json!({ "url": url, "error": format!("Delete task failed: {}", error) })),
+            }
+        }
+
+        let json_obj = json!({
+            "matched": matches.len(),
+            "deleted": deleted,
+            "errors": errors,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(error) => create_response_vector(true, format!("Failed to serialize JSON: {}", error)),
+        }
+    })
+}
+
+// Number of files `export_public_records` fetches concurrently, reporting progress after each batch
+const EXPORT_BATCH_SIZE: usize = 10;
+
+// Fetch one file for `export_public_records`
+async fn export_public_record(client: &PubkyClient, http_client: &reqwest::Client, url: &str) -> Result<(Option<String>, Vec<u8>), String> {
+    let parsed_url = Url::parse(url).map_err(|_| "Failed to parse URL".to_string())?;
+    let https_url = pubky_to_https_url(client, &parsed_url).await?;
+    let response = http_client.get(https_url).send().await.map_err(|e| format!("Request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Request failed with status {}", response.status()));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let bytes = read_data_response(response).await?;
+    Ok((content_type, bytes.to_vec()))
+}
+
+/**
+* Export every public file of a user, e.g. to move to another platform. Files under `pubky://{public_key}/pub/` are
+* fetched 10 at a time; after each batch the event listener receives
+* `{ "event": "export_progress", "public_key", "fetched", "total" }`.
+* @param public_key The public key of the user
+* @returns A JSON array of `{ "url", "content_type", "content_base64", "fetched_at" }` objects, `fetched_at` in seconds
+* since the Unix epoch. Files that could not be fetched have a null `content_base64` and an `error`.
+**/
+#[uniffi::export]
+pub fn export_public_records(public_key: String) -> Vec<String> {
+    let public_key = match PublicKey::try_from(public_key.as_str()) {
+        Ok(public_key) => public_key,
+        Err(e) => return create_response_vector(true, format!("Invalid public key: {}", e)),
+    };
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let prefix = match Url::parse(&format!("pubky://{}/pub/", public_key)) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
+        let urls = match list_all(&client, &prefix).await {
+            Ok(urls) => urls,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let http_client = match reqwest::Client::builder().build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
+        };
+
+        let mut records = Vec::with_capacity(urls.len());
+        for batch in urls.chunks(EXPORT_BATCH_SIZE) {
+            let tasks: Vec<_> = batch
+                .iter()
+                .map(|url| {
+                    let client = client.clone();
+                    let http_client = http_client.clone();
+                    let task_url = url.clone();
+                    (url.clone(), tokio::spawn(async move { export_public_record(&client, &http_client, &task_url).await }))
+                })
+                .collect();
+            for (url, task) in tasks {
+                let result = task.await.unwrap_or_else(|e| Err(format!("Task failed: {}", e)));
+                let fetched_at = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or(0);
+                records.push(match result {
+                    Ok((content_type, bytes)) => json!({
+                        "url": url,
+                        "content_type": content_type,
+                        "content_base64": general_purpose::STANDARD.encode(bytes),
+                        "fetched_at": fetched_at,
+                    }),
+                    Err(error) => json!({
+                        "url": url,
+                        "content_type": null,
+                        "content_base64": null,
+                        "fetched_at": fetched_at,
+                        "error": error,
+                    }),
+                });
+            }
+
+            let progress = json!({
+                "event": "export_progress",
+                "public_key": public_key.to_string(),
+                "fetched": records.len(),
+                "total": urls.len(),
+            });
+            EVENT_NOTIFIER.as_ref().notify_event(progress.to_string());
+        }
+
+        match serde_json::to_string(&records) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Restore files from `export_public_records`, e.g. after signing up on a new homeserver. Every entry must belong
+* to the signing key, otherwise nothing is imported. Signs in first unless the key already has a session allowed to write them, then
+* puts the files one by one at their URLs, which resolve to the key's current homeserver.
+* @param secret_key The secret key of the account the export belongs to
+* @param records_json The JSON array returned by `export_public_records`
+* @returns A JSON object `{ "imported", "skipped", "failed" }`, `skipped` counting entries exported without content
+**/
+#[uniffi::export]
+pub fn import_public_records(secret_key: String, records_json: String) -> Vec<String> {
+    #[derive(serde::Deserialize)]
+    struct ExportedRecord {
+        url: String,
+        content_base64: Option<String>,
+    }
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let records: Vec<ExportedRecord> = match serde_json::from_str(&records_json) {
+        Ok(records) => records,
+        Err(e) => return create_response_vector(true, format!("Failed to parse records JSON: {}", e)),
+    };
+
+    // Check every entry before writing anything, so a foreign export is never partially imported
+    let public_key = keypair.public_key().to_string();
+    let mut urls = Vec::with_capacity(records.len());
+    for record in &records {
+        let parsed_url = match Url::parse(record.url.trim_end_matches('/')) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, format!("Failed to parse URL: {}", record.url)),
+        };
+        if let Err(error) = validate_data_url_scheme(&parsed_url) {
+            return create_response_vector(true, error);
+        }
+        let parsed_url = normalize_data_url(parsed_url);
+        if data_url_public_key(&parsed_url).as_deref() != Some(public_key.as_str()) {
+            return create_response_vector(true, format!("{} does not belong to {}", record.url, public_key));
+        }
+        urls.push(parsed_url);
+    }
+
+    if !urls.iter().all(|url| account_has_capability(&public_key, url.path(), Action::Write)) {
+        let sign_in_result = sign_in(secret_key);
+        if sign_in_result[0] == "error" {
+            return sign_in_result;
+        }
+    }
+
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let (mut imported, mut skipped, mut failed) = (0, 0, 0);
+        for (url, record) in urls.into_iter().zip(&records) {
+            let content = match &record.content_base64 {
+                Some(content_base64) => match general_purpose::STANDARD.decode(content_base64) {
+                    Ok(content) => content,
+                    Err(_) => {
+                        failed += 1;
+                        continue;
+                    },
+                },
+                None => {
+                    skipped += 1;
+                    continue;
+                },
+            };
+            match put_data(&client, url, &content).await {
+                Ok(()) => imported += 1,
+                Err(_) => failed += 1,
+            }
+        }
+
+        let json_obj = json!({
+            "imported": imported,
+            "skipped": skipped,
+            "failed": failed,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+// Maximum number of concurrent fetches made by `content_search`
+const CONTENT_SEARCH_CONCURRENCY: usize = 10;
+
+// Characters of context kept on each side of a `content_search` match
+const CONTENT_SEARCH_EXCERPT_CONTEXT: usize = 40;
+
+// Fetch a file for `content_search` and match it against the query. Unreadable files count as no match,
+// only an open circuit is an error, as every other file of the homeserver would be rejected too.
+async fn search_file(client: Arc<PubkyClient>, url: String, query: String) -> Result<Option<serde_json::Value>, String> {
+    let Ok(parsed_url) = Url::parse(&url) else {
+        return Ok(None);
+    };
+    let bytes = match get_data(&client, parsed_url).await {
+        Ok(Some(bytes)) => bytes,
+        Ok(None) | Err(DataRequestError::Failed(_)) => return Ok(None),
+        Err(DataRequestError::CircuitOpen(error)) => return Err(error),
+    };
+    let Ok(text) = str::from_utf8(&bytes) else {
+        return Ok(None);
+    };
+    Ok(search_excerpt(text, &query, CONTENT_SEARCH_EXCERPT_CONTEXT).map(|excerpt| json!({ "url": url, "excerpt": excerpt })))
+}
+
+/**
+* Search the content of a user's files client-side, as homeservers have no search. Files under the prefix are
+* fetched in listing order (10 at a time) and matched case-insensitively until `max_results` matches are found;
+* files that aren't UTF-8 text are skipped. Fails when the homeserver's circuit is open rather than returning no matches.
+* @param public_key The public key of the user
+* @param path_prefix The directory to search, e.g. `/pub/pubky.app/posts/`
+* @param query The text to look for
+* @param max_results The maximum number of matches to return
+* @returns A JSON array of `{ "url", "excerpt" }`, in listing order, `excerpt` being the match with some context
+**/
+#[uniffi::export]
+pub fn content_search(public_key: String, path_prefix: String, query: String, max_results: u32) -> Vec<String> {
+    if query.is_empty() {
+        return create_response_vector(true, "Query must not be empty".to_string());
+    }
+    let public_key = match PublicKey::try_from(public_key.as_str()) {
+        Ok(public_key) => public_key,
+        Err(e) => return create_response_vector(true, format!("Invalid public key: {}", e)),
+    };
     let runtime = TOKIO_RUNTIME.clone();
     runtime.block_on(async {
         let client = get_pubky_client();
-        let trimmed_url = url.trim_end_matches('/');
-        let parsed_url = match Url::parse(&trimmed_url) {
+        let path_prefix = path_prefix.trim_matches('/');
+        let prefix = match Url::parse(&format!("pubky://{}/{}/", public_key, path_prefix)) {
             Ok(url) => url,
             Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
         };
-        match client.put(parsed_url, &content.as_bytes()).await {
-            Ok(_) => create_response_vector(false, trimmed_url.to_string()),
-            Err(error) => {
-                create_response_vector(true, format!("Failed to put: {}", error))
+        let urls = match list_all(&client, &prefix).await {
+            Ok(urls) => urls,
+            Err(error) => return create_response_vector(true, error),
+        };
+
+        let mut urls = urls.into_iter();
+        let mut in_flight: VecDeque<JoinHandle<Result<Option<serde_json::Value>, String>>> = VecDeque::new();
+        let mut results = Vec::new();
+        while results.len() < max_results as usize {
+            while in_flight.len() < CONTENT_SEARCH_CONCURRENCY {
+                let Some(url) = urls.next() else { break };
+                in_flight.push_back(tokio::spawn(search_file(client.clone(), url, query.clone())));
             }
+            let Some(task) = in_flight.pop_front() else { break };
+            match task.await {
+                Ok(Ok(Some(result))) => results.push(result),
+                Ok(Err(error)) => {
+                    in_flight.iter().for_each(|task| task.abort());
+                    return create_response_vector(true, error);
+                },
+                _ => {},
+            }
+        }
+        in_flight.iter().for_each(|task| task.abort());
+
+        match serde_json::to_string(&results) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
         }
     })
 }
 
+/**
+* Count the entries under a directory, e.g. for progress indicators or empty states. Asks the homeserver for a count
+* first (`?limit=0&count=true`); homeservers without that endpoint are listed page by page and counted here instead.
+* @param url The pubky:// or https:// URL of the directory
+* @returns A JSON object `{ "count", "approximate" }`. `approximate` is true for a client-side count, whose pages are
+* read at different times, so concurrent writes can make it drift from any single state of the directory.
+**/
 #[uniffi::export]
-pub fn get(url: String) -> Vec<String> {
+pub fn get_list_item_count(url: String) -> Vec<String> {
     let runtime = TOKIO_RUNTIME.clone();
     runtime.block_on(async {
         let client = get_pubky_client();
-        let trimmed_url = url.trim_end_matches('/');
-        let parsed_url = match Url::parse(&trimmed_url) {
+        let directory_url = format!("{}/", url.trim_end_matches('/'));
+        let parsed_url = match Url::parse(&directory_url) {
             Ok(url) => url,
             Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
         };
-        let result: Option<Bytes> = match client.get(parsed_url).await {
-            Ok(res) => res,
-            Err(_) => return create_response_vector(true, "Request failed".to_string()),
-        };
-        let bytes = match result {
-            Some(bytes) => bytes,
-            None => return create_response_vector(true, "No data returned".to_string()),
+        if let Err(error) = validate_data_url_scheme(&parsed_url) {
+            return create_response_vector(true, error);
+        }
+
+        let mut count_url = match pubky_to_https_url(&client, &parsed_url).await {
+            Ok(url) => url,
+            Err(error) => return create_response_vector(true, error),
         };
-        let string = match str::from_utf8(&bytes) {
-            Ok(s) => s.to_string(),
-            Err(_) => return create_response_vector(true, "Invalid UTF-8 sequence".to_string()),
+        count_url.set_query(Some("limit=0&count=true"));
+        let http_client = match reqwest::Client::builder().build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
         };
-        create_response_vector(false, string)
-    })
-}
-
-/**
-* Resolve a signed packet from a public key
-* @param public_key The public key to resolve
-* @returns A vector with two elements: the first element is a boolean indicating success or failure,
-* and the second element is the response data (either an error message or the resolved signed packet)
-**/
-#[uniffi::export]
-pub fn resolve(public_key: String) -> Vec<String> {
-    let runtime = TOKIO_RUNTIME.clone();
-    runtime.block_on(async {
-        let public_key = match public_key.as_str().try_into() {
-            Ok(key) => key,
-            Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+        let server_count = match http_client.get(count_url).send().await {
+            Ok(response) if response.status().is_success() => {
+                response.text().await.ok().and_then(|body| body.trim().parse::<u64>().ok())
+            },
+            _ => None,
         };
-        let client = get_pubky_client();
-
-        match client.pkarr().resolve(&public_key).await {
-            Ok(Some(signed_packet)) => {
-                // Collect references to ResourceRecords from the signed packet's answers
-                let all_records: Vec<&ResourceRecord> = signed_packet.packet().answers.iter().collect();
-                // Convert each ResourceRecord to a JSON value, handling errors appropriately
-                let json_records: Vec<serde_json::Value> = all_records
-                    .iter()
-                    .filter_map(|record| {
-                        match resource_record_to_json(record) {
-                            Ok(json_value) => Some(json_value),
-                            Err(e) => {
-                                eprintln!("Error converting record to JSON: {}", e);
-                                None
-                            }
-                        }
-                    })
-                    .collect();
-
-                let bytes = signed_packet.as_bytes();
-                let public_key = &bytes[..32];
-                let signature = &bytes[32..96];
-                let timestamp = signed_packet.timestamp();
-                let dns_packet = &bytes[104..];
-                let hex: String = signed_packet.encode_hex();
-
-                let json_obj = json!({
-                    "signed_packet": hex,
-                    "public_key": general_purpose::STANDARD.encode(public_key),
-                    "signature": general_purpose::STANDARD.encode(signature),
-                    "timestamp": timestamp,
-                    "last_seen": signed_packet.last_seen(),
-                    "dns_packet": general_purpose::STANDARD.encode(dns_packet),
-                    "records": json_records
-                });
-
-                let json_str = serde_json::to_string(&json_obj)
-                    .expect("Failed to convert JSON object to string");
 
-                create_response_vector(false, json_str)
+        let (count, approximate) = match server_count {
+            Some(count) => (count, false),
+            None => match list_all(&client, &parsed_url).await {
+                Ok(urls) => (urls.len() as u64, true),
+                Err(error) => return create_response_vector(true, error),
             },
-            Ok(None) => {
-                create_response_vector(true, "No signed packet found".to_string())
-            }
-            Err(e) => {
-                create_response_vector(true, format!("Failed to resolve: {}", e))
-            }
+        };
+        let json_obj = json!({
+            "count": count,
+            "approximate": approximate,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
         }
     })
 }
 
+// Maximum number of concurrent size lookups made by `storage_usage`
+const STORAGE_USAGE_CONCURRENCY: usize = 16;
+
+/**
+* Compute how much storage an account uses on its homeserver: lists every file under `/pub/`
+* and sums their sizes, looked up concurrently with HEAD requests
+* @param secret_key_or_pubky The account's hex encoded secret key or its public key
+* @returns A JSON object `{ "file_count", "total_bytes", "unknown_size_count" }`, where `unknown_size_count`
+* counts files whose size could not be determined and are left out of `total_bytes`
+**/
 #[uniffi::export]
-pub fn publish(record_name: String, record_content: String, secret_key: String) -> Vec<String> {
+pub fn storage_usage(secret_key_or_pubky: String) -> Vec<String> {
+    let public_key = match get_keypair_from_secret_key(&secret_key_or_pubky) {
+        Ok(keypair) => keypair.public_key(),
+        Err(_) => match PublicKey::try_from(secret_key_or_pubky.as_str()) {
+            Ok(public_key) => public_key,
+            Err(e) => return create_response_vector(true, format!("Invalid secret key or public key: {}", e)),
+        },
+    };
     let runtime = TOKIO_RUNTIME.clone();
     runtime.block_on(async {
         let client = get_pubky_client();
+        let prefix = match Url::parse(&format!("pubky://{}/pub/", public_key)) {
+            Ok(url) => url,
+            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
+        };
 
-        let keypair = match get_keypair_from_secret_key(&secret_key) {
-            Ok(keypair) => keypair,
+        let urls = match list_all(&client, &prefix).await {
+            Ok(urls) => urls,
             Err(error) => return create_response_vector(true, error),
         };
 
-        let mut packet = dns::Packet::new_reply(0);
-
-        let dns_name = match dns::Name::new(&record_name) {
-            Ok(name) => name,
-            Err(e) => return create_response_vector(true, format!("Failed to create DNS name: {}", e)),
+        let http_client = match reqwest::Client::builder().build() {
+            Ok(http_client) => http_client,
+            Err(error) => return create_response_vector(true, format!("Failed to create HTTP client: {}", error)),
         };
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(STORAGE_USAGE_CONCURRENCY));
+        let tasks: Vec<JoinHandle<Option<u64>>> = urls
+            .iter()
+            .map(|url| {
+                let client = client.clone();
+                let http_client = http_client.clone();
+                let semaphore = semaphore.clone();
+                let url = url.clone();
+                tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await.ok()?;
+                    let https_url = pubky_to_https_url(&client, &Url::parse(&url).ok()?).await.ok()?;
+                    let response = http_client.head(https_url).send().await.ok()?;
+                    if !response.status().is_success() {
+                        return None;
+                    }
+                    response
+                        .headers()
+                        .get(reqwest::header::CONTENT_LENGTH)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse::<u64>().ok())
+                })
+            })
+            .collect();
 
-        let record_content_str: &str = record_content.as_str();
-
-        let txt_record = match record_content_str.try_into() {
-            Ok(value) => RData::TXT(value),
-            Err(e) => {
-                return create_response_vector(true, format!("Failed to convert string to TXT record: {}", e))
+        let mut total_bytes: u64 = 0;
+        let mut unknown_size_count = 0;
+        for task in tasks {
+            match task.await {
+                Ok(Some(size)) => total_bytes += size,
+                _ => unknown_size_count += 1,
             }
-        };
-
-        packet.answers.push(dns::ResourceRecord::new(
-            dns_name,
-            dns::CLASS::IN,
-            30,
-            txt_record,
-        ));
+        }
 
-        match SignedPacket::from_packet(&keypair, &packet) {
-            Ok(signed_packet) => {
-                match client.pkarr().publish(&signed_packet).await {
-                    Ok(()) => {
-                        create_response_vector(false, keypair.public_key().to_string())
-                    }
-                    Err(e) => {
-                        create_response_vector(true, format!("Failed to publish: {}", e))
-                    }
-                }
-            }
-            Err(e) => {
-                create_response_vector(true, format!("Failed to create signed packet: {}", e))
-            }
+        let json_obj = json!({
+            "file_count": urls.len(),
+            "total_bytes": total_bytes,
+            "unknown_size_count": unknown_size_count,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
         }
     })
 }
+
+/**
+* Create a deep link for sharing content with other pubky apps
+* @param public_key The public key owning the content
+* @param path The path of the content, e.g. `/pub/example.com/file`
+* @returns The `pubkyapp://open?pubky=<key>&path=<path>` link
+**/
 #[uniffi::export]
-pub fn list(url: String) -> Vec<String> {
-    let runtime = TOKIO_RUNTIME.clone();
-    runtime.block_on(async {
-        let client = get_pubky_client();
-        let trimmed_url = url.trim_end_matches('/');
-        let parsed_url = match Url::parse(&trimmed_url) {
-            Ok(url) => url,
-            Err(_) => return create_response_vector(true, "Failed to parse URL".to_string()),
-        };
-        let list_builder = match client.list(parsed_url) {
-            Ok(list) => list,
-            Err(error) => return create_response_vector(true, format!("Failed to list: {}", error)),
-        };
-        // Execute the non-Send part synchronously
-        let send_future = list_builder.send();
-        let send_res = match send_future.await {
-            Ok(res) => res,
-            Err(error) => return create_response_vector(true, format!("Failed to send list request: {}", error))
-        };
-        let json_string = match serde_json::to_string(&send_res) {
-            Ok(json) => json,
-            Err(error) => return create_response_vector(true, format!("Failed to serialize JSON: {}", error)),
-        };
-        create_response_vector(false, json_string)
-    })
+pub fn generate_share_link(public_key: String, path: String) -> Vec<String> {
+    let public_key = match PublicKey::try_from(public_key.as_str()) {
+        Ok(key) => key,
+        Err(e) => return create_response_vector(true, format!("Invalid zbase32 encoded key: {}", e)),
+    };
+    match build_share_link(&public_key, &path) {
+        Ok(link) => create_response_vector(false, link.to_string()),
+        Err(error) => create_response_vector(true, error),
+    }
+}
+
+/**
+* Parse a deep link created by `generate_share_link`
+* @param link The share link
+* @returns A JSON object `{ "public_key", "path", "full_pubky_url" }`
+**/
+#[uniffi::export]
+pub fn parse_share_link(link: String) -> Vec<String> {
+    let (public_key, path) = match parse_share_link_url(&link) {
+        Ok(parts) => parts,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let json_obj = json!({
+        "public_key": public_key.to_string(),
+        "full_pubky_url": format!("pubky://{}{}", public_key, path),
+        "path": path,
+    });
+
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
 }
 
 #[uniffi::export]
@@ -621,6 +5762,27 @@ pub fn parse_auth_url(url: String) -> Vec<String> {
     }
 }
 
+/**
+* Dedupe and merge capabilities, e.g. those combined from several auth requests
+* @param caps_json A JSON array of capabilities such as `["/pub/app/:r", "/pub/app/:w"]`; each entry may also be a comma separated list
+* @returns A JSON array of the minimal canonical capabilities, sorted by scope
+**/
+#[uniffi::export]
+pub fn merge_capabilities(caps_json: String) -> Vec<String> {
+    let capabilities: Vec<String> = match serde_json::from_str(&caps_json) {
+        Ok(capabilities) => capabilities,
+        Err(e) => return create_response_vector(true, format!("Failed to parse capabilities JSON: {}", e)),
+    };
+    let canonical = match canonicalize_capabilities(&capabilities) {
+        Ok(canonical) => canonical,
+        Err(error) => return create_response_vector(true, error),
+    };
+    match serde_json::to_string(&canonical) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
 #[uniffi::export]
 pub fn create_recovery_file(secret_key: String, passphrase: String,) -> Vec<String> {
     if secret_key.is_empty() || passphrase.is_empty() {
@@ -654,3 +5816,235 @@ pub fn decrypt_recovery_file(recovery_file: String, passphrase: String) -> Vec<S
     let secret_key = get_secret_key_from_keypair(&keypair);
     create_response_vector(false, secret_key)
 }
+
+#[derive(Debug, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum RecoveryFileError {
+    InvalidInput { message: String },
+    CreationFailed { message: String },
+}
+
+impl std::fmt::Display for RecoveryFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecoveryFileError::InvalidInput { message } => write!(f, "{}", message),
+            RecoveryFileError::CreationFailed { message } => write!(f, "Failed to create recovery file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RecoveryFileError {}
+
+/**
+* Create a recovery file as raw bytes, e.g. to write straight to a `.pkarr` file, instead of base64 like `create_recovery_file`
+* @param secret_key The hex encoded secret key
+* @param passphrase The passphrase to encrypt the secret key with
+* @returns The recovery file, or a `RecoveryFileError` when the secret key is invalid, either argument is empty
+* or the encryption fails
+**/
+#[uniffi::export]
+pub fn create_recovery_file_bytes(secret_key: String, passphrase: String) -> Result<Vec<u8>, RecoveryFileError> {
+    if secret_key.is_empty() || passphrase.is_empty() {
+        return Err(RecoveryFileError::InvalidInput { message: "Secret key and passphrase must not be empty".to_string() });
+    }
+    let keypair = get_keypair_from_secret_key(&secret_key)
+        .map_err(|message| RecoveryFileError::InvalidInput { message })?;
+    PubkyClient::create_recovery_file(&keypair, &passphrase)
+        .map_err(|error| RecoveryFileError::CreationFailed { message: error.to_string() })
+}
+
+/**
+* Decrypt a recovery file given as raw bytes, see `create_recovery_file_bytes`
+* @param bytes The recovery file
+* @param passphrase The passphrase it was encrypted with
+* @returns The hex encoded secret key, as for `decrypt_recovery_file`
+**/
+#[uniffi::export]
+pub fn decrypt_recovery_file_bytes(bytes: Vec<u8>, passphrase: String) -> Vec<String> {
+    if bytes.is_empty() || passphrase.is_empty() {
+        return create_response_vector(true, "Recovery file and passphrase must not be empty".to_string());
+    }
+    match PubkyClient::decrypt_recovery_file(&bytes, &passphrase) {
+        Ok(keypair) => create_response_vector(false, get_secret_key_from_keypair(&keypair)),
+        Err(_) => create_response_vector(true, "Failed to decrypt recovery file".to_string()),
+    }
+}
+
+/**
+* Check that a recovery file is well-formed before asking for its passphrase. Nothing is decrypted.
+* @param recovery_file_base64 The base64 encoded recovery file
+* @returns A JSON object `{ "valid_format", "version" }`, `version` being 1 (legacy pkarr), 2 or 3 (time locked),
+* or null when the format is invalid
+**/
+#[uniffi::export]
+pub fn is_recovery_file(recovery_file_base64: String) -> Vec<String> {
+    let version = general_purpose::STANDARD
+        .decode(recovery_file_base64.trim())
+        .ok()
+        .and_then(|bytes| recovery_file_version(&bytes));
+    let json_obj = json!({
+        "valid_format": version.is_some(),
+        "version": version,
+    });
+    match serde_json::to_string(&json_obj) {
+        Ok(json) => create_response_vector(false, json),
+        Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    }
+}
+
+/**
+* Create a passphrase-encrypted backup of an account: a ZIP archive holding a recovery file, the profile and,
+* optionally, every file under `/pub/`, encrypted with AES-256-GCM under a key derived from the passphrase with Argon2id
+* @param secret_key The hex encoded secret key of the account
+* @param passphrase The passphrase protecting both the archive and the recovery file inside it
+* @param include_content Whether to back up every file under `/pub/`, rather than only the profile
+* @returns The base64 encoded backup
+**/
+#[uniffi::export]
+pub fn create_account_backup(secret_key: String, passphrase: String, include_content: bool) -> Vec<String> {
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        match create_account_backup_bytes(&client, &keypair, &passphrase, include_content).await {
+            Ok(backup) => create_response_vector(false, general_purpose::STANDARD.encode(backup)),
+            Err(error) => create_response_vector(true, error),
+        }
+    })
+}
+
+/**
+* Restore a backup created by `create_account_backup`: signs the recovered key up at the target homeserver
+* and uploads the backed up files
+* @param backup_base64 The base64 encoded backup
+* @param passphrase The passphrase the backup was created with
+* @param target_homeserver The public key of the homeserver to restore the account to
+* @returns A JSON object `{ "public_key", "secret_key", "restored_files" }`
+**/
+#[uniffi::export]
+pub fn restore_account_backup(backup_base64: String, passphrase: String, target_homeserver: String) -> Vec<String> {
+    let backup = match general_purpose::STANDARD.decode(backup_base64.trim()) {
+        Ok(backup) => backup,
+        Err(e) => return create_response_vector(true, format!("Failed to decode backup: {}", e)),
+    };
+    let homeserver = match PublicKey::try_from(target_homeserver) {
+        Ok(key) => key,
+        Err(error) => return create_response_vector(true, format!("Invalid homeserver public key: {}", error)),
+    };
+    let runtime = TOKIO_RUNTIME.clone();
+    runtime.block_on(async {
+        let client = get_pubky_client();
+        let (keypair, restored_files) = match restore_account_backup_bytes(&client, &backup, &passphrase, &homeserver).await {
+            Ok(restored) => restored,
+            Err(error) => return create_response_vector(true, error),
+        };
+        let json_obj = json!({
+            "public_key": keypair.public_key().to_string(),
+            "secret_key": get_secret_key_from_keypair(&keypair),
+            "restored_files": restored_files,
+        });
+        match serde_json::to_string(&json_obj) {
+            Ok(json) => create_response_vector(false, json),
+            Err(e) => create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+        }
+    })
+}
+
+/**
+* Re-encrypt a recovery file with a new passphrase, without returning the secret key
+* @param recovery_file The base64 encoded recovery file
+* @param old_passphrase The current passphrase
+* @param new_passphrase The new passphrase
+* @returns The base64 encoded recovery file encrypted with the new passphrase
+**/
+#[uniffi::export]
+pub fn rekey_recovery_file(recovery_file: String, old_passphrase: String, new_passphrase: String) -> Vec<String> {
+    if recovery_file.is_empty() || old_passphrase.is_empty() || new_passphrase.is_empty() {
+        return create_response_vector(true, "Recovery file and passphrases must not be empty".to_string());
+    }
+    let recovery_file_bytes = match general_purpose::STANDARD.decode(&recovery_file) {
+        Ok(bytes) => bytes,
+        Err(error) => return create_response_vector(true, format!("Failed to decode recovery file: {}", error)),
+    };
+    let keypair = match PubkyClient::decrypt_recovery_file(&recovery_file_bytes, &old_passphrase) {
+        Ok(keypair) => keypair,
+        Err(_) => return create_response_vector(true, "Failed to decrypt recovery file".to_string()),
+    };
+    match PubkyClient::create_recovery_file(&keypair, &new_passphrase) {
+        Ok(bytes) => create_response_vector(false, general_purpose::STANDARD.encode(bytes)),
+        Err(_) => create_response_vector(true, "Failed to create recovery file".to_string()),
+    }
+}
+
+/**
+* Create a recovery file that `decrypt_time_locked_recovery_file` won't decrypt before a given time.
+* The lock is advisory and only enforced by this library: the file is encrypted with the passphrase and the unlock
+* time, so anyone holding the passphrase can decrypt it early with other tooling. Don't rely on it against the passphrase holder.
+* @param secret_key The secret key to back up
+* @param passphrase The passphrase used to encrypt the secret key
+* @param unlock_unix_timestamp The UNIX time (in seconds) from which the file can be decrypted
+* @returns The base64 encoded time locked (v3) recovery file
+**/
+#[uniffi::export]
+pub fn create_time_lock_recovery_file(secret_key: String, passphrase: String, unlock_unix_timestamp: u64) -> Vec<String> {
+    if secret_key.is_empty() || passphrase.is_empty() {
+        return create_response_vector(true, "Secret key and passphrase must not be empty".to_string());
+    }
+    let keypair = match get_keypair_from_secret_key(&secret_key) {
+        Ok(keypair) => keypair,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let recovery_file_bytes = match create_time_locked_recovery_file_bytes(&keypair, &passphrase, unlock_unix_timestamp) {
+        Ok(bytes) => bytes,
+        Err(error) => return create_response_vector(true, error),
+    };
+    create_response_vector(false, general_purpose::STANDARD.encode(recovery_file_bytes))
+}
+
+/**
+* Decrypt a time locked recovery file, once its unlock time has passed by this device's clock.
+* The lock is advisory: see `create_time_lock_recovery_file`.
+* @param recovery_file The base64 encoded time locked recovery file
+* @param passphrase The passphrase used when creating the file
+* @returns `{ "locked": true, "unlocks_at": N }` if the unlock time has not arrived yet,
+* otherwise `{ "locked": false, "secret_key": "..." }`
+**/
+#[uniffi::export]
+pub fn decrypt_time_locked_recovery_file(recovery_file: String, passphrase: String) -> Vec<String> {
+    if recovery_file.is_empty() || passphrase.is_empty() {
+        return create_response_vector(true, "Recovery file and passphrase must not be empty".to_string());
+    }
+    let recovery_file_bytes = match general_purpose::STANDARD.decode(&recovery_file) {
+        Ok(bytes) => bytes,
+        Err(error) => return create_response_vector(true, format!("Failed to decode recovery file: {}", error)),
+    };
+    let (unlocks_at, _) = match parse_time_locked_recovery_file(&recovery_file_bytes) {
+        Ok(parts) => parts,
+        Err(error) => return create_response_vector(true, error),
+    };
+    let now = pkarr::system_time() / 1_000_000;
+    let json_obj = if now < unlocks_at {
+        json!({
+            "locked": true,
+            "unlocks_at": unlocks_at,
+        })
+    } else {
+        let keypair = match decrypt_time_locked_recovery_file_bytes(&recovery_file_bytes, &passphrase, now) {
+            Ok(keypair) => keypair,
+            Err(error) => return create_response_vector(true, error),
+        };
+        json!({
+            "locked": false,
+            "secret_key": get_secret_key_from_keypair(&keypair),
+        })
+    };
+
+    let json_str = match serde_json::to_string(&json_obj) {
+        Ok(json) => json,
+        Err(e) => return create_response_vector(true, format!("Failed to serialize JSON: {}", e)),
+    };
+    create_response_vector(false, json_str)
+}
@@ -0,0 +1,90 @@
+use pkarr::Keypair;
+use pubky::PubkyClient;
+
+/**
+ * Spec line of time locked (v3) recovery files
+ */
+pub const TIME_LOCK_SPEC_LINE: &str = "pubky.org/recovery/v3";
+
+/**
+ * The passphrase used to encrypt the inner recovery file of a time locked recovery file.
+ * Binding the unlock time into the passphrase means editing the header breaks decryption.
+ */
+fn time_lock_passphrase(passphrase: &str, unlock_unix_timestamp: u64) -> String {
+    format!("{}:{}", passphrase, unlock_unix_timestamp)
+}
+
+/**
+ * Create a time locked recovery file:
+ * `<spec line>\n<8 bytes big-endian unlock timestamp><inner recovery file>`
+ */
+pub fn create_time_locked_recovery_file_bytes(keypair: &Keypair, passphrase: &str, unlock_unix_timestamp: u64) -> Result<Vec<u8>, String> {
+    let inner = PubkyClient::create_recovery_file(keypair, &time_lock_passphrase(passphrase, unlock_unix_timestamp))
+        .map_err(|_| "Failed to create recovery file".to_string())?;
+
+    let mut out = Vec::with_capacity(TIME_LOCK_SPEC_LINE.len() + 9 + inner.len());
+    out.extend_from_slice(TIME_LOCK_SPEC_LINE.as_bytes());
+    out.push(b'\n');
+    out.extend_from_slice(&unlock_unix_timestamp.to_be_bytes());
+    out.extend_from_slice(&inner);
+    Ok(out)
+}
+
+/**
+ * Split a time locked recovery file into its unlock timestamp and inner recovery file
+ */
+pub fn parse_time_locked_recovery_file(recovery_file: &[u8]) -> Result<(u64, &[u8]), String> {
+    let header_length = TIME_LOCK_SPEC_LINE.len() + 1;
+    if recovery_file.len() < header_length + 8 || !recovery_file.starts_with(TIME_LOCK_SPEC_LINE.as_bytes()) || recovery_file[header_length - 1] != b'\n' {
+        return Err("Not a time locked recovery file".to_string());
+    }
+    let timestamp_bytes: [u8; 8] = match recovery_file[header_length..header_length + 8].try_into() {
+        Ok(bytes) => bytes,
+        Err(_) => return Err("Invalid unlock timestamp".to_string()),
+    };
+    Ok((u64::from_be_bytes(timestamp_bytes), &recovery_file[header_length + 8..]))
+}
+
+/**
+ * Decrypt a time locked recovery file, refusing to before its unlock time.
+ * The lock is advisory: only this check enforces it, and anyone with the passphrase can decrypt the inner file
+ * earlier with their own tooling, since its passphrase is just the passphrase and the unlock timestamp.
+ */
+pub fn decrypt_time_locked_recovery_file_bytes(recovery_file: &[u8], passphrase: &str, now_unix_timestamp: u64) -> Result<Keypair, String> {
+    let (unlock_unix_timestamp, inner) = parse_time_locked_recovery_file(recovery_file)?;
+    if now_unix_timestamp < unlock_unix_timestamp {
+        return Err(format!("Recovery file is locked until {}", unlock_unix_timestamp));
+    }
+    PubkyClient::decrypt_recovery_file(inner, &time_lock_passphrase(passphrase, unlock_unix_timestamp))
+        .map_err(|_| "Failed to decrypt recovery file".to_string())
+}
+
+/**
+ * Length of the encrypted secret key in a recovery file: a 24-byte nonce, the 32-byte key and a 16-byte tag
+ */
+const ENCRYPTED_SECRET_KEY_LENGTH: usize = 24 + 32 + 16;
+
+/**
+ * Check the structure of a recovery file without decrypting it, returning its format version:
+ * 1 for legacy `pkarr.org/recovery` files, 2 for `pubky.org/recovery` files and 3 for time locked files.
+ * Returns None when the bytes are not a well-formed recovery file.
+ */
+pub fn recovery_file_version(recovery_file: &[u8]) -> Option<u8> {
+    if let Ok((_, inner)) = parse_time_locked_recovery_file(recovery_file) {
+        // The inner file of a time locked file is a regular recovery file
+        return match recovery_file_version(inner) {
+            Some(1 | 2) => Some(3),
+            _ => None,
+        };
+    }
+    let newline_index = recovery_file.iter().position(|byte| *byte == b'\n')?;
+    let version = match &recovery_file[..newline_index] {
+        b"pkarr.org/recovery" => 1,
+        b"pubky.org/recovery" => 2,
+        _ => return None,
+    };
+    if recovery_file.len() - newline_index - 1 != ENCRYPTED_SECRET_KEY_LENGTH {
+        return None;
+    }
+    Some(version)
+}
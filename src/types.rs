@@ -11,4 +11,6 @@ pub struct PubkyAuthDetails {
     pub relay: String,
     pub capabilities: Vec<Capability>,
     pub secret: String,
+    pub secret_normalized: String,
+    pub secret_length: usize,
 }
\ No newline at end of file
@@ -1,9 +1,17 @@
 use std::error::Error;
+use std::str;
 use std::net::{Ipv4Addr, Ipv6Addr};
 use serde_json::json;
-use pkarr::dns::rdata::RData;
-use pkarr::dns::ResourceRecord;
+use base64::Engine;
+use base64::engine::general_purpose;
+use pkarr::dns::rdata::{RData, A, AAAA, CAA, CNAME, HTTPS, MX, NS, SRV, SVCB, TXT};
+use pkarr::dns::{self, ResourceRecord};
+use pkarr::{Keypair, SignedPacket};
+use pkarr::bytes::Bytes;
+use pubky_common::capabilities::{Action, Capability};
 use pubky_common::session::Session;
+use sha2::{Digest, Sha256};
+use crate::dns_registry::DNS_TYPE_REGISTRY;
 
 pub fn create_response_vector(error: bool, data: String) -> Vec<String> {
     if error {
@@ -13,6 +21,14 @@ pub fn create_response_vector(error: bool, data: String) -> Vec<String> {
     }
 }
 
+/**
+ * Record types rendered by `extract_rdata_for_json`; keep in sync with its match arms
+ */
+pub const SUPPORTED_RDATA_TYPES: [&str; 22] = [
+    "A", "AAAA", "AFSDB", "CAA", "CNAME", "HINFO", "HTTPS", "ISDN", "LOC", "MINFO", "MX", "NAPTR",
+    "NS", "NULL", "OPT", "RP", "RT", "SOA", "SRV", "SVCB", "TXT", "WKS",
+];
+
 pub fn extract_rdata_for_json(record: &ResourceRecord) -> serde_json::Value {
     match &record.rdata {
         RData::TXT(txt) => {
@@ -110,6 +126,14 @@ pub fn extract_rdata_for_json(record: &ResourceRecord) -> serde_json::Value {
                 "replacement": naptr.replacement.to_string()
             })
         },
+        // Record types the DNS library doesn't know are parsed as NULL records carrying their own type number
+        RData::NULL(type_number, null_record) if *type_number != u16::from(dns::TYPE::NULL) => {
+            json!({
+                "type": DNS_TYPE_REGISTRY.name_of(*type_number).unwrap_or_else(|| "UNKNOWN".to_string()),
+                "type_number": type_number,
+                "data": general_purpose::STANDARD.encode(null_record.get_data())
+            })
+        },
         RData::NULL(_, null_record) => {
             json!({
                 "type": "NULL",
@@ -164,16 +188,18 @@ pub fn extract_rdata_for_json(record: &ResourceRecord) -> serde_json::Value {
                 "target": srv.target.to_string()
             })
         },
-        RData::SVCB(svcb) => {
-            let mut params = serde_json::Map::new();
-            for (key, value) in svcb.iter_params() {
-                params.insert(key.to_string(), json!(base64::encode(value)));
-            }
+        RData::SVCB(svcb) => svcb_to_json("SVCB", svcb),
+        RData::HTTPS(https) => svcb_to_json("HTTPS", &https.0),
+        RData::NS(ns) => {
             json!({
-                "type": "SVCB",
-                "priority": svcb.priority,
-                "target": svcb.target.to_string(),
-                "params": params
+                "type": "NS",
+                "nameserver": ns.0.to_string()
+            })
+        },
+        RData::CNAME(cname) => {
+            json!({
+                "type": "CNAME",
+                "target": cname.0.to_string()
             })
         },
         RData::WKS(wks) => {
@@ -185,13 +211,72 @@ pub fn extract_rdata_for_json(record: &ResourceRecord) -> serde_json::Value {
             })
         },
 
-        _ => json!({
-            "type": format!("{:?}", record.rdata.type_code()),
-            "data": "Unhandled record type"
-        }),
+        _ => {
+            let type_number = u16::from(record.rdata.type_code());
+            json!({
+                "type": DNS_TYPE_REGISTRY.name_of(type_number).unwrap_or_else(|| format!("{:?}", record.rdata.type_code())),
+                "type_number": type_number,
+                "data": "Unhandled record type"
+            })
+        },
     }
 }
 
+fn svcb_to_json(record_type: &str, svcb: &SVCB) -> serde_json::Value {
+    let mut params = serde_json::Map::new();
+    for (key, value) in svcb.iter_params() {
+        params.insert(key.to_string(), json!(base64::encode(value)));
+    }
+    json!({
+        "type": record_type,
+        "priority": svcb.priority,
+        "target": svcb.target.to_string(),
+        "params": params
+    })
+}
+
+/**
+* Separator between the `key=value` pairs of a TXT record published as a single string
+*/
+pub const TXT_PAIR_SEPARATOR: char = ';';
+
+/**
+* Parse a `key1=value1;key2=value2` TXT string into its pairs.
+* Returns None when any segment is not a `key=value` pair with a non-empty key.
+*/
+pub fn parse_txt_pairs(txt: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let mut pairs = serde_json::Map::new();
+    for segment in txt.split(TXT_PAIR_SEPARATOR).filter(|segment| !segment.is_empty()) {
+        let (key, value) = segment.split_once('=')?;
+        if key.is_empty() {
+            return None;
+        }
+        pairs.insert(key.to_string(), json!(value));
+    }
+    if pairs.is_empty() {
+        return None;
+    }
+    Some(pairs)
+}
+
+/**
+* Encode string pairs into a `key1=value1;key2=value2` TXT string, the inverse of `parse_txt_pairs`
+*/
+pub fn txt_pairs_to_string(pairs: &serde_json::Map<String, serde_json::Value>) -> Result<String, String> {
+    let mut segments = Vec::with_capacity(pairs.len());
+    for (key, value) in pairs {
+        let value = value.as_str().ok_or_else(|| format!("Value of {} must be a string", key))?;
+        if key.is_empty() || key.contains('=') || key.contains(TXT_PAIR_SEPARATOR) {
+            return Err(format!("Invalid TXT key: {}", key));
+        }
+        if value.contains(TXT_PAIR_SEPARATOR) {
+            return Err(format!("Value of {} must not contain '{}'", key, TXT_PAIR_SEPARATOR));
+        }
+        segments.push(format!("{}={}", key, value));
+    }
+    Ok(segments.join(&TXT_PAIR_SEPARATOR.to_string()))
+}
+
 pub fn resource_record_to_json(record: &ResourceRecord) -> Result<serde_json::Value, Box<dyn Error>> {
     Ok(json!({
         "name": record.name.to_string(),
@@ -202,6 +287,624 @@ pub fn resource_record_to_json(record: &ResourceRecord) -> Result<serde_json::Va
     }))
 }
 
+/**
+* Convert a JSON record, in the same shape as the `records` returned by `resolve`, back into a ResourceRecord
+*
+* Supported rdata types are A, AAAA, TXT, NS, CNAME, MX, SRV, SVCB, HTTPS and CAA. The `class` field is optional
+* and only "IN" is accepted, `ttl` defaults to 3600 seconds.
+*/
+pub fn resource_record_from_json(value: &serde_json::Value) -> Result<ResourceRecord<'static>, String> {
+    let name = value["name"].as_str().ok_or_else(|| "Record is missing a name".to_string())?;
+    let ttl = match value.get("ttl") {
+        Some(ttl) => ttl.as_u64()
+            .and_then(|ttl| u32::try_from(ttl).ok())
+            .ok_or_else(|| format!("Invalid TTL for record {}", name))?,
+        None => 3600,
+    };
+    if let Some(class) = value.get("class") {
+        if class.as_str() != Some("IN") {
+            return Err(format!("Unsupported class for record {}: {}", name, class));
+        }
+    }
+    let rdata = &value["rdata"];
+    let record_type = rdata["type"].as_str().ok_or_else(|| format!("Record {} is missing an rdata type", name))?;
+
+    let get_str = |field: &str| -> Result<&str, String> {
+        rdata[field].as_str().ok_or_else(|| format!("{} record {} is missing {}", record_type, name, field))
+    };
+    let get_u16 = |field: &str| -> Result<u16, String> {
+        rdata[field].as_u64()
+            .and_then(|number| u16::try_from(number).ok())
+            .ok_or_else(|| format!("{} record {} has an invalid {}", record_type, name, field))
+    };
+    let get_name = |field: &str| -> Result<dns::Name, String> {
+        let value = get_str(field)?;
+        dns::Name::new(value).map_err(|e| format!("Invalid {} for record {}: {}", field, name, e))
+    };
+
+    let rdata = match record_type {
+        "A" => {
+            let address: Ipv4Addr = get_str("address")?.parse()
+                .map_err(|e| format!("Invalid IPv4 address for record {}: {}", name, e))?;
+            RData::A(A { address: address.into() })
+        },
+        "AAAA" => {
+            let address: Ipv6Addr = get_str("address")?.parse()
+                .map_err(|e| format!("Invalid IPv6 address for record {}: {}", name, e))?;
+            RData::AAAA(AAAA { address: address.into() })
+        },
+        "TXT" => {
+            let strings = rdata["strings"].as_array()
+                .ok_or_else(|| format!("TXT record {} is missing strings", name))?;
+            let mut txt = TXT::new();
+            for string in strings {
+                let string = string.as_str().ok_or_else(|| format!("TXT record {} has a non-string entry", name))?;
+                txt.add_string(string).map_err(|e| format!("Invalid TXT string for record {}: {}", name, e))?;
+            }
+            RData::TXT(txt)
+        },
+        "NS" => RData::NS(NS(get_name("nameserver")?)),
+        "CNAME" => RData::CNAME(CNAME(get_name("target")?)),
+        "MX" => RData::MX(MX {
+            preference: get_u16("preference")?,
+            exchange: get_name("exchange")?,
+        }),
+        "SRV" => RData::SRV(SRV {
+            priority: get_u16("priority")?,
+            weight: get_u16("weight")?,
+            port: get_u16("port")?,
+            target: get_name("target")?,
+        }),
+        "SVCB" | "HTTPS" => {
+            let mut svcb = SVCB::new(get_u16("priority")?, get_name("target")?);
+            if let Some(params) = rdata["params"].as_object() {
+                for (key, value) in params {
+                    let key: u16 = key.parse().map_err(|_| format!("Invalid SVCB param key for record {}: {}", name, key))?;
+                    let value = value.as_str()
+                        .and_then(|value| general_purpose::STANDARD.decode(value).ok())
+                        .ok_or_else(|| format!("Invalid SVCB param value for record {}", name))?;
+                    svcb.set_param(key, value).map_err(|e| format!("Invalid SVCB param for record {}: {}", name, e))?;
+                }
+            }
+            if record_type == "HTTPS" {
+                RData::HTTPS(HTTPS(svcb))
+            } else {
+                RData::SVCB(svcb)
+            }
+        },
+        "CAA" => {
+            let flag = rdata["flag"].as_u64()
+                .and_then(|flag| u8::try_from(flag).ok())
+                .ok_or_else(|| format!("CAA record {} has an invalid flag", name))?;
+            RData::CAA(CAA {
+                flag,
+                tag: get_str("tag")?.try_into().map_err(|e| format!("Invalid CAA tag for record {}: {}", name, e))?,
+                value: get_str("value")?.try_into().map_err(|e| format!("Invalid CAA value for record {}: {}", name, e))?,
+            })
+        },
+        other => return Err(format!("Unsupported record type: {}", other)),
+    };
+
+    let dns_name = dns::Name::new(name).map_err(|e| format!("Invalid DNS name {}: {}", name, e))?;
+    Ok(ResourceRecord::new(dns_name, dns::CLASS::IN, ttl, rdata).into_owned())
+}
+
+/**
+* The wire format rdata of a record, read back from an uncompressed single-record packet
+*/
+fn record_rdata_bytes(record: &ResourceRecord) -> Result<Vec<u8>, String> {
+    let mut packet = dns::Packet::new_reply(0);
+    packet.answers.push(record.clone());
+    let bytes = packet.build_bytes_vec().map_err(|e| format!("Failed to encode record: {}", e))?;
+    // Skip the 12-byte header, the owner name, then type, class, TTL and rdata length
+    let mut offset = 12;
+    while offset < bytes.len() && bytes[offset] != 0 {
+        offset += bytes[offset] as usize + 1;
+    }
+    offset += 1 + 10;
+    bytes.get(offset..).map(|rdata| rdata.to_vec()).ok_or_else(|| "Failed to encode record".to_string())
+}
+
+/**
+* Quote a DNS character-string for a zone file, escaping quotes and backslashes and writing other
+* non-printable bytes as `\DDD`
+*/
+pub fn zone_quote(bytes: &[u8]) -> String {
+    let mut quoted = String::from("\"");
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => {
+                quoted.push('\\');
+                quoted.push(*byte as char);
+            },
+            0x20..=0x7e => quoted.push(*byte as char),
+            _ => quoted.push_str(&format!("\\{:03}", byte)),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Split wire format rdata into its length-prefixed character-strings
+fn split_character_strings(mut bytes: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut strings = Vec::new();
+    while let Some((length, rest)) = bytes.split_first() {
+        let length = *length as usize;
+        if rest.len() < length {
+            return None;
+        }
+        strings.push(&rest[..length]);
+        bytes = &rest[length..];
+    }
+    Some(strings)
+}
+
+// Fully qualified presentation form of a name
+fn zone_name(name: &dns::Name) -> String {
+    let name = name.to_string();
+    if name.ends_with('.') {
+        name
+    } else {
+        format!("{}.", name)
+    }
+}
+
+// Presentation names of the SvcParamKeys defined in RFC 9460, by key number
+const SVC_PARAM_KEY_NAMES: [&str; 7] = ["mandatory", "alpn", "no-default-alpn", "port", "ipv4hint", "ech", "ipv6hint"];
+
+fn svc_param_key_name(key: u16) -> String {
+    SVC_PARAM_KEY_NAMES
+        .get(key as usize)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("key{}", key))
+}
+
+// Presentation form of a SVCB or HTTPS record's rdata, falling back to `keyN="..."` for params that don't parse
+fn svcb_to_zone_rdata(svcb: &SVCB) -> String {
+    let mut rdata = format!("{} {}", svcb.priority, zone_name(&svcb.target));
+    for (key, value) in svcb.iter_params() {
+        let rendered = match key {
+            0 if value.len() % 2 == 0 => Some(
+                value
+                    .chunks(2)
+                    .map(|key| svc_param_key_name(u16::from_be_bytes([key[0], key[1]])))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            1 => split_character_strings(value).map(|protocols| {
+                let protocols: Vec<String> = protocols.iter().map(|protocol| String::from_utf8_lossy(protocol).to_string()).collect();
+                zone_quote(protocols.join(",").as_bytes())
+            }),
+            2 if value.is_empty() => None,
+            3 if value.len() == 2 => Some(u16::from_be_bytes([value[0], value[1]]).to_string()),
+            4 if value.len() % 4 == 0 => Some(
+                value
+                    .chunks(4)
+                    .map(|ip| Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]).to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            5 => Some(general_purpose::STANDARD.encode(value)),
+            6 if value.len() % 16 == 0 => Some(
+                value
+                    .chunks(16)
+                    .map(|ip| Ipv6Addr::from(<[u8; 16]>::try_from(ip).unwrap_or_default()).to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            _ => Some(zone_quote(value)),
+        };
+        match (key, rendered) {
+            (2, None) => rdata.push_str(" no-default-alpn"),
+            (_, Some(rendered)) => rdata.push_str(&format!(" {}={}", svc_param_key_name(key), rendered)),
+            (_, None) => rdata.push_str(&format!(" key{}={}", key, zone_quote(value))),
+        }
+    }
+    rdata
+}
+
+/**
+* Render a record as a zone file line in presentation format: `<name> <ttl> <class> <type> <rdata>`.
+* Names are fully qualified. Record types without a dedicated presentation format use the generic
+* `\# <length> <hex>` rdata form of RFC 3597, with `TYPE<number>` for types the DNS library doesn't know.
+*/
+pub fn resource_record_to_zone_line(record: &ResourceRecord) -> Result<String, String> {
+    let type_number = match &record.rdata {
+        RData::NULL(type_number, _) => *type_number,
+        rdata => u16::from(rdata.type_code()),
+    };
+    let type_name = match dns::TYPE::from(type_number) {
+        dns::TYPE::Unknown(_) => format!("TYPE{}", type_number),
+        known => format!("{:?}", known),
+    };
+
+    let rdata = match &record.rdata {
+        RData::A(a) => Ipv4Addr::from(a.address).to_string(),
+        RData::AAAA(aaaa) => Ipv6Addr::from(aaaa.address).to_string(),
+        RData::CNAME(cname) => zone_name(&cname.0),
+        RData::NS(ns) => zone_name(&ns.0),
+        RData::MX(mx) => format!("{} {}", mx.preference, zone_name(&mx.exchange)),
+        RData::SRV(srv) => format!("{} {} {} {}", srv.priority, srv.weight, srv.port, zone_name(&srv.target)),
+        RData::SVCB(svcb) => svcb_to_zone_rdata(svcb),
+        RData::HTTPS(https) => svcb_to_zone_rdata(&https.0),
+        RData::TXT(_) => {
+            let bytes = record_rdata_bytes(record)?;
+            let strings = split_character_strings(&bytes).ok_or_else(|| "Invalid TXT record".to_string())?;
+            strings.into_iter().map(zone_quote).collect::<Vec<_>>().join(" ")
+        },
+        RData::CAA(caa) => {
+            let bytes = record_rdata_bytes(record)?;
+            let tag_length = *bytes.get(1).ok_or_else(|| "Invalid CAA record".to_string())? as usize;
+            let value = bytes.get(2 + tag_length..).ok_or_else(|| "Invalid CAA record".to_string())?;
+            format!("{} {} {}", caa.flag, String::from_utf8_lossy(&bytes[2..2 + tag_length]), zone_quote(value))
+        },
+        _ => {
+            let bytes = record_rdata_bytes(record)?;
+            if bytes.is_empty() {
+                "\\# 0".to_string()
+            } else {
+                format!("\\# {} {}", bytes.len(), hex::encode(&bytes))
+            }
+        },
+    };
+
+    Ok(format!("{} {} {:?} {} {}", zone_name(&record.name), record.ttl, record.class, type_name, rdata))
+}
+
+// A zone file token, with quotes and escapes resolved; quoted tokens are never taken as a TTL, class or type
+struct ZoneToken {
+    bytes: Vec<u8>,
+    quoted: bool,
+}
+
+impl ZoneToken {
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).to_string()
+    }
+}
+
+// Split a zone file line into tokens, resolving `\X` and `\DDD` escapes and dropping `;` comments
+fn tokenize_zone_line(line: &str) -> Result<Vec<ZoneToken>, String> {
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b' ' | b'\t' | b'\r' => index += 1,
+            b';' => break,
+            b'(' | b')' => return Err("Multi-line records are not supported".to_string()),
+            _ => {
+                // Quotes may also open mid-token, as in `alpn="h2,h3"`
+                let quoted = bytes[index] == b'"';
+                let mut in_quotes = false;
+                let mut token = Vec::new();
+                loop {
+                    let Some(&byte) = bytes.get(index) else {
+                        if in_quotes {
+                            return Err("Unterminated quoted string".to_string());
+                        }
+                        break;
+                    };
+                    if byte == b'"' {
+                        in_quotes = !in_quotes;
+                        index += 1;
+                        if quoted && !in_quotes {
+                            break;
+                        }
+                        continue;
+                    }
+                    if !in_quotes && matches!(byte, b' ' | b'\t' | b'\r' | b';') {
+                        break;
+                    }
+                    if byte == b'\\' {
+                        let digits = bytes.get(index + 1..index + 4).filter(|digits| digits.iter().all(u8::is_ascii_digit));
+                        if let Some(digits) = digits {
+                            let value: u16 = str::from_utf8(digits).ok().and_then(|digits| digits.parse().ok()).unwrap_or(256);
+                            token.push(u8::try_from(value).map_err(|_| "Invalid \\DDD escape".to_string())?);
+                            index += 4;
+                        } else {
+                            token.push(*bytes.get(index + 1).ok_or_else(|| "Dangling escape".to_string())?);
+                            index += 2;
+                        }
+                        continue;
+                    }
+                    token.push(byte);
+                    index += 1;
+                }
+                tokens.push(ZoneToken { bytes: token, quoted });
+            },
+        }
+    }
+    Ok(tokens)
+}
+
+// Parse a name from a zone file, `@` standing for the key itself; pkarr qualifies relative names on signing
+fn parse_zone_name(name: &str) -> Result<dns::Name<'_>, String> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let name = if name.is_empty() { "@" } else { name };
+    dns::Name::new(name).map_err(|e| format!("Invalid name {}: {}", name, e))
+}
+
+// Parse the parameters of a SVCB or HTTPS record, in the same presentation form `resource_record_to_zone_line` writes
+fn parse_svc_params(svcb: &mut SVCB, params: &[ZoneToken]) -> Result<(), String> {
+    for param in params {
+        let param = param.text();
+        let (key, value) = match param.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (param.as_str(), None),
+        };
+        let key_number = match SVC_PARAM_KEY_NAMES.iter().position(|name| *name == key) {
+            Some(position) => position as u16,
+            None => key
+                .strip_prefix("key")
+                .and_then(|number| number.parse().ok())
+                .ok_or_else(|| format!("Unknown SVCB parameter: {}", key))?,
+        };
+        let invalid = || format!("Invalid value for SVCB parameter {}", key);
+        let list = || value.map(|value| value.split(',').collect::<Vec<_>>()).ok_or_else(invalid);
+        let wire: Vec<u8> = match key_number {
+            0 => {
+                let mut wire = Vec::new();
+                for name in list()? {
+                    let number = SVC_PARAM_KEY_NAMES
+                        .iter()
+                        .position(|known| *known == name)
+                        .map(|position| position as u16)
+                        .or_else(|| name.strip_prefix("key").and_then(|number| number.parse().ok()))
+                        .ok_or_else(invalid)?;
+                    wire.extend_from_slice(&number.to_be_bytes());
+                }
+                wire
+            },
+            1 => {
+                let mut wire = Vec::new();
+                for protocol in list()? {
+                    wire.push(u8::try_from(protocol.len()).map_err(|_| invalid())?);
+                    wire.extend_from_slice(protocol.as_bytes());
+                }
+                wire
+            },
+            2 if value.is_none() => Vec::new(),
+            3 => value.and_then(|port| port.parse::<u16>().ok()).ok_or_else(invalid)?.to_be_bytes().to_vec(),
+            4 => {
+                let mut wire = Vec::new();
+                for ip in list()? {
+                    wire.extend_from_slice(&ip.parse::<Ipv4Addr>().map_err(|_| invalid())?.octets());
+                }
+                wire
+            },
+            5 => general_purpose::STANDARD.decode(value.ok_or_else(invalid)?).map_err(|_| invalid())?,
+            6 => {
+                let mut wire = Vec::new();
+                for ip in list()? {
+                    wire.extend_from_slice(&ip.parse::<Ipv6Addr>().map_err(|_| invalid())?.octets());
+                }
+                wire
+            },
+            _ => value.map(|value| value.as_bytes().to_vec()).unwrap_or_default(),
+        };
+        svcb.set_param(key_number, wire).map_err(|e| format!("Invalid SVCB parameter {}: {}", key, e))?;
+    }
+    Ok(())
+}
+
+// Parse the fields of one zone file record, the owner name having already been taken off
+fn parse_zone_record(name: &str, fields: &[ZoneToken], default_ttl: u32) -> Result<ResourceRecord<'static>, String> {
+    let mut ttl = None;
+    let mut index = 0;
+    // The TTL and class are both optional and may come in either order
+    while let Some(field) = fields.get(index).filter(|field| !field.quoted) {
+        let text = field.text();
+        if ttl.is_none() && !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit()) {
+            ttl = Some(text.parse::<u32>().map_err(|_| format!("Invalid TTL: {}", text))?);
+        } else if text.eq_ignore_ascii_case("IN") {
+        } else if ["CH", "HS", "CS"].iter().any(|class| text.eq_ignore_ascii_case(class)) {
+            return Err(format!("Unsupported class: {}", text));
+        } else {
+            break;
+        }
+        index += 1;
+    }
+    let record_type = fields.get(index).ok_or_else(|| "Missing record type".to_string())?.text().to_uppercase();
+    let rdata_fields = &fields[index + 1..];
+    let rdata_texts: Vec<String> = rdata_fields.iter().map(ZoneToken::text).collect();
+    let expect = |count: usize| -> Result<(), String> {
+        if rdata_fields.len() < count {
+            return Err(format!("{} record needs {} rdata fields, got {}", record_type, count, rdata_fields.len()));
+        }
+        Ok(())
+    };
+    let number = |index: usize| -> Result<u16, String> {
+        rdata_texts[index].parse().map_err(|_| format!("Invalid number in {} record: {}", record_type, rdata_texts[index]))
+    };
+
+    let rdata = match record_type.as_str() {
+        "A" => {
+            expect(1)?;
+            let address: Ipv4Addr = rdata_texts[0].parse().map_err(|_| format!("Invalid IPv4 address: {}", rdata_texts[0]))?;
+            RData::A(A { address: address.into() })
+        },
+        "AAAA" => {
+            expect(1)?;
+            let address: Ipv6Addr = rdata_texts[0].parse().map_err(|_| format!("Invalid IPv6 address: {}", rdata_texts[0]))?;
+            RData::AAAA(AAAA { address: address.into() })
+        },
+        "TXT" => {
+            expect(1)?;
+            let mut txt = TXT::new();
+            for field in rdata_fields {
+                let string = dns::CharacterString::new(&field.bytes).map_err(|e| format!("Invalid TXT string: {}", e))?;
+                txt.add_char_string(string);
+            }
+            RData::TXT(txt)
+        },
+        "CNAME" => {
+            expect(1)?;
+            RData::CNAME(CNAME(parse_zone_name(&rdata_texts[0])?))
+        },
+        "NS" => {
+            expect(1)?;
+            RData::NS(NS(parse_zone_name(&rdata_texts[0])?))
+        },
+        "MX" => {
+            expect(2)?;
+            RData::MX(MX { preference: number(0)?, exchange: parse_zone_name(&rdata_texts[1])? })
+        },
+        "SRV" => {
+            expect(4)?;
+            RData::SRV(SRV {
+                priority: number(0)?,
+                weight: number(1)?,
+                port: number(2)?,
+                target: parse_zone_name(&rdata_texts[3])?,
+            })
+        },
+        "SVCB" | "HTTPS" => {
+            expect(2)?;
+            let mut svcb = SVCB::new(number(0)?, parse_zone_name(&rdata_texts[1])?);
+            parse_svc_params(&mut svcb, &rdata_fields[2..])?;
+            if record_type == "HTTPS" {
+                RData::HTTPS(HTTPS(svcb))
+            } else {
+                RData::SVCB(svcb)
+            }
+        },
+        other => return Err(format!("Unsupported record type: {}", other)),
+    };
+
+    let record = ResourceRecord::new(parse_zone_name(name)?, dns::CLASS::IN, ttl.unwrap_or(default_ttl), rdata);
+    Ok(record.into_owned())
+}
+
+/**
+* Parse zone file text in presentation format into records. Supports A, AAAA, TXT, CNAME, NS, MX, SRV, SVCB and HTTPS
+* records of class IN, `$TTL` (the default TTL being 3600 seconds), `;` comments, and lines starting with whitespace
+* reusing the previous owner name. Names are kept relative to the key, `@` standing for the key itself.
+* Errors are prefixed with their 1-based line number.
+*/
+pub fn records_from_zonefile(zonefile: &str) -> Result<Vec<ResourceRecord<'static>>, String> {
+    let mut records = Vec::new();
+    let mut default_ttl: u32 = 3600;
+    let mut previous_name: Option<String> = None;
+    for (line_index, line) in zonefile.lines().enumerate() {
+        let line_error = |error: String| format!("Line {}: {}", line_index + 1, error);
+        let tokens = tokenize_zone_line(line).map_err(line_error)?;
+        let Some(first) = tokens.first() else {
+            continue;
+        };
+        let first_text = first.text();
+        if let Some(directive) = first_text.strip_prefix('$') {
+            match (directive.to_uppercase().as_str(), tokens.get(1)) {
+                ("TTL", Some(ttl)) => {
+                    default_ttl = ttl.text().parse().map_err(|_| line_error(format!("Invalid TTL: {}", ttl.text())))?;
+                },
+                _ => return Err(line_error(format!("Unsupported directive: {}", first_text))),
+            }
+            continue;
+        }
+
+        let (name, fields) = if line.starts_with([' ', '\t']) {
+            let name = previous_name.clone().ok_or_else(|| line_error("Missing owner name".to_string()))?;
+            (name, &tokens[..])
+        } else {
+            (first_text, &tokens[1..])
+        };
+        records.push(parse_zone_record(&name, fields, default_ttl).map_err(line_error)?);
+        previous_name = Some(name);
+    }
+    Ok(records)
+}
+
+/**
+* Parse a JSON array of records into ResourceRecords, see `resource_record_from_json`
+*/
+pub fn records_from_json(records_json: &str) -> Result<Vec<ResourceRecord<'static>>, String> {
+    let value: serde_json::Value = serde_json::from_str(records_json)
+        .map_err(|e| format!("Failed to parse records JSON: {}", e))?;
+    let records = value.as_array().ok_or_else(|| "Records JSON must be an array".to_string())?;
+    records.iter().map(resource_record_from_json).collect()
+}
+
+/**
+* Build the bytes a pkarr signature covers: the bencoded sequence number (timestamp) and value
+* (encoded DNS packet), as defined in BEP 44
+*/
+pub fn signable_bytes(timestamp: u64, encoded_packet: &[u8]) -> Vec<u8> {
+    let mut signable = format!("3:seqi{}e1:v{}:", timestamp, encoded_packet.len()).into_bytes();
+    signable.extend_from_slice(encoded_packet);
+    signable
+}
+
+/**
+* Split bytes built by `signable_bytes` back into the timestamp and the encoded DNS packet
+*/
+pub fn parse_signable_bytes(signable: &[u8]) -> Result<(u64, &[u8]), String> {
+    let invalid = || "Invalid packet bytes, expected `3:seqi<timestamp>e1:v<length>:<packet>`".to_string();
+    let rest = signable.strip_prefix(b"3:seqi").ok_or_else(invalid)?;
+    let timestamp_end = rest.iter().position(|byte| *byte == b'e').ok_or_else(invalid)?;
+    let timestamp: u64 = std::str::from_utf8(&rest[..timestamp_end]).ok().and_then(|t| t.parse().ok()).ok_or_else(invalid)?;
+    let rest = rest[timestamp_end + 1..].strip_prefix(b"1:v").ok_or_else(invalid)?;
+    let length_end = rest.iter().position(|byte| *byte == b':').ok_or_else(invalid)?;
+    let length: usize = std::str::from_utf8(&rest[..length_end]).ok().and_then(|l| l.parse().ok()).ok_or_else(invalid)?;
+    let encoded_packet = &rest[length_end + 1..];
+    if encoded_packet.len() != length {
+        return Err(invalid());
+    }
+    Ok((timestamp, encoded_packet))
+}
+
+/**
+* Encode records into a compressed DNS packet the way pkarr does before signing: relative names
+* get the origin (a z-base32 public key) appended, and `@` stands for the origin itself
+*/
+pub fn encode_records_for_origin(records: &[ResourceRecord], origin: &str) -> Result<Vec<u8>, String> {
+    let names: Vec<String> = records
+        .iter()
+        .map(|record| {
+            let name = record.name.to_string();
+            let name = name.trim_end_matches('.');
+            if name.is_empty() || name == "@" || name == origin {
+                origin.to_string()
+            } else if name.ends_with(&format!(".{}", origin)) {
+                name.to_string()
+            } else {
+                format!("{}.{}", name, origin)
+            }
+        })
+        .collect();
+
+    let mut packet = dns::Packet::new_reply(0);
+    for (record, name) in records.iter().zip(names.iter()) {
+        packet.answers.push(ResourceRecord::new(
+            dns::Name::new_unchecked(name),
+            record.class,
+            record.ttl,
+            record.rdata.clone(),
+        ));
+    }
+    packet.build_bytes_vec_compressed().map_err(|e| format!("Failed to encode DNS packet: {}", e))
+}
+
+/**
+* Sign a DNS packet with an explicit timestamp (microseconds since the UNIX epoch) instead of the current time
+*/
+pub fn sign_packet_with_timestamp(keypair: &Keypair, packet: &dns::Packet, timestamp: u64) -> Result<SignedPacket, String> {
+    // Let pkarr normalize the record names and encode the packet, then re-sign it with our timestamp
+    let signed_packet = SignedPacket::from_packet(keypair, packet)
+        .map_err(|e| format!("Failed to create signed packet: {}", e))?;
+    let encoded_packet = signed_packet.encoded_packet();
+    let signature = keypair.sign(&signable_bytes(timestamp, &encoded_packet));
+
+    let mut bytes = Vec::with_capacity(104 + encoded_packet.len());
+    bytes.extend_from_slice(keypair.public_key().as_bytes());
+    bytes.extend_from_slice(&signature.to_bytes());
+    bytes.extend_from_slice(&timestamp.to_be_bytes());
+    bytes.extend_from_slice(&encoded_packet);
+
+    SignedPacket::from_bytes(&Bytes::from(bytes))
+        .map_err(|e| format!("Failed to create signed packet: {}", e))
+}
+
 pub fn construct_pubky_url(public_key: &str, domain: &str, path_segments: &[&str]) -> String {
     // Construct the base URL
     let mut url = format!("pubky://{}/pub/{}", public_key, domain);
@@ -243,11 +946,392 @@ pub fn get_list_url(full_url: &str) -> Option<String> {
     }
 }
 
-pub fn session_to_json(session: &Session) -> String {
-    let json_obj = json!({
+/**
+* Whether a failed request is worth retrying: timeouts, connection failures, 429 and 5xx responses.
+* Other request errors (invalid requests, redirect loops, undecodable bodies) fail the same way again.
+*/
+pub fn is_transient_error(error: &pubky::Error) -> bool {
+    match error {
+        pubky::Error::Reqwest(error) => {
+            error.is_timeout()
+                || error.is_connect()
+                || error.status().is_some_and(|status| status.is_server_error() || status.as_u16() == 429)
+        },
+        _ => false,
+    }
+}
+
+/**
+* Whether a request failed for lack of connectivity, i.e. timed out or couldn't connect, as when offline
+*/
+pub fn is_connectivity_error(error: &pubky::Error) -> bool {
+    matches!(error, pubky::Error::Reqwest(error) if error.is_timeout() || error.is_connect())
+}
+
+/**
+* Turn a list cursor into the full URL it stands for: cursors are either a full `pubky://` URL
+* or a path relative to the listed directory
+*/
+pub fn list_cursor_url(directory_url: &str, cursor: &str) -> String {
+    if cursor.starts_with("pubky://") {
+        cursor.to_string()
+    } else {
+        format!("{}/{}", directory_url.trim_end_matches('/'), cursor.trim_start_matches('/'))
+    }
+}
+
+/**
+* Find the first case-insensitive occurrence of a query in a text, and return it with up to
+* `context_chars` characters of context on each side, marking cut ends with `...`
+*/
+pub fn search_excerpt(text: &str, query: &str, context_chars: usize) -> Option<String> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return None;
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let lowercase_at = |index: usize| chars[index..].iter().flat_map(|c| c.to_lowercase());
+    let start = (0..chars.len()).find(|&index| lowercase_at(index).take(query.len()).eq(query.iter().copied()))?;
+
+    // Count the original characters the match spans, as lowercasing can change the number of characters
+    let mut matched_chars = 0;
+    let mut lowercase_length = 0;
+    while lowercase_length < query.len() {
+        lowercase_length += chars[start + matched_chars].to_lowercase().count();
+        matched_chars += 1;
+    }
+
+    let excerpt_start = start.saturating_sub(context_chars);
+    let excerpt_end = (start + matched_chars + context_chars).min(chars.len());
+    let mut excerpt: String = chars[excerpt_start..excerpt_end].iter().collect();
+    if excerpt_start > 0 {
+        excerpt.insert_str(0, "...");
+    }
+    if excerpt_end < chars.len() {
+        excerpt.push_str("...");
+    }
+    Some(excerpt)
+}
+
+/**
+* Name of the directory soft deleted files are moved to, inside the directory they were deleted from
+*/
+pub const TRASH_DIRECTORY: &str = "_trash";
+
+/**
+* Split a file URL into its directory URL (without trailing slash) and file name
+*/
+pub fn split_file_url(url: &str) -> Option<(&str, &str)> {
+    let (directory, file_name) = url.trim_end_matches('/').rsplit_once('/')?;
+    if file_name.is_empty() || directory.ends_with('/') {
+        return None;
+    }
+    Some((directory, file_name))
+}
+
+/**
+* URL a soft deleted file is kept at: `{directory}/_trash/{deleted_at_ms}/{file name}`
+*/
+pub fn trash_url(file_url: &str, deleted_at_ms: u64) -> Option<String> {
+    let (directory, file_name) = split_file_url(file_url)?;
+    Some(format!("{}/{}/{}/{}", directory, TRASH_DIRECTORY, deleted_at_ms, file_name))
+}
+
+/**
+* Parse a URL listed under `{directory}/_trash/` into the deletion time and original URL of the file
+*/
+pub fn parse_trash_url(directory: &str, url: &str) -> Option<(u64, String)> {
+    let directory = directory.trim_end_matches('/');
+    let rest = url.strip_prefix(&format!("{}/{}/", directory, TRASH_DIRECTORY))?;
+    let (deleted_at_ms, file_name) = rest.split_once('/')?;
+    if file_name.is_empty() || file_name.contains('/') {
+        return None;
+    }
+    Some((deleted_at_ms.parse().ok()?, format!("{}/{}", directory, file_name)))
+}
+
+/**
+* Detect a publish refused because of rate limiting: a relay answering 429, a DHT node reporting a rate limit,
+* or the DHT refusing a publish while another one for the same key is still in flight.
+* Returns Some with the `Retry-After` seconds suggested by the relay, if it sent one.
+*/
+pub fn publish_rate_limit(error: &pkarr::Error) -> Option<Option<u64>> {
+    match error {
+        pkarr::Error::RelayError(error) => match error.as_ref() {
+            ureq::Error::Status(429, response) => Some(
+                response.header("Retry-After").and_then(|value| value.trim().parse::<u64>().ok()),
+            ),
+            _ => None,
+        },
+        pkarr::Error::PublishInflight
+        | pkarr::Error::MainlineError(pkarr::mainline::Error::PutQueryIsInflight(_)) => Some(None),
+        pkarr::Error::MainlineError(pkarr::mainline::Error::QueryError(error)) => {
+            let description = error.description.to_lowercase();
+            (description.contains("rate limit") || description.contains("too many")).then_some(None)
+        },
+        _ => None,
+    }
+}
+
+/**
+* Detect a publish refused because its timestamp isn't newer than the packet already published: a relay
+* answering 409, a DHT node reporting an older sequence number, or a newer packet in the local cache.
+* Packets are timestamped with the device clock, so this usually means the clock is behind.
+*/
+pub fn publish_clock_skew(error: &pkarr::Error) -> bool {
+    match error {
+        pkarr::Error::NotMostRecent => true,
+        pkarr::Error::RelayError(error) => matches!(error.as_ref(), ureq::Error::Status(409, _)),
+        pkarr::Error::MainlineError(pkarr::mainline::Error::QueryError(error)) => {
+            error.code == 302 || error.description.to_lowercase().contains("sequence number less than current")
+        },
+        _ => false,
+    }
+}
+
+/**
+* Error message for a failed publish: a `CLOCK_SKEW` error if its timestamp was rejected, a `RATE_LIMITED` error
+* (with `retry_after_seconds` when known) if it was rate limited
+*/
+pub fn publish_error_message(error: &pkarr::Error) -> String {
+    if publish_clock_skew(error) {
+        return format!(
+            "CLOCK_SKEW: a more recent packet is already published, check the device clock with check_clock_skew: {}",
+            error
+        );
+    }
+    match publish_rate_limit(error) {
+        Some(Some(retry_after_seconds)) => format!("RATE_LIMITED: retry_after_seconds={}: {}", retry_after_seconds, error),
+        Some(None) => format!("RATE_LIMITED: {}", error),
+        None => format!("Failed to publish: {}", error),
+    }
+}
+
+/**
+* Match a name against a glob pattern where `*` matches any run of characters and `?` a single one
+*/
+pub fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern, and the name position it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/**
+* Compute the content identifier of some content: `cid:sha2-256:<base58btc SHA-256 hash>`
+* @param content The content to identify
+* @returns The content identifier and the hex encoded SHA-256 hash
+**/
+pub fn content_id(content: &[u8]) -> (String, String) {
+    let hash = Sha256::digest(content);
+    (format!("cid:sha2-256:{}", bs58::encode(hash).into_string()), hex::encode(hash))
+}
+
+/**
+* Check that a URL targets data on a homeserver: `pubky://` or an `https://` homeserver URL
+*/
+pub fn validate_data_url_scheme(url: &url::Url) -> Result<(), String> {
+    match url.scheme() {
+        "pubky" | "https" => Ok(()),
+        scheme => Err(format!("INVALID_SCHEME: expected a pubky:// or https:// URL, got {}://", scheme)),
+    }
+}
+
+/**
+* Rewrite an `https://<public key>/<path>` URL as `pubky://<public key>/<path>`.
+* pubky's client resolves the homeserver of such URLs but drops their path; homeserver URLs
+* (`https://<homeserver host>/<public key>/<path>`) and `pubky://` URLs are returned unchanged.
+*/
+pub fn normalize_data_url(url: url::Url) -> url::Url {
+    let is_public_key_host = url.host_str().is_some_and(|host| pkarr::PublicKey::try_from(host).is_ok());
+    if url.scheme() != "https" || !is_public_key_host {
+        return url;
+    }
+    let mut pubky_url = format!("pubky://{}{}", url.host_str().unwrap_or_default(), url.path());
+    if let Some(query) = url.query() {
+        pubky_url.push('?');
+        pubky_url.push_str(query);
+    }
+    url::Url::parse(&pubky_url).unwrap_or(url)
+}
+
+/**
+* The public key owning the data a URL points to: the host of a `pubky://` URL, or the first path segment of
+* an `https://` homeserver URL. Expects a URL passed through `normalize_data_url`.
+*/
+pub fn data_url_public_key(url: &url::Url) -> Option<String> {
+    match url.scheme() {
+        "pubky" => url.host_str().map(|host| host.to_string()),
+        "https" => url.path_segments()?.next().filter(|segment| !segment.is_empty()).map(|segment| segment.to_string()),
+        _ => None,
+    }
+}
+
+/**
+* Build a `pubkyapp://open?pubky=<key>&path=<path>` deep link to content on a homeserver
+*/
+pub fn build_share_link(public_key: &pkarr::PublicKey, path: &str) -> Result<url::Url, String> {
+    let path = normalize_share_path(path)?;
+    let mut link = url::Url::parse("pubkyapp://open").map_err(|e| format!("Failed to build link: {}", e))?;
+    link.query_pairs_mut()
+        .append_pair("pubky", &public_key.to_string())
+        .append_pair("path", &path);
+    Ok(link)
+}
+
+/**
+* Split a share link built by `build_share_link` into its public key and path
+*/
+pub fn parse_share_link_url(link: &str) -> Result<(pkarr::PublicKey, String), String> {
+    let link = url::Url::parse(link).map_err(|_| "Invalid URL".to_string())?;
+    if link.scheme() != "pubkyapp" || link.host_str() != Some("open") {
+        return Err("Invalid share link, expected 'pubkyapp://open'".to_string());
+    }
+    let query_params: std::collections::HashMap<_, _> = link.query_pairs().into_owned().collect();
+    let public_key = query_params.get("pubky").ok_or_else(|| "Missing pubky".to_string())?;
+    let public_key = pkarr::PublicKey::try_from(public_key.as_str())
+        .map_err(|e| format!("Invalid public key: {}", e))?;
+    let path = query_params.get("path").ok_or_else(|| "Missing path".to_string())?;
+    Ok((public_key, normalize_share_path(path)?))
+}
+
+// Paths are absolute and may not walk up the directory tree
+fn normalize_share_path(path: &str) -> Result<String, String> {
+    if path.is_empty() || path.split('/').any(|segment| segment == "..") {
+        return Err(format!("Invalid path: {}", path));
+    }
+    if path.starts_with('/') {
+        Ok(path.to_string())
+    } else {
+        Ok(format!("/{}", path))
+    }
+}
+
+/**
+* A capability as a `{ "path", "read", "write" }` object, so bindings don't have to parse `/path/:rw` strings
+*/
+pub fn capability_to_json(capability: &Capability) -> serde_json::Value {
+    json!({
+        "path": capability.scope,
+        "read": capability.actions.contains(&Action::Read),
+        "write": capability.actions.contains(&Action::Write),
+    })
+}
+
+pub fn session_to_json_value(session: &Session) -> serde_json::Value {
+    json!({
         "pubky": session.pubky().to_string(),
         "capabilities": session.capabilities().iter().map(|c| c.to_string()).collect::<Vec<String>>(),
-    });
+        "capabilities_detailed": session.capabilities().iter().map(capability_to_json).collect::<Vec<_>>(),
+    })
+}
+
+pub fn session_to_json(session: &Session) -> String {
+    serde_json::to_string(&session_to_json_value(session)).unwrap_or_else(|e| format!("Failed to serialize JSON: {}", e))
+}
+
+/**
+* Find the preferred SVCB or HTTPS target published under a name in a signed packet
+* @param signed_packet The resolved signed packet
+* @param name The record name, relative to the packet's public key (e.g. "_pubky" or ".")
+* @returns The target of the record with the lowest priority and its port parameter if any,
+* or None if no SVCB or HTTPS record exists for the name
+**/
+pub fn get_svcb_target(signed_packet: &SignedPacket, name: &str) -> Option<(String, Option<u16>)> {
+    signed_packet
+        .resource_records(name)
+        .filter_map(|record| match &record.rdata {
+            RData::SVCB(svcb) => Some(svcb),
+            RData::HTTPS(https) => Some(&https.0),
+            _ => None,
+        })
+        .min_by_key(|svcb| svcb.priority)
+        .map(|svcb| {
+            let port = svcb
+                .get_param(SVCB::PORT)
+                .filter(|port| port.len() == 2)
+                .map(|port| u16::from_be_bytes([port[0], port[1]]));
+            (svcb.target.to_string().trim_end_matches('.').to_string(), port)
+        })
+}
+
+/**
+* Build the HTTP(S) endpoint URL for a SVCB target, using plain HTTP for localhost
+*/
+pub fn svcb_target_to_endpoint(target: &str, port: Option<u16>) -> String {
+    let scheme = if target.starts_with("localhost") { "http" } else { "https" };
+    match port {
+        Some(port) => format!("{}://{}:{}", scheme, target, port),
+        None => format!("{}://{}", scheme, target),
+    }
+}
+
+/**
+* Escape a vCard property value: backslashes, commas, semicolons and line breaks
+*/
+fn escape_vcard_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/**
+* Fold a vCard content line into lines of at most 75 octets, continuation lines starting with a space
+*/
+fn fold_vcard_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut line_length = 0;
+    for character in line.chars() {
+        if line_length + character.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+        folded.push(character);
+        line_length += character.len_utf8();
+    }
+    folded
+}
+
+/**
+* Build a vCard 4.0 for a pubky profile. The formatted name falls back to the public key when the profile has no name.
+*/
+pub fn build_vcard(public_key: &pkarr::PublicKey, name: Option<&str>, bio: Option<&str>, avatar_url: Option<&str>) -> String {
+    let public_key = public_key.to_string();
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        "VERSION:4.0".to_string(),
+        format!("FN:{}", escape_vcard_value(name.unwrap_or(&public_key))),
+    ];
+    if let Some(bio) = bio {
+        lines.push(format!("NOTE:{}", escape_vcard_value(bio)));
+    }
+    if let Some(avatar_url) = avatar_url {
+        lines.push(format!("PHOTO:{}", avatar_url));
+    }
+    lines.push(format!("URL:pubky://{}", public_key));
+    lines.push(format!("X-PUBKY-KEY:{}", public_key));
+    lines.push("END:VCARD".to_string());
 
-    serde_json::to_string(&json_obj).unwrap_or_else(|e| format!("Failed to serialize JSON: {}", e))
+    lines.iter().map(|line| fold_vcard_line(line) + "\r\n").collect()
 }
@@ -3,7 +3,7 @@ use tokio;
 use base64;
 
 mod common;
-use crate::common::{get_test_setup};
+use crate::common::{get_test_setup, generate_test_keypair};
 
 // Test keypair generation
 #[test]
@@ -16,6 +16,10 @@ fn test_put_and_get_and_list() {
 
     let sign_up_result = sign_up(secret_key, homeserver);
     assert_eq!(sign_up_result[0], "success");
+    let session: serde_json::Value = serde_json::from_str(&sign_up_result[1]).unwrap();
+    assert_eq!(session["pubky"], public_key);
+    assert_ne!(session["token_required"], true);
+    assert_eq!(session["token_consumed"], false);
 
     let inner_url = url.clone();
 
@@ -73,6 +77,27 @@ async fn test_get_public_key_from_secret_key() {
     assert_eq!(result[0], "error");
 }
 
+// Test public key comparison across textual forms
+#[test]
+fn test_pubky_equals() {
+    let (keypair, _, _) = get_test_setup();
+    let public_key = keypair.public_key().to_string();
+
+    let result = pubky_equals(public_key.clone(), format!("pk:{}", public_key));
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["equal"], true);
+
+    let other = generate_secret_key();
+    let other_json: serde_json::Value = serde_json::from_str(&other[1]).unwrap();
+    let result = pubky_equals(public_key.clone(), other_json["public_key"].as_str().unwrap().to_string());
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["equal"], false);
+
+    let result = pubky_equals(public_key, "invalid_key".to_string());
+    assert_eq!(result[0], "error");
+}
+
 // Test sign up functionality
 #[test]
 fn test_publish_and_resolve() {
@@ -224,4 +249,1933 @@ fn test_error_cases() {
     // Test empty recovery file creation
     let recovery_result = create_recovery_file("".to_string(), "passphrase".to_string());
     assert_eq!(recovery_result[0], "error");
-}
\ No newline at end of file
+}
+
+// Test relaying a malformed signed packet
+#[test]
+fn test_relay_signed_packet_invalid_input() {
+    let result = relay_signed_packet("not hex".to_string(), "https://relay.pkarr.org".to_string());
+    assert_eq!(result[0], "error");
+
+    let result = relay_signed_packet(hex::encode([1u8; 50]), "https://relay.pkarr.org".to_string());
+    assert_eq!(result[0], "error");
+
+    let result = relay_signed_packet(hex::encode([0u8; 120]), "https://relay.pkarr.org".to_string());
+    assert_eq!(result[0], "error");
+    assert_eq!(result[1], "Signed packet has an empty signature");
+}
+
+// Test resolving the homeserver and endpoint of a key
+#[test]
+fn test_resolve_identity() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+
+    let sign_up_result = sign_up(secret_key, homeserver.clone());
+    assert_eq!(sign_up_result[0], "success");
+
+    let result = resolve_identity(keypair.public_key().to_string());
+    assert_eq!(result[0], "success");
+
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["pubky"], keypair.public_key().to_string());
+    assert_eq!(json["homeserver"], homeserver);
+
+    let result = resolve_identity("invalid_public_key".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test DNS packet size estimation
+#[test]
+fn test_estimate_dns_packet_size() {
+    let records = serde_json::json!([
+        { "name": "test.record", "class": "IN", "ttl": 30, "rdata": { "type": "TXT", "strings": ["hello=world"] } },
+        { "name": "_pubky", "ttl": 3600, "rdata": { "type": "A", "address": "127.0.0.1" } },
+    ]);
+    let result = estimate_dns_packet_size(records.to_string());
+    assert_eq!(result[0], "success");
+
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert!(json["packet_size_bytes"].as_u64().unwrap() > 0);
+    assert_eq!(json["max_allowed_bytes"], 1000);
+    assert_eq!(json["fits"], true);
+
+    let large_txt: Vec<String> = (0..10).map(|_| "x".repeat(200)).collect();
+    let records = serde_json::json!([{ "name": "large", "rdata": { "type": "TXT", "strings": large_txt } }]);
+    let result = estimate_dns_packet_size(records.to_string());
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["fits"], false);
+
+    let records = serde_json::json!([{ "name": "bad", "rdata": { "type": "BOGUS" } }]);
+    let result = estimate_dns_packet_size(records.to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test publishing with a custom timestamp
+#[test]
+fn test_publish_with_timestamp() {
+    let (_, secret_key, _) = get_test_setup();
+
+    // Timestamps far in the future are rejected before anything is published
+    let far_future = u64::MAX / 2;
+    let result = publish_with_timestamp("test.record".to_string(), "test content".to_string(), secret_key.clone(), Some(far_future));
+    assert_eq!(result[0], "error");
+
+    let result = publish_with_timestamp("test.record".to_string(), "test content".to_string(), secret_key, None);
+    assert_eq!(result[0], "success");
+}
+
+// Test time locked recovery files
+#[test]
+fn test_time_lock_recovery_file() {
+    let (_, secret_key, _) = get_test_setup();
+    let passphrase = "test_passphrase".to_string();
+
+    // Locked until far in the future
+    let create_result = create_time_lock_recovery_file(secret_key.clone(), passphrase.clone(), 32503680000);
+    assert_eq!(create_result[0], "success");
+    let decrypt_result = decrypt_time_locked_recovery_file(create_result[1].clone(), passphrase.clone());
+    assert_eq!(decrypt_result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&decrypt_result[1]).unwrap();
+    assert_eq!(json["locked"], true);
+    assert_eq!(json["unlocks_at"], 32503680000u64);
+    let recovery_file_bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &create_result[1]).unwrap();
+    assert!(decrypt_time_locked_recovery_file_bytes(&recovery_file_bytes, &passphrase, 0).is_err());
+    assert!(decrypt_time_locked_recovery_file_bytes(&recovery_file_bytes, &passphrase, 32503680000).is_ok());
+
+    // Already unlocked
+    let create_result = create_time_lock_recovery_file(secret_key.clone(), passphrase.clone(), 0);
+    let decrypt_result = decrypt_time_locked_recovery_file(create_result[1].clone(), passphrase);
+    let json: serde_json::Value = serde_json::from_str(&decrypt_result[1]).unwrap();
+    assert_eq!(json["locked"], false);
+    assert_eq!(json["secret_key"], secret_key);
+
+    // Wrong passphrase
+    let decrypt_result = decrypt_time_locked_recovery_file(create_result[1].clone(), "wrong".to_string());
+    assert_eq!(decrypt_result[0], "error");
+}
+
+// Test version info
+#[test]
+fn test_version_info() {
+    let result = version_info();
+    assert_eq!(result[0], "success");
+
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["ffi_version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(json["pubky"], "0.3.0");
+    assert!(json["pkarr"].is_string());
+}
+
+// Test get with redirects error handling
+#[test]
+fn test_get_with_redirects_invalid_url() {
+    let result = get_with_redirects("not a url".to_string(), 5);
+    assert_eq!(result[0], "error");
+    assert_eq!(result[1], "Failed to parse URL");
+
+    let result = get_with_redirects("pubky://invalid_key/pub/test".to_string(), 5);
+    assert_eq!(result[0], "error");
+}
+
+// Test preloading public keys
+#[test]
+fn test_preload_public_keys() {
+    let (keypair, _, _) = get_test_setup();
+    let public_key = keypair.public_key().to_string();
+
+    let result = preload_public_keys(format!("[\"{}\"]", public_key));
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["prefetch_started"], 1);
+
+    let result = preload_public_keys("[\"invalid_key\"]".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test listing supported DNS record types
+#[test]
+fn test_get_dns_record_types() {
+    let result = get_dns_record_types();
+    assert_eq!(result[0], "success");
+    let types: Vec<String> = serde_json::from_str(&result[1]).unwrap();
+    assert!(types.contains(&"TXT".to_string()));
+    assert!(types.contains(&"SVCB".to_string()));
+}
+
+// Test building and publishing the homeserver record
+#[test]
+fn test_homeserver_record() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+
+    let result = build_homeserver_record(homeserver.clone());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["name"], "_pubky");
+    assert_eq!(json["rdata"]["type"], "HTTPS");
+    assert_eq!(json["rdata"]["target"], homeserver);
+
+    let result = build_homeserver_record("invalid_key".to_string());
+    assert_eq!(result[0], "error");
+
+    let result = publish_homeserver_record(secret_key, homeserver, 3600);
+    assert_eq!(result[0], "success");
+    assert_eq!(result[1], keypair.public_key().to_string());
+}
+
+// Test TXT record helpers
+#[test]
+fn test_txt_record_helpers() {
+    let result = create_txt_record_string(r#"{"v":"1","name":"alice"}"#.to_string());
+    assert_eq!(result[0], "success");
+
+    let parsed = parse_txt_record(result[1].clone());
+    assert_eq!(parsed[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&parsed[1]).unwrap();
+    assert_eq!(json["format"], "kv");
+    assert_eq!(json["pairs"]["v"], "1");
+    assert_eq!(json["pairs"]["name"], "alice");
+
+    let parsed = parse_txt_record("just some text".to_string());
+    let json: serde_json::Value = serde_json::from_str(&parsed[1]).unwrap();
+    assert_eq!(json["format"], "raw");
+    assert!(json["pairs"].is_null());
+
+    let result = create_txt_record_string(r#"{"key":"a;b"}"#.to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test active account tracking
+#[test]
+fn test_active_accounts() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+    let public_key = keypair.public_key().to_string();
+
+    let sign_up_result = sign_up(secret_key.clone(), homeserver.clone());
+    assert_eq!(sign_up_result[0], "success");
+
+    let result = active_accounts();
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    let account = json.as_array().unwrap().iter().find(|a| a["pubky"] == public_key.as_str()).unwrap();
+    assert_eq!(account["homeserver"], homeserver);
+
+    let sign_out_result = sign_out(secret_key);
+    assert_eq!(sign_out_result[0], "success");
+
+    let result = active_accounts();
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert!(json.as_array().unwrap().iter().all(|a| a["pubky"] != public_key.as_str()));
+}
+
+// Test switching networks signs every tracked account out
+#[test]
+fn test_switch_network_clears_active_accounts() {
+    let keypair = generate_test_keypair();
+    let public_key = keypair.public_key().to_string();
+    let secret_key = hex::encode(keypair.secret_key());
+    let (_, _, homeserver) = get_test_setup();
+
+    let sign_up_result = sign_up(secret_key, homeserver);
+    assert_eq!(sign_up_result[0], "success");
+    assert!(is_account_tracked(&public_key));
+
+    let switch_result = switch_network(false);
+    assert_eq!(switch_result[0], "success");
+
+    assert!(!is_account_tracked(&public_key));
+    let result = active_accounts();
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert!(json.as_array().unwrap().iter().all(|a| a["pubky"] != public_key.as_str()));
+}
+
+// Test idempotent put
+#[test]
+fn test_put_idempotent() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+    let public_key = keypair.public_key().to_string();
+    let url = format!("pubky://{}/pub/test.com/idempotent", public_key);
+
+    let sign_up_result = sign_up(secret_key, homeserver);
+    assert_eq!(sign_up_result[0], "success");
+
+    let result = put_idempotent(url.clone(), "first".to_string(), "write-1".to_string());
+    assert_eq!(result[0], "success");
+
+    // Same key and content does not write again
+    let result = put_idempotent(url.clone(), "first".to_string(), "write-1".to_string());
+    assert_eq!(result[0], "success");
+    let get_result = get(url.clone());
+    assert_eq!(get_result[1], "first");
+
+    // Same key with other content is rejected rather than dropped
+    let result = put_idempotent(url.clone(), "second".to_string(), "write-1".to_string());
+    assert_eq!(result[0], "error");
+    let get_result = get(url.clone());
+    assert_eq!(get_result[1], "first");
+
+    // Same key for another URL is rejected
+    let result = put_idempotent(format!("{}-other", url), "other".to_string(), "write-1".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test put_idempotent rejects URLs with the wrong scheme, like put
+#[test]
+fn test_put_idempotent_invalid_scheme() {
+    let result = put_idempotent("http://example.com/pub/file".to_string(), "content".to_string(), "scheme-1".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("INVALID_SCHEME"));
+}
+
+// Test runtime configuration validation
+#[test]
+fn test_configure_runtime_invalid_thread_count() {
+    let result = configure_runtime(0);
+    assert_eq!(result[0], "error");
+    let result = configure_runtime(17);
+    assert_eq!(result[0], "error");
+}
+
+// Test DHT key derivation
+#[test]
+fn test_dht_key() {
+    let (keypair, _, _) = get_test_setup();
+
+    let result = dht_key(keypair.public_key().to_string());
+    assert_eq!(result[0], "success");
+    assert_eq!(result[1].len(), 40);
+    assert_eq!(result, dht_key(keypair.public_key().to_string()));
+
+    let result = dht_key("invalid_key".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test glob matching used by list_filtered
+#[test]
+fn test_glob_matches() {
+    assert!(glob_matches("*.jpg", "photo.jpg"));
+    assert!(!glob_matches("*.jpg", "photo.png"));
+    assert!(glob_matches("img_??.png", "img_01.png"));
+    assert!(!glob_matches("img_??.png", "img_1.png"));
+    assert!(glob_matches("*a*b*", "xxaxxbxx"));
+    assert!(glob_matches("*", ""));
+}
+
+// Test resolve cache TTL configuration
+#[test]
+fn test_max_resolve_cache_ttl() {
+    let result = set_max_resolve_cache_ttl(10);
+    assert_eq!(result[0], "error");
+    let result = set_max_resolve_cache_ttl(100000);
+    assert_eq!(result[0], "error");
+
+    let result = set_max_resolve_cache_ttl(600);
+    assert_eq!(result[0], "success");
+    let result = get_max_resolve_cache_ttl();
+    assert_eq!(result, vec!["success".to_string(), "600".to_string()]);
+
+    let result = set_max_resolve_cache_ttl(3600);
+    assert_eq!(result[0], "success");
+}
+
+// Test puts that can't connect are queued, kept by flush_queue while still unreachable, and cleared
+#[test]
+fn test_queue_summary() {
+    let result = clear_queue();
+    assert_eq!(result[0], "success");
+
+    let result = get_pending_queue_count();
+    assert_eq!(result, vec!["success".to_string(), "0".to_string()]);
+
+    let result = get_queue_summary();
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["count"], 0);
+    assert_eq!(json["total_bytes"], 0);
+    assert!(json["oldest_queued_at_unix"].is_null());
+
+    // Nothing listens on port 1, so the put fails to connect
+    let url = "https://127.0.0.1:1/pub/queued".to_string();
+    let result = put(url.clone(), "queued content".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("QUEUED: "));
+    let result = put(url, "newer content".to_string());
+    assert!(result[1].starts_with("QUEUED: "));
+
+    let result = get_queue_summary();
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["count"], 1);
+    assert_eq!(json["total_bytes"], "newer content".len());
+
+    let result = flush_queue();
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json, serde_json::json!({ "written": 0, "dropped": 0, "remaining": 1 }));
+
+    let result = clear_queue();
+    assert_eq!(result, vec!["success".to_string(), "1".to_string()]);
+    assert_eq!(get_pending_queue_count()[1], "0");
+}
+
+// Test the summary of a queue holding writes
+#[test]
+fn test_queue_summary_json() {
+    let queued_puts = vec![
+        QueuedPut { url: "pubky://example/pub/a".to_string(), content: "abc".to_string(), queued_at_unix: 1_700_000_100 },
+        QueuedPut { url: "pubky://example/pub/b".to_string(), content: "de".to_string(), queued_at_unix: 1_700_000_000 },
+    ];
+    assert_eq!(queue_summary_json(&queued_puts), serde_json::json!({
+        "count": 2,
+        "total_bytes": 5,
+        "oldest_queued_at_unix": 1_700_000_000u64,
+        "newest_queued_at_unix": 1_700_000_100u64,
+    }));
+}
+
+// Test lossy text get
+#[test]
+fn test_get_text_lossy() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+    let url = format!("pubky://{}/pub/test.com/lossy", keypair.public_key());
+
+    let sign_up_result = sign_up(secret_key, homeserver);
+    assert_eq!(sign_up_result[0], "success");
+    let put_result = put(url.clone(), "plain text".to_string());
+    assert_eq!(put_result[0], "success");
+
+    let result = get_text_lossy(url);
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["text"], "plain text");
+    assert_eq!(json["had_invalid_bytes"], false);
+}
+
+// Test get_text_lossy rejects URLs with the wrong scheme, like get
+#[test]
+fn test_get_text_lossy_invalid_scheme() {
+    let result = get_text_lossy("http://example.com/pub/file".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("INVALID_SCHEME"));
+}
+
+// Test content identifiers
+#[test]
+fn test_create_content_id() {
+    let result = create_content_id("hello".to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["sha256_hex"], "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+    assert!(json["cid"].as_str().unwrap().starts_with("cid:sha2-256:"));
+    assert_eq!(result, create_content_id("hello".to_string()));
+}
+
+// Test auth URL secret normalization
+#[test]
+fn test_parse_auth_url_secret_encodings() {
+    let url_safe = "pubkyauth:///?caps=/pub/pubky.app/:rw&secret=U55XnoH6vsMCpx1pxHtt8fReVg4Brvu9C0gUBuw-Jkw&relay=http://167.86.102.121:4173/";
+    let result = parse_auth_url(url_safe.to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["secret_length"], 32);
+    assert_eq!(json["secret_normalized"], "U55XnoH6vsMCpx1pxHtt8fReVg4Brvu9C0gUBuw-Jkw");
+
+    // The same secret in padded standard base64, with `+` percent-encoded
+    let standard = "pubkyauth:///?caps=/pub/pubky.app/:rw&secret=U55XnoH6vsMCpx1pxHtt8fReVg4Brvu9C0gUBuw%2BJkw%3D&relay=http://167.86.102.121:4173/";
+    let result = parse_auth_url(standard.to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["secret_normalized"], "U55XnoH6vsMCpx1pxHtt8fReVg4Brvu9C0gUBuw-Jkw");
+
+    let malformed = "pubkyauth:///?caps=/pub/pubky.app/:rw&secret=not*base64&relay=http://167.86.102.121:4173/";
+    let result = parse_auth_url(malformed.to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test event loop control
+#[test]
+fn test_event_loop_control() {
+    let result = set_event_loop_interval(0);
+    assert_eq!(result[0], "error");
+    let result = set_event_loop_interval(5);
+    assert_eq!(result[0], "success");
+
+    stop_event_loop();
+    let result = start_event_loop();
+    assert_eq!(result[0], "success");
+    let result = start_event_loop();
+    assert_eq!(result[0], "error");
+
+    let result = stop_event_loop();
+    assert_eq!(result[0], "success");
+    let result = stop_event_loop();
+    assert_eq!(result[0], "error");
+}
+
+// Test changing the passphrase of a recovery file
+#[test]
+fn test_rekey_recovery_file() {
+    let (_, secret_key, _) = get_test_setup();
+
+    let recovery_file = create_recovery_file(secret_key.clone(), "old passphrase".to_string());
+    assert_eq!(recovery_file[0], "success");
+
+    let result = rekey_recovery_file(recovery_file[1].clone(), "old passphrase".to_string(), "new passphrase".to_string());
+    assert_eq!(result[0], "success");
+
+    let decrypted = decrypt_recovery_file(result[1].clone(), "new passphrase".to_string());
+    assert_eq!(decrypted, vec!["success".to_string(), secret_key]);
+    let decrypted = decrypt_recovery_file(result[1].clone(), "old passphrase".to_string());
+    assert_eq!(decrypted[0], "error");
+
+    let result = rekey_recovery_file(recovery_file[1].clone(), "wrong".to_string(), "new passphrase".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test publishing and resolving an alias
+#[test]
+fn test_link_record() {
+    let (keypair, secret_key, _) = get_test_setup();
+    let target = generate_test_keypair().public_key().to_string();
+
+    let result = create_link_record(secret_key.clone(), "bad.alias".to_string(), target.clone());
+    assert_eq!(result[0], "error");
+
+    let result = create_link_record(secret_key, "friend".to_string(), target.clone());
+    assert_eq!(result[0], "success");
+
+    let result = resolve_link(keypair.public_key().to_string(), "friend".to_string());
+    assert_eq!(result, vec!["success".to_string(), target]);
+}
+
+// Test put and get reject URLs with the wrong scheme
+#[test]
+fn test_put_get_invalid_scheme() {
+    let result = put("http://example.com/pub/file".to_string(), "content".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("INVALID_SCHEME"));
+
+    let result = get("file:///tmp/file".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("INVALID_SCHEME"));
+}
+
+// Test share links
+#[test]
+fn test_share_link() {
+    let (keypair, _, _) = get_test_setup();
+    let public_key = keypair.public_key().to_string();
+
+    let link = generate_share_link(public_key.clone(), "/pub/example.com/my file.txt".to_string());
+    assert_eq!(link[0], "success");
+    assert!(link[1].starts_with("pubkyapp://open?pubky="));
+
+    let result = parse_share_link(link[1].clone());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["public_key"], public_key);
+    assert_eq!(json["path"], "/pub/example.com/my file.txt");
+    assert_eq!(json["full_pubky_url"], format!("pubky://{}/pub/example.com/my file.txt", public_key));
+
+    let result = generate_share_link(public_key, "/pub/../secret".to_string());
+    assert_eq!(result[0], "error");
+    let result = parse_share_link("https://example.com".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test resolving records grouped by type
+#[test]
+fn test_resolve_grouped() {
+    let (keypair, secret_key, _) = get_test_setup();
+
+    let publish_result = publish("grouped".to_string(), "grouped content".to_string(), secret_key);
+    assert_eq!(publish_result[0], "success");
+
+    let result = resolve_grouped(keypair.public_key().to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert!(json["TXT"].is_array());
+
+    let result = resolve_grouped("invalid_key".to_string());
+    assert_eq!(result[0], "error");
+    // The deprecated alias answers exactly like `resolve_grouped`
+    assert_eq!(get_all_dns_records_grouped("invalid_key".to_string()), resolve_grouped("invalid_key".to_string()));
+}
+
+// Test homeserver public key verification input validation
+#[test]
+fn test_verify_homeserver_public_key_invalid_input() {
+    let (_, _, homeserver) = get_test_setup();
+
+    let result = verify_homeserver_public_key("https://example.com".to_string(), "invalid_key".to_string());
+    assert_eq!(result[0], "error");
+
+    let result = verify_homeserver_public_key("not a url".to_string(), homeserver);
+    assert_eq!(result[0], "error");
+}
+
+// Test bulk operations report per-item status
+#[test]
+fn test_bulk_operations_partial_results() {
+    let result = resolve_many("[\"invalid_key\"]".to_string(), 1000);
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json[0]["status"], "error");
+    assert_eq!(json[0]["timed_out"], false);
+
+    let result = put_many(r#"[{"url":"http://example.com/file","content":"x"}]"#.to_string(), 1000);
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json[0]["status"], "error");
+
+    let result = resolve_many("not json".to_string(), 0);
+    assert_eq!(result[0], "error");
+}
+
+// Test child key derivation
+#[test]
+fn test_derive_key_from_master() {
+    let (_, secret_key, _) = get_test_setup();
+
+    let first = derive_key_from_master(secret_key.clone(), 0);
+    assert_eq!(first[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&first[1]).unwrap();
+    assert_eq!(json["derivation_path"], "m/44'/0'");
+    assert_ne!(json["secret_key"], secret_key);
+
+    assert_eq!(first, derive_key_from_master(secret_key.clone(), 0));
+    assert_ne!(first, derive_key_from_master(secret_key.clone(), 1));
+
+    let result = derive_key_from_master(secret_key, 0x8000_0000);
+    assert_eq!(result[0], "error");
+}
+
+// Test publishing a NAPTR record
+#[test]
+fn test_publish_naptr_record() {
+    let (keypair, secret_key, _) = get_test_setup();
+
+    let result = publish_naptr_record(
+        "sip".to_string(), 100, 10, "U".to_string(), "E2U+sip".to_string(),
+        "!^.*$!sip:info@example.com!".to_string(), ".".to_string(), 3600, "invalid".to_string(),
+    );
+    assert_eq!(result[0], "error");
+
+    let result = publish_naptr_record(
+        "sip".to_string(), 100, 10, "U".to_string(), "E2U+sip".to_string(),
+        "!^.*$!sip:info@example.com!".to_string(), ".".to_string(), 3600, secret_key,
+    );
+    assert_eq!(result[0], "success");
+    assert_eq!(result[1], keypair.public_key().to_string());
+}
+
+// Test extracting the public key from a pubky URL
+#[test]
+fn test_extract_pubky_from_url() {
+    let (keypair, _, _) = get_test_setup();
+    let public_key = keypair.public_key().to_string();
+
+    let result = extract_pubky_from_url(format!("pubky://{}/pub/example.com/file", public_key));
+    assert_eq!(result, vec!["success".to_string(), public_key.clone()]);
+
+    let result = extract_pubky_from_url(format!("https://{}/pub/example.com/file", public_key));
+    assert_eq!(result[0], "error");
+    let result = extract_pubky_from_url("pubky://invalid_key/pub/file".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test publishing NS and CNAME records
+#[test]
+fn test_publish_ns_and_cname_records() {
+    let (keypair, secret_key, _) = get_test_setup();
+
+    // Labels are limited to 63 bytes
+    let result = publish_ns_record("sub".to_string(), format!("{}.com", "a".repeat(64)), 3600, secret_key.clone());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("Invalid target"));
+
+    let result = publish_ns_record("sub".to_string(), "ns.example.com".to_string(), 3600, secret_key.clone());
+    assert_eq!(result[0], "success");
+    let result = publish_cname_record("www".to_string(), "example.com".to_string(), 3600, secret_key);
+    assert_eq!(result[0], "success");
+
+    let result = resolve_grouped(keypair.public_key().to_string());
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["NS"][0]["rdata"]["nameserver"], "ns.example.com");
+    assert_eq!(json["CNAME"][0]["rdata"]["target"], "example.com");
+}
+
+// Test signing up with the default homeserver
+#[test]
+fn test_default_homeserver() {
+    let (_, secret_key, homeserver) = get_test_setup();
+
+    let result = set_default_homeserver("invalid_key".to_string());
+    assert_eq!(result[0], "error");
+
+    let result = set_default_homeserver(homeserver.clone());
+    assert_eq!(result, vec!["success".to_string(), homeserver]);
+
+    let result = sign_up_default(secret_key.clone(), Some("token".to_string()));
+    assert_eq!(result[0], "error");
+
+    let result = sign_up_default(secret_key, None);
+    assert_eq!(result[0], "success");
+}
+
+// Test signup token request validation
+#[test]
+fn test_get_signup_token_invalid_input() {
+    let (_, _, homeserver) = get_test_setup();
+
+    let result = get_signup_token("invalid_key".to_string(), "admin".to_string(), None, 0);
+    assert_eq!(result[0], "error");
+
+    let result = get_signup_token(homeserver, "admin".to_string(), Some("ftp://localhost".to_string()), 1);
+    assert_eq!(result, vec!["error".to_string(), "Failed to parse URL".to_string()]);
+}
+
+// Test homeserver info falls back to open signups without a well-known document
+#[test]
+fn test_homeserver_info_json() {
+    let info = homeserver_info_json(None);
+    assert_eq!(info["signup_mode"], "open");
+    assert!(info["quota_bytes"].is_null());
+
+    let well_known = serde_json::json!({ "version": "0.1.0", "signup_mode": "invite", "quota_bytes": 1024, "features": ["events"] });
+    let info = homeserver_info_json(Some(&well_known));
+    assert_eq!(info["signup_mode"], "invite");
+    assert_eq!(info["quota_bytes"], 1024);
+    assert_eq!(info["features"], serde_json::json!(["events"]));
+    assert!(info["terms_url"].is_null());
+}
+
+// Test publishing an MX record
+#[test]
+fn test_publish_mx_record() {
+    let (keypair, secret_key, _) = get_test_setup();
+
+    let result = publish_mx_record("mail".to_string(), 10, format!("{}.com", "a".repeat(64)), 3600, secret_key.clone());
+    assert_eq!(result[0], "error");
+
+    let result = publish_mx_record("mail".to_string(), 10, "mx.example.com".to_string(), 3600, secret_key);
+    assert_eq!(result[0], "success");
+    assert_eq!(result[1], keypair.public_key().to_string());
+}
+
+// Test merging a TXT record keeps the other published records and replaces the one with the same name
+#[test]
+fn test_publish_merge() {
+    // A fresh key, so publishes of the shared test key by other tests can't replace the records in between
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let public_key = keypair.public_key().to_string();
+
+    let publish_result = publish("kept".to_string(), "kept=1".to_string(), secret_key.clone());
+    assert_eq!(publish_result[0], "success");
+    let merge_result = publish_merge("merged".to_string(), "merged=1".to_string(), secret_key.clone());
+    assert_eq!(merge_result[0], "success");
+    let merge_result = publish_merge("merged".to_string(), "merged=2".to_string(), secret_key);
+    assert_eq!(merge_result[0], "success");
+
+    let resolve_result = resolve(public_key.clone());
+    assert_eq!(resolve_result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&resolve_result[1]).unwrap();
+    let records = json["records"].as_array().unwrap();
+    let strings = |name: &str| -> Vec<serde_json::Value> {
+        records
+            .iter()
+            .filter(|record| record["name"] == format!("{}.{}", name, public_key))
+            .map(|record| record["rdata"]["strings"].clone())
+            .collect()
+    };
+    assert_eq!(strings("kept"), vec![serde_json::json!(["kept=1"])]);
+    assert_eq!(strings("merged"), vec![serde_json::json!(["merged=2"])]);
+}
+
+// Test merging rejects invalid input before publishing
+#[test]
+fn test_publish_merge_invalid_input() {
+    let (_, secret_key, _) = get_test_setup();
+
+    let result = publish_merge("note".to_string(), "hello".to_string(), "invalid_secret_key".to_string());
+    assert_eq!(result[0], "error");
+
+    let result = publish_merge("a".repeat(64), "hello".to_string(), secret_key);
+    assert_eq!(result[0], "error");
+}
+
+// Test resolving contacts
+#[test]
+fn test_resolve_contacts() {
+    let result = resolve_contacts("[\"invalid_key\"]".to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json[0]["exists"], false);
+    assert!(json[0]["error"].is_string());
+
+    let result = resolve_contacts("{}".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test secret key format conversion
+#[test]
+fn test_convert_secret_key_format() {
+    let (_, secret_key, _) = get_test_setup();
+
+    let base58 = convert_secret_key_format(secret_key.clone(), "hex".to_string(), "base58".to_string());
+    assert_eq!(base58[0], "success");
+    let base64 = convert_secret_key_format(base58[1].clone(), "base58".to_string(), "base64".to_string());
+    assert_eq!(base64[0], "success");
+    let hex = convert_secret_key_format(base64[1].clone(), "base64".to_string(), "hex".to_string());
+    assert_eq!(hex, vec!["success".to_string(), secret_key.clone()]);
+
+    let result = convert_secret_key_format("abcd".to_string(), "hex".to_string(), "base58".to_string());
+    assert_eq!(result[0], "error");
+    let result = convert_secret_key_format(secret_key, "hex".to_string(), "base32".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test relay priority validation
+#[test]
+fn test_set_relay_priority() {
+    let result = set_relay_priority("[\"not a url\"]".to_string());
+    assert_eq!(result[0], "error");
+
+    let result = set_relay_priority("[]".to_string());
+    assert_eq!(result, vec!["success".to_string(), "[]".to_string()]);
+}
+
+// Test ephemeral keypairs can be used once
+#[test]
+fn test_ephemeral_keypair() {
+    let result = generate_ephemeral_keypair(60);
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    let public_key = json["public_key"].as_str().unwrap().to_string();
+
+    let secret = use_ephemeral_keypair(public_key.clone());
+    assert_eq!(secret[0], "success");
+    let derived = get_public_key_from_secret_key(secret[1].clone());
+    let derived: serde_json::Value = serde_json::from_str(&derived[1]).unwrap();
+    assert_eq!(derived["public_key"], public_key);
+
+    let again = use_ephemeral_keypair(public_key);
+    assert_eq!(again[0], "error");
+}
+
+// Test merging overlapping capabilities
+#[test]
+fn test_merge_capabilities() {
+    let caps = r#"["/pub/app/:r", "/pub/app/:w", "/pub/app/files/:r,/pub/other/:w", "/pub/other/:w"]"#;
+    let result = merge_capabilities(caps.to_string());
+    assert_eq!(result[0], "success");
+    let merged: Vec<String> = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(merged, vec!["/pub/app/:rw".to_string(), "/pub/other/:w".to_string()]);
+
+    let result = merge_capabilities(r#"["pub/app:r"]"#.to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test get_to_file writes the content put at a URL
+#[test]
+fn test_get_to_file() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+    let url = format!("pubky://{}/pub/test.com/downloaded", keypair.public_key());
+    let content = "downloaded content".to_string();
+    let path = std::env::temp_dir().join("pubkycore_get_to_file_round_trip");
+    let path = path.to_str().unwrap().to_string();
+
+    let sign_up_result = sign_up(secret_key, homeserver);
+    assert_eq!(sign_up_result[0], "success");
+    let put_result = put(url.clone(), content.clone());
+    assert_eq!(put_result[0], "success");
+
+    let result = get_to_file(url, path.clone());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["path"], path);
+    assert_eq!(json["bytes"], content.len());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+    assert!(!std::path::Path::new(&format!("{}.part", path)).exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+// Test get_to_file rejects invalid URLs without creating a file
+#[test]
+fn test_get_to_file_invalid_url() {
+    let path = std::env::temp_dir().join("pubkycore_get_to_file_test");
+    let path = path.to_str().unwrap().to_string();
+    let result = get_to_file("not a url".to_string(), path.clone());
+    assert_eq!(result[0], "error");
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+// Test rotating a key requires two distinct keys
+#[test]
+fn test_rotate_key_same_key() {
+    let secret_key = hex::encode(generate_test_keypair().secret_key());
+    let result = rotate_key(secret_key.clone(), secret_key);
+    assert_eq!(result[0], "error");
+}
+
+// Test the revocation certificate is signed by the revoked key
+#[test]
+fn test_create_revocation_certificate() {
+    use base64::Engine;
+    let keypair = generate_test_keypair();
+    let result = create_revocation_certificate(hex::encode(keypair.secret_key()), "key leaked".to_string());
+    assert_eq!(result[0], "success");
+
+    let document = base64::engine::general_purpose::STANDARD.decode(&result[1]).unwrap();
+    let document: serde_json::Value = serde_json::from_slice(&document).unwrap();
+    let certificate = document["certificate"].as_str().unwrap();
+    let details: serde_json::Value = serde_json::from_str(certificate).unwrap();
+    assert_eq!(details["public_key"], keypair.public_key().to_string());
+    assert_eq!(details["reason"], "key leaked");
+
+    let signature = base64::engine::general_purpose::STANDARD.decode(document["signature"].as_str().unwrap()).unwrap();
+    // Ed25519 signatures are deterministic
+    assert_eq!(signature, keypair.sign(certificate.as_bytes()).to_bytes().to_vec());
+}
+
+// Test put_from_file reports a missing file
+#[test]
+fn test_put_from_file_missing_file() {
+    let keypair = generate_test_keypair();
+    let url = format!("pubky://{}/pub/test.com/upload", keypair.public_key());
+    let result = put_from_file(url, "/nonexistent/pubkycore/upload".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("Failed to read file"));
+}
+
+// Test put_from_file rejects URLs with the wrong scheme, like put
+#[test]
+fn test_put_from_file_invalid_scheme() {
+    let result = put_from_file("http://example.com/pub/file".to_string(), "/nonexistent/pubkycore/upload".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("INVALID_SCHEME"));
+}
+
+// Test the remaining TTL of a freshly signed packet
+#[test]
+fn test_packet_remaining_ttl() {
+    use pkarr::dns::{self, rdata::{RData, TXT}, Packet, ResourceRecord};
+    use pkarr::SignedPacket;
+
+    let keypair = generate_test_keypair();
+    let mut packet = Packet::new_reply(0);
+    packet.answers.push(ResourceRecord::new(dns::Name::new("a").unwrap(), dns::CLASS::IN, 600, RData::TXT(TXT::try_from("a").unwrap())));
+    packet.answers.push(ResourceRecord::new(dns::Name::new("b").unwrap(), dns::CLASS::IN, 300, RData::TXT(TXT::try_from("b").unwrap())));
+    let signed_packet = SignedPacket::from_packet(&keypair, &packet).unwrap();
+
+    let result = packet_remaining_ttl(hex::encode(signed_packet.as_bytes()));
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["min_ttl"], 300);
+    assert!(json["remaining_seconds"].as_i64().unwrap() > 290);
+    assert_eq!(json["stale"], false);
+
+    let result = packet_remaining_ttl("00".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test registering custom DNS record types
+#[test]
+fn test_custom_dns_rdata_type() {
+    let result = add_custom_dns_rdata_type(65400, "pubkyx".to_string());
+    assert_eq!(result, vec!["success".to_string(), "PUBKYX".to_string()]);
+
+    let types: serde_json::Value = serde_json::from_str(&get_registered_dns_types()[1]).unwrap();
+    assert!(types.as_array().unwrap().iter().any(|t| t["type_number"] == 65400 && t["type_name"] == "PUBKYX"));
+
+    assert_eq!(add_custom_dns_rdata_type(16, "MYTXT".to_string())[0], "error");
+    assert_eq!(add_custom_dns_rdata_type(65401, "TXT".to_string())[0], "error");
+    assert_eq!(add_custom_dns_rdata_type(65402, "bad name".to_string())[0], "error");
+}
+
+// Test resolve_at rejects invalid keys
+#[test]
+fn test_resolve_at_invalid_key() {
+    let result = resolve_at("invalid".to_string(), 0);
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("Invalid zbase32 encoded key"));
+}
+
+// Test resolve_at serves the latest packet only for times after it was published
+#[test]
+fn test_resolve_at_history_unavailable() {
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let public_key = keypair.public_key().to_string();
+
+    let publish_result = publish("audited".to_string(), "audited=1".to_string(), secret_key);
+    assert_eq!(publish_result[0], "success");
+
+    let result = resolve_at(public_key.clone(), 1);
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("HISTORY_UNAVAILABLE"));
+
+    let result = resolve_at(public_key, u64::MAX);
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["requested_timestamp"], u64::MAX);
+    assert!(json["served_timestamp"].as_u64().unwrap() > 1);
+    assert!(!json["records"].as_array().unwrap().is_empty());
+}
+
+// Test a half-open circuit lets a single trial request through
+#[test]
+fn test_circuit_breaker_single_trial() {
+    let circuit_breaker = CircuitBreaker::new();
+    circuit_breaker.configure(1, std::time::Duration::from_millis(50));
+    let endpoint = "example";
+
+    circuit_breaker.record_failure(endpoint);
+    assert_eq!(circuit_breaker.state(endpoint), CircuitState::Open);
+    assert!(circuit_breaker.check(endpoint).is_err());
+
+    std::thread::sleep(std::time::Duration::from_millis(60));
+    assert_eq!(circuit_breaker.state(endpoint), CircuitState::HalfOpen);
+    assert!(circuit_breaker.check(endpoint).is_ok());
+    assert!(circuit_breaker.check(endpoint).is_err());
+
+    // A failed trial opens the circuit again
+    circuit_breaker.record_failure(endpoint);
+    assert!(circuit_breaker.check(endpoint).is_err());
+
+    std::thread::sleep(std::time::Duration::from_millis(60));
+    assert!(circuit_breaker.check(endpoint).is_ok());
+    circuit_breaker.record_success(endpoint);
+    assert_eq!(circuit_breaker.state(endpoint), CircuitState::Closed);
+    assert!(circuit_breaker.check(endpoint).is_ok());
+    assert!(circuit_breaker.check(endpoint).is_ok());
+}
+
+// Test circuit breaker state of an endpoint without failures
+#[test]
+fn test_circuit_breaker_state() {
+    let endpoint = generate_test_keypair().public_key().to_string();
+    let result = get_circuit_breaker_state(endpoint.clone());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["state"], "closed");
+    assert_eq!(json["consecutive_failures"], 0);
+
+    assert_eq!(reset_circuit_breaker(endpoint.clone()), vec!["success".to_string(), endpoint]);
+}
+
+// Test recovery file format detection
+#[test]
+fn test_is_recovery_file() {
+    let secret_key = hex::encode(generate_test_keypair().secret_key());
+
+    let recovery_file = create_recovery_file(secret_key.clone(), "passphrase".to_string());
+    let result = is_recovery_file(recovery_file[1].clone());
+    assert_eq!(result, vec!["success".to_string(), r#"{"valid_format":true,"version":2}"#.to_string()]);
+
+    let time_locked = create_time_lock_recovery_file(secret_key, "passphrase".to_string(), 0);
+    let result = is_recovery_file(time_locked[1].clone());
+    assert_eq!(result[1], r#"{"valid_format":true,"version":3}"#);
+
+    let result = is_recovery_file("bm90IGEgcmVjb3ZlcnkgZmlsZQ==".to_string());
+    assert_eq!(result[1], r#"{"valid_format":false,"version":null}"#);
+    let result = is_recovery_file("not base64!".to_string());
+    assert_eq!(result[1], r#"{"valid_format":false,"version":null}"#);
+}
+
+// Test resolve_and_cache rejects invalid keys
+#[test]
+fn test_resolve_and_cache_invalid_key() {
+    let result = resolve_and_cache("invalid".to_string(), 60);
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("Invalid zbase32 encoded key"));
+}
+
+// Test resolve_and_cache serves a second resolve from the cache
+#[test]
+fn test_resolve_and_cache_hit() {
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let public_key = keypair.public_key().to_string();
+
+    let publish_result = publish("cached".to_string(), "cached=1".to_string(), secret_key);
+    assert_eq!(publish_result[0], "success");
+
+    let first_result = resolve_and_cache(public_key.clone(), 3600);
+    assert_eq!(first_result[0], "success");
+    let first: serde_json::Value = serde_json::from_str(&first_result[1]).unwrap();
+
+    let second_result = resolve_and_cache(public_key, 3600);
+    assert_eq!(second_result[0], "success");
+    let second: serde_json::Value = serde_json::from_str(&second_result[1]).unwrap();
+    assert_eq!(second["cached"], true);
+    assert_eq!(second["timestamp"], first["timestamp"]);
+    assert!(second["age_seconds"].as_u64().unwrap() <= 3600);
+}
+
+// Test sign_up_and_wait validates its input before signing up
+#[test]
+fn test_sign_up_and_wait_invalid_input() {
+    let secret_key = hex::encode(generate_test_keypair().secret_key());
+    let result = sign_up_and_wait(secret_key.clone(), "invalid".to_string(), None, 1);
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("Invalid homeserver public key"));
+
+    let homeserver = generate_test_keypair().public_key().to_string();
+    let result = sign_up_and_wait(secret_key, homeserver, Some("token".to_string()), 1);
+    assert_eq!(result, vec!["error".to_string(), "Signup tokens are not supported".to_string()]);
+}
+
+// Test list_authenticated rejects an invalid secret key
+#[test]
+fn test_list_authenticated_invalid_secret_key() {
+    let result = list_authenticated("pubky://invalid/pub/".to_string(), "invalid".to_string());
+    assert_eq!(result, vec!["error".to_string(), "Failed to decode secret key".to_string()]);
+}
+
+// Test list_authenticated signs in again to list after signing out
+#[test]
+fn test_list_authenticated() {
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let public_key = keypair.public_key().to_string();
+    let (_, _, homeserver) = get_test_setup();
+    let url = format!("pubky://{}/pub/test.com/listed", public_key);
+
+    let sign_up_result = sign_up(secret_key.clone(), homeserver);
+    assert_eq!(sign_up_result[0], "success");
+    let put_result = put(url, "content".to_string());
+    assert_eq!(put_result[0], "success");
+    let sign_out_result = sign_out(secret_key.clone());
+    assert_eq!(sign_out_result[0], "success");
+    assert!(!account_has_capability(&public_key, "/pub/test.com/", pubky_common::capabilities::Action::Read));
+
+    let list_result = list_authenticated(format!("pubky://{}/pub/test.com/", public_key), secret_key);
+    assert_eq!(list_result[0], "success");
+    let urls: Vec<String> = serde_json::from_str(&list_result[1]).unwrap();
+    assert_eq!(urls.len(), 1);
+    assert!(urls[0].ends_with(&format!("{}/pub/test.com/listed", public_key)));
+    assert!(account_has_capability(&public_key, "/pub/test.com/", pubky_common::capabilities::Action::Read));
+}
+
+// Test storage_usage sums the sizes of an account's files
+#[test]
+fn test_storage_usage() {
+    // A fresh key, so files put by other tests don't count
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let public_key = keypair.public_key().to_string();
+    let (_, _, homeserver) = get_test_setup();
+
+    let sign_up_result = sign_up(secret_key.clone(), homeserver);
+    assert_eq!(sign_up_result[0], "success");
+    let put_result = put(format!("pubky://{}/pub/test.com/first", public_key), "12345".to_string());
+    assert_eq!(put_result[0], "success");
+    let put_result = put(format!("pubky://{}/pub/test.com/second", public_key), "1234567890".to_string());
+    assert_eq!(put_result[0], "success");
+
+    let result = storage_usage(secret_key);
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["file_count"], 2);
+    assert_eq!(json["total_bytes"], 15);
+    assert_eq!(json["unknown_size_count"], 0);
+
+    let result = storage_usage(public_key);
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["total_bytes"], 15);
+}
+
+// Test storage_usage rejects input that is neither a secret key nor a public key
+#[test]
+fn test_storage_usage_invalid_input() {
+    let result = storage_usage("invalid".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("Invalid secret key or public key"));
+}
+
+// Test publish_with_retry doesn't retry invalid input
+#[test]
+fn test_publish_with_retry_invalid_secret_key() {
+    let result = publish_with_retry("name".to_string(), "content".to_string(), "invalid".to_string(), 3);
+    assert_eq!(result, vec!["error".to_string(), "Failed to decode secret key".to_string()]);
+}
+
+// Test resolve_endpoints rejects invalid keys
+#[test]
+fn test_resolve_endpoints_invalid_key() {
+    let result = resolve_endpoints("invalid".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("Invalid public key"));
+}
+
+// Test publish_with_retry publishes in a single attempt when the first one succeeds
+#[test]
+fn test_publish_with_retry() {
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let public_key = keypair.public_key().to_string();
+
+    // With a single attempt allowed, success means the first attempt went through without retrying
+    let result = publish_with_retry("retried".to_string(), "retried=1".to_string(), secret_key, 1);
+    assert_eq!(result, vec!["success".to_string(), public_key.clone()]);
+
+    let resolve_result = resolve(public_key.clone());
+    assert_eq!(resolve_result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&resolve_result[1]).unwrap();
+    let records = json["records"].as_array().unwrap();
+    assert!(records.iter().any(|record| record["name"] == format!("retried.{}", public_key)
+        && record["rdata"]["strings"] == serde_json::json!(["retried=1"])));
+}
+
+// Test resolve_endpoints falls back to A and AAAA records when no HTTPS records are published
+#[test]
+fn test_resolve_endpoints_address_fallback() {
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let public_key = keypair.public_key().to_string();
+
+    let zonefile = "@ 300 IN A 192.0.2.1\n@ 300 IN AAAA 2001:db8::1\n@ 300 IN TXT \"hello=world\"";
+    let publish_result = publish_zonefile(zonefile.to_string(), secret_key);
+    assert_eq!(publish_result[0], "success");
+
+    let result = resolve_endpoints(public_key.clone());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["public_key"], public_key);
+    let endpoints = json["endpoints"].as_array().unwrap();
+    assert_eq!(endpoints.len(), 2);
+    assert!(endpoints.iter().any(|endpoint| endpoint["type"] == "A" && endpoint["address"] == "192.0.2.1"));
+    assert!(endpoints.iter().any(|endpoint| endpoint["type"] == "AAAA" && endpoint["address"] == "2001:db8::1"));
+}
+
+// Test https URLs with a public key host are treated as pubky URLs
+#[test]
+fn test_normalize_data_url() {
+    let public_key = generate_test_keypair().public_key().to_string();
+    let https_url = url::Url::parse(&format!("https://{}/pub/example.com/file?x=1", public_key)).unwrap();
+    assert_eq!(normalize_data_url(https_url).as_str(), format!("pubky://{}/pub/example.com/file?x=1", public_key));
+
+    let homeserver_url = url::Url::parse(&format!("https://homeserver.example.com/{}/pub/file", public_key)).unwrap();
+    assert_eq!(normalize_data_url(homeserver_url.clone()), homeserver_url);
+}
+
+// Test concurrent resolves of the same key run a single lookup and share its result
+#[tokio::test]
+async fn test_resolve_deduplication() {
+    let client = NetworkClient::new();
+    let lookups = std::sync::atomic::AtomicUsize::new(0);
+    let lookup = || async {
+        lookups.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        vec!["success".to_string(), "packet".to_string()]
+    };
+    let pending_while_in_flight = async {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        client.pending_request_count()
+    };
+
+    let (first, second, third, pending) = tokio::join!(
+        client.deduplicate("resolve:key".to_string(), lookup()),
+        client.deduplicate("resolve:key".to_string(), lookup()),
+        client.deduplicate("resolve:key".to_string(), lookup()),
+        pending_while_in_flight,
+    );
+    assert_eq!(pending, 1);
+    assert_eq!(lookups.load(std::sync::atomic::Ordering::SeqCst), 1);
+    assert_eq!(first, vec!["success".to_string(), "packet".to_string()]);
+    assert_eq!(second, first);
+    assert_eq!(third, first);
+    assert_eq!(client.pending_request_count(), 0);
+}
+
+// Test signing several payloads at once
+#[test]
+fn test_batch_sign_bytes() {
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let result = batch_sign_bytes(r#"["", "68656c6c6f"]"#.to_string(), secret_key.clone());
+    assert_eq!(result[0], "success");
+    let signatures: Vec<String> = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(signatures.len(), 2);
+    assert_eq!(signatures[1], hex::encode(keypair.sign(b"hello").to_bytes()));
+
+    let result = batch_sign_bytes(r#"["zz"]"#.to_string(), secret_key);
+    assert_eq!(result[0], "error");
+}
+
+// Test rate-limited publishes are reported as RATE_LIMITED
+#[test]
+fn test_publish_rate_limit_detection() {
+    let error = pkarr::Error::PublishInflight;
+    assert_eq!(publish_rate_limit(&error), Some(None));
+    assert!(publish_error_message(&error).starts_with("RATE_LIMITED: "));
+
+    let error = pkarr::Error::DhtIsShutdown;
+    assert_eq!(publish_rate_limit(&error), None);
+    assert!(publish_error_message(&error).starts_with("Failed to publish: "));
+}
+
+// Test backup archives round trip through the ZIP archive and the AES-256-GCM passphrase encryption
+#[test]
+fn test_backup_archive_roundtrip() {
+    let entries = vec![
+        ("manifest.json".to_string(), b"{}".to_vec()),
+        ("content/pub/example.com/file".to_string(), vec![0, 1, 2, 255]),
+    ];
+    let archive = write_zip_archive(&entries).unwrap();
+    assert!(archive.starts_with(b"PK\x03\x04"));
+    assert_eq!(read_zip_archive(&archive).unwrap(), entries);
+
+    let backup = encrypt_backup_archive(&archive, "passphrase").unwrap();
+    assert!(backup.starts_with(BACKUP_SPEC_LINE.as_bytes()));
+    let decrypted = decrypt_backup_archive(&backup, "passphrase").unwrap();
+    assert_eq!(decrypted, archive);
+    assert!(decrypt_backup_archive(&backup, "wrong").is_err());
+
+    let mut zip_archive = zip::ZipArchive::new(std::io::Cursor::new(decrypted)).unwrap();
+    assert_eq!(zip_archive.len(), 2);
+    let mut file = zip_archive.by_name("content/pub/example.com/file").unwrap();
+    let mut data = Vec::new();
+    std::io::Read::read_to_end(&mut file, &mut data).unwrap();
+    assert_eq!(data, vec![0, 1, 2, 255]);
+
+    let result = restore_account_backup("not base64!".to_string(), "passphrase".to_string(), generate_test_keypair().public_key().to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test keypairs derived from seed bytes are deterministic
+#[test]
+fn test_keypair_from_seed_bytes() {
+    let seed = vec![7u8; 32];
+    let result = keypair_from_seed_bytes(seed.clone());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["secret_key"], hex::encode(&seed));
+    assert_eq!(keypair_from_seed_bytes(seed), result);
+
+    let short = keypair_from_seed_bytes(b"test".to_vec());
+    assert_eq!(short, keypair_from_seed_bytes(b"test".to_vec()));
+    assert_ne!(short, keypair_from_seed_bytes(b"test2".to_vec()));
+
+    assert_eq!(keypair_from_seed_bytes(vec![])[0], "error");
+}
+
+// Test vCard generation escapes values and carries the pubky key
+#[test]
+fn test_build_vcard() {
+    let public_key = generate_test_keypair().public_key();
+    let vcard = build_vcard(&public_key, Some("Satoshi; N."), None, Some("https://example.com/a.png"));
+    assert!(vcard.starts_with("BEGIN:VCARD\r\nVERSION:4.0\r\n"));
+    assert!(vcard.contains("FN:Satoshi\\; N.\r\n"));
+    assert!(vcard.contains(&format!("X-PUBKY-KEY:{}\r\n", public_key)));
+    assert!(!vcard.contains("NOTE:"));
+    assert!(vcard.ends_with("END:VCARD\r\n"));
+
+    let vcard = build_vcard(&public_key, None, Some(&"a".repeat(200)), None);
+    assert!(vcard.contains(&format!("FN:{}\r\n", public_key)));
+    assert!(vcard.split("\r\n").all(|line| line.len() <= 75));
+}
+
+// Test list range cursors resolve to full URLs
+#[test]
+fn test_list_cursor_url() {
+    let directory = "pubky://key/pub/example.com/";
+    assert_eq!(list_cursor_url(directory, "b.txt"), "pubky://key/pub/example.com/b.txt");
+    assert_eq!(list_cursor_url(directory, "pubky://key/pub/example.com/c"), "pubky://key/pub/example.com/c");
+
+    assert_eq!(list_range("pubky://key/pub/".to_string(), String::new(), "z".to_string(), 0)[0], "error");
+}
+
+// Test custom testnet bootstrap nodes are validated
+#[test]
+fn test_set_testnet_bootstrap_nodes() {
+    assert_eq!(set_testnet_bootstrap_nodes(vec![])[0], "error");
+    assert_eq!(set_testnet_bootstrap_nodes(vec!["localhost".to_string()])[0], "error");
+    assert_eq!(set_testnet_bootstrap_nodes(vec!["localhost:70000".to_string()])[0], "error");
+
+    let result = set_testnet_bootstrap_nodes(vec!["127.0.0.1:6881".to_string()]);
+    assert_eq!(result[0], "success");
+    assert_eq!(result[1], r#"["127.0.0.1:6881"]"#);
+    assert_eq!(clear_testnet_bootstrap_nodes()[0], "success");
+}
+
+// Test offline signing: the built bytes round trip and a bad signature is rejected before publishing
+#[test]
+fn test_build_unsigned_packet() {
+    let keypair = generate_test_keypair();
+    let public_key = keypair.public_key().to_string();
+    let records = serde_json::json!([
+        { "name": "test.record", "ttl": 30, "rdata": { "type": "TXT", "strings": ["hello=world"] } },
+    ]);
+    let result = build_unsigned_packet(records.to_string(), public_key.clone());
+    assert_eq!(result[0], "success");
+
+    let signable = hex::decode(&result[1]).unwrap();
+    let (_, encoded_packet) = parse_signable_bytes(&signable).unwrap();
+    let packet = pkarr::dns::Packet::parse(encoded_packet).unwrap();
+    assert_eq!(packet.answers[0].name.to_string(), format!("test.record.{}", public_key));
+
+    let wrong_signature = hex::encode(generate_test_keypair().sign(&signable).to_bytes());
+    let result = publish_presigned(result[1].clone(), wrong_signature, public_key);
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("Invalid signed packet"));
+}
+
+// Test network stats report only the fields the clients can populate
+#[test]
+fn test_get_network_stats() {
+    let result = get_network_stats();
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json.as_object().unwrap().len(), 4);
+    assert!(json["cached_packets"].is_u64());
+    assert!(json["pending_requests"].is_u64());
+    assert!(json["bytes_sent"].is_u64());
+    assert!(json["bytes_received"].is_u64());
+}
+
+// Test get_json passes through fetch errors
+#[test]
+fn test_get_json_invalid_url() {
+    let result = get_json("not a url".to_string());
+    assert_eq!(result[0], "error");
+    assert_eq!(result[1], "Failed to parse URL");
+}
+
+// Test an unreachable relay is reported as such
+#[test]
+fn test_health_check_relay_unreachable() {
+    assert_eq!(health_check_relay("not a url".to_string())[0], "error");
+
+    let result = health_check_relay("http://127.0.0.1:1".to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["reachable"], false);
+    assert!(json["error"].is_string());
+}
+
+// Test performance stats can be read and reset
+#[test]
+fn test_performance_stats() {
+    assert_eq!(reset_performance_stats()[0], "success");
+    let result = performance_stats();
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert!(json["resolve_count"].is_u64());
+    assert!(json["publish_count"].is_u64());
+    assert!(json.get("cache_hit_ratio").is_some());
+    assert!(json.get("average_resolve_latency_ms").is_some());
+}
+
+// Test getting the URI of a public key
+#[test]
+fn test_get_pubky_uri_from_public_key() {
+    let public_key = generate_test_keypair().public_key();
+    let result = get_pubky_uri_from_public_key(public_key.to_string());
+    assert_eq!(result[0], "success");
+    assert_eq!(result[1], public_key.to_uri_string());
+
+    assert_eq!(get_pubky_uri_from_public_key("invalid_key".to_string())[0], "error");
+}
+
+// Test scoped sign in validates its capabilities and relay before any network request
+#[test]
+fn test_sign_in_scoped_invalid_capabilities() {
+    let secret_key = hex::encode(generate_test_keypair().secret_key());
+    assert_eq!(sign_in_scoped(secret_key.clone(), "not json".to_string())[0], "error");
+    assert_eq!(sign_in_scoped(secret_key.clone(), "[]".to_string())[0], "error");
+    assert_eq!(sign_in_scoped(secret_key.clone(), r#"["pub/no-leading-slash:r"]"#.to_string())[0], "error");
+    assert_eq!(sign_in_scoped_with_relay(secret_key, r#"["/pub/pubky.app/:r"]"#.to_string(), "not a url".to_string())[0], "error");
+}
+
+// Test scoped sign in refuses to replace a broader session of the same key
+#[test]
+fn test_sign_in_scoped_keeps_broader_session() {
+    let keypair = generate_test_keypair();
+    let public_key = keypair.public_key().to_string();
+    let token = pubky_common::auth::AuthToken::sign(&keypair, vec![pubky_common::capabilities::Capability::root()]);
+    track_account(&pubky_common::session::Session::new(&token, None), None);
+
+    let result = sign_in_scoped(hex::encode(keypair.secret_key()), r#"["/pub/pubky.app/:r"]"#.to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].contains("broader session"));
+    assert!(account_has_capability(&public_key, "/pub/other.app/file", pubky_common::capabilities::Action::Write));
+
+    let capabilities = pubky_common::capabilities::Capabilities::try_from("/pub/:rw").unwrap();
+    assert!(!account_has_broader_session(&public_key, &[pubky_common::capabilities::Capability::root()]));
+    assert!(account_has_broader_session(&public_key, &capabilities.0));
+
+    untrack_account(&public_key);
+    assert!(!account_has_broader_session(&public_key, &capabilities.0));
+}
+
+// Test a tracked read-only session doesn't count as allowed to write
+#[test]
+fn test_account_has_capability() {
+    let keypair = generate_test_keypair();
+    let public_key = keypair.public_key().to_string();
+    let capabilities = pubky_common::capabilities::Capabilities::try_from("/pub/pubky.app/:r").unwrap();
+    let token = pubky_common::auth::AuthToken::sign(&keypair, capabilities);
+    track_account(&pubky_common::session::Session::new(&token, None), None);
+
+    assert!(account_has_capability(&public_key, "/pub/pubky.app/profile.json", pubky_common::capabilities::Action::Read));
+    assert!(account_has_capability(&public_key, "/pub/pubky.app", pubky_common::capabilities::Action::Read));
+    assert!(!account_has_capability(&public_key, "/pub/pubky.app/profile.json", pubky_common::capabilities::Action::Write));
+    assert!(!account_has_capability(&public_key, "/pub/other.app/file", pubky_common::capabilities::Action::Read));
+
+    untrack_account(&public_key);
+    assert!(!account_has_capability(&public_key, "/pub/pubky.app/profile.json", pubky_common::capabilities::Action::Read));
+}
+
+// Test creating a signed packet offline from records JSON
+#[test]
+fn test_create_signed_packet_json() {
+    let keypair = generate_test_keypair();
+    let records = serde_json::json!([
+        { "name": "test.record", "ttl": 30, "rdata": { "type": "TXT", "strings": ["hello=world"] } },
+    ]);
+    let result = create_signed_packet_json(records.to_string(), hex::encode(keypair.secret_key()));
+    assert_eq!(result[0], "success");
+
+    let bytes = hex::decode(&result[1]).unwrap();
+    let signed_packet = pkarr::SignedPacket::from_bytes(&pkarr::bytes::Bytes::from(bytes)).unwrap();
+    assert_eq!(signed_packet.public_key(), keypair.public_key());
+    assert_eq!(signed_packet.resource_records("test.record").count(), 1);
+
+    let result = create_signed_packet_json("[{}]".to_string(), hex::encode(keypair.secret_key()));
+    assert_eq!(result[0], "error");
+}
+
+// Test filtered deletes refuse an empty pattern
+#[test]
+fn test_delete_filtered_empty_pattern() {
+    let result = delete_filtered("pubky://key/pub/example.com/".to_string(), " ".to_string());
+    assert_eq!(result[0], "error");
+    assert_eq!(result[1], "Pattern must not be empty");
+}
+
+// Test trash URLs round trip to the original URL
+#[test]
+fn test_trash_urls() {
+    let url = "pubky://key/pub/example.com/notes/todo.txt";
+    let trash = trash_url(url, 1700000000000).unwrap();
+    assert_eq!(trash, "pubky://key/pub/example.com/notes/_trash/1700000000000/todo.txt");
+    assert_eq!(
+        parse_trash_url("pubky://key/pub/example.com/notes/", &trash),
+        Some((1700000000000, url.to_string()))
+    );
+    assert_eq!(parse_trash_url("pubky://key/pub/example.com/notes", "pubky://key/pub/example.com/notes/todo.txt"), None);
+
+    assert_eq!(soft_delete_file(trash, 60)[0], "error");
+}
+
+// Test resolving from a single relay reports unreachable relays as errors
+#[test]
+fn test_resolve_from_relay_errors() {
+    let public_key = generate_test_keypair().public_key().to_string();
+    assert_eq!(resolve_from_relay("invalid_key".to_string(), "http://127.0.0.1:1".to_string())[0], "error");
+    assert_eq!(resolve_from_relay(public_key.clone(), "not a url".to_string())[0], "error");
+
+    let result = resolve_from_relay(public_key, "http://127.0.0.1:1".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test recovery files as raw bytes
+#[test]
+fn test_recovery_file_bytes() {
+    let secret_key = hex::encode(generate_test_keypair().secret_key());
+    let bytes = create_recovery_file_bytes(secret_key.clone(), "passphrase".to_string()).unwrap();
+    assert!(bytes.starts_with(b"pubky.org/recovery\n"));
+
+    let result = decrypt_recovery_file_bytes(bytes.clone(), "passphrase".to_string());
+    assert_eq!(result, vec!["success".to_string(), secret_key.clone()]);
+    assert_eq!(decrypt_recovery_file_bytes(bytes, "wrong".to_string())[0], "error");
+
+    assert!(create_recovery_file_bytes(secret_key, String::new()).is_err());
+    assert!(create_recovery_file_bytes("invalid".to_string(), "passphrase".to_string()).is_err());
+}
+
+// Test expiring content reports fetch errors
+#[test]
+fn test_put_with_expiry_invalid_url() {
+    assert_eq!(put_with_expiry("not a url".to_string(), "content".to_string(), 0)[0], "error");
+    assert_eq!(get_expiry("not a url".to_string())[0], "error");
+    assert_eq!(cleanup_expired("not a url".to_string())[0], "error");
+}
+
+// Test search excerpts are case-insensitive and cut with context
+#[test]
+fn test_search_excerpt() {
+    assert_eq!(search_excerpt("Hello World", "world", 40), Some("Hello World".to_string()));
+    assert_eq!(search_excerpt("aaaa needle bbbb", "NEEDLE", 2), Some("...a needle b...".to_string()));
+    assert_eq!(search_excerpt("Grüße", "GRÜSSE", 0), None);
+    assert_eq!(search_excerpt("ÄÖÜ", "öü", 0), Some("...ÖÜ".to_string()));
+    assert_eq!(search_excerpt("text", "missing", 10), None);
+    assert_eq!(search_excerpt("text", "", 10), None);
+}
+
+// Test content_search stops at max_results matches, in listing order
+#[test]
+fn test_content_search() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+    let sign_up_result = sign_up(secret_key, homeserver);
+    assert_eq!(sign_up_result[0], "success");
+
+    let public_key = keypair.public_key().to_string();
+    let directory = format!("search-{}", generate_test_keypair().public_key());
+    for name in ["a", "b", "c"] {
+        let url = format!("pubky://{}/pub/{}/{}", public_key, directory, name);
+        assert_eq!(put(url, format!("note {} mentions the needle", name))[0], "success");
+    }
+
+    let result = content_search(public_key.clone(), format!("/pub/{}/", directory), "NEEDLE".to_string(), 2);
+    assert_eq!(result[0], "success");
+    let matches: Vec<serde_json::Value> = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(matches.len(), 2);
+    assert!(matches[0]["url"].as_str().unwrap().ends_with(&format!("{}/a", directory)));
+    assert!(matches[1]["url"].as_str().unwrap().ends_with(&format!("{}/b", directory)));
+}
+
+// Test publishes rejected for their timestamp are reported as CLOCK_SKEW
+#[test]
+fn test_publish_clock_skew_detection() {
+    let error = pkarr::Error::NotMostRecent;
+    assert!(publish_clock_skew(&error));
+    assert!(publish_error_message(&error).starts_with("CLOCK_SKEW: "));
+
+    let error = pkarr::Error::PublishInflight;
+    assert!(!publish_clock_skew(&error));
+    assert!(publish_error_message(&error).starts_with("RATE_LIMITED: "));
+}
+
+// Test atomic writes reject invalid operations before writing anything
+#[test]
+fn test_atomic_write_validation() {
+    let result = atomic_write("not json".to_string());
+    assert_eq!(result[0], "error");
+
+    let operations = serde_json::json!([
+        { "url": "pubky://example/pub/example.com/post", "content": "post" },
+        { "url": "ftp://example/pub/example.com/meta", "content": "meta" },
+    ]);
+    let result = atomic_write(operations.to_string());
+    assert_eq!(result[0], "error");
+
+    let result = atomic_write("[]".to_string());
+    assert_eq!(result[0], "success");
+    assert_eq!(result[1], r#"{"written":0}"#);
+}
+
+// Test overwrite-detecting puts reject invalid URLs before any request
+#[test]
+fn test_put_detect_overwrite_validation() {
+    let result = put_detect_overwrite("not a url".to_string(), "content".to_string());
+    assert_eq!(result[0], "error");
+    assert_eq!(result[1], "Failed to parse URL");
+
+    let result = put_detect_overwrite("ftp://example.com/file".to_string(), "content".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test the first put reports no overwrite and the second one does
+#[test]
+fn test_put_detect_overwrite() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+    let sign_up_result = sign_up(secret_key, homeserver);
+    assert_eq!(sign_up_result[0], "success");
+
+    let url = format!("pubky://{}/pub/test.com/detect-overwrite-{}", keypair.public_key(), generate_test_keypair().public_key());
+    let result = put_detect_overwrite(url.clone(), "first".to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["url"], url.as_str());
+    assert_eq!(json["overwritten"], false);
+    assert!(json["previous_etag"].is_null());
+
+    let result = put_detect_overwrite(url.clone(), "second".to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["overwritten"], true);
+    assert_eq!(get(url)[1], "second");
+}
+
+// Test get_or_put rejects invalid URLs without writing
+#[test]
+fn test_get_or_put_validation() {
+    let result = get_or_put("not a url".to_string(), "{}".to_string());
+    assert_eq!(result[0], "error");
+    assert_eq!(result[1], "Failed to parse URL");
+
+    let result = get_or_put("ftp://example.com/config.json".to_string(), "{}".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test get_or_put writes the default once and then returns the stored content
+#[test]
+fn test_get_or_put() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+    let sign_up_result = sign_up(secret_key, homeserver);
+    assert_eq!(sign_up_result[0], "success");
+
+    let url = format!("pubky://{}/pub/test.com/get-or-put-{}", keypair.public_key(), generate_test_keypair().public_key());
+    let result = get_or_put(url.clone(), "default".to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json, serde_json::json!({ "content": "default", "created": true }));
+
+    let result = get_or_put(url, "other default".to_string());
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json, serde_json::json!({ "content": "default", "created": false }));
+}
+
+// Test accounts hold their keypair and resolve paths against their own root
+#[test]
+fn test_account() {
+    let keypair = generate_test_keypair();
+    let account = Account::from_secret_key(hex::encode(keypair.secret_key())).unwrap();
+    assert_eq!(account.pubky(), keypair.public_key().to_string());
+
+    let result = account.get("not a url".to_string());
+    assert_eq!(result[0], "error");
+    assert_eq!(result[1], "Failed to parse URL");
+
+    let error = Account::from_secret_key("not hex".to_string()).err().unwrap();
+    assert_eq!(error.to_string(), "Invalid secret key: Failed to decode secret key");
+}
+
+// Test accounts created from a mnemonic derive the same keypair every time
+#[test]
+fn test_account_from_mnemonic() {
+    let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let keypair = keypair_from_mnemonic(mnemonic).unwrap();
+    let account = Account::from_mnemonic(mnemonic.to_string()).unwrap();
+    assert_eq!(account.pubky(), keypair.public_key().to_string());
+    assert_eq!(Account::from_mnemonic(mnemonic.to_string()).unwrap().pubky(), account.pubky());
+
+    assert!(Account::from_mnemonic("abandon abandon abandon".to_string()).is_err());
+    assert!(Account::from_mnemonic(mnemonic.replace("about", "abandon")).is_err());
+}
+
+// Test reachable relays are listed fastest first
+#[test]
+fn test_find_nearest_relay() {
+    let result = find_nearest_relay();
+    if result[0] == "error" {
+        assert_eq!(result[1], "No known relay is reachable");
+        return;
+    }
+    let relays: Vec<serde_json::Value> = serde_json::from_str(&result[1]).unwrap();
+    let latencies: Vec<u64> = relays.iter().map(|relay| relay["latency_ms"].as_u64().unwrap()).collect();
+    assert!(latencies.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+// Test records render as zone file lines
+#[test]
+fn test_resource_record_to_zone_line() {
+    use pkarr::dns::rdata::RData;
+    use pkarr::dns::ResourceRecord;
+
+    let name = pkarr::dns::Name::new("_pubky.example").unwrap();
+    let record = ResourceRecord::new(name.clone(), pkarr::dns::CLASS::IN, 3600, RData::A(pkarr::dns::rdata::A { address: 0x7f000001 }));
+    assert_eq!(resource_record_to_zone_line(&record).unwrap(), "_pubky.example. 3600 IN A 127.0.0.1");
+
+    let txt = pkarr::dns::rdata::TXT::new().with_string("say \"hi\"").unwrap();
+    let record = ResourceRecord::new(name.clone(), pkarr::dns::CLASS::IN, 60, RData::TXT(txt));
+    assert_eq!(resource_record_to_zone_line(&record).unwrap(), r#"_pubky.example. 60 IN TXT "say \"hi\"""#);
+
+    let mut svcb = pkarr::dns::rdata::SVCB::new(1, pkarr::dns::Name::new("homeserver.example").unwrap());
+    svcb.set_port(6287);
+    let record = ResourceRecord::new(name, pkarr::dns::CLASS::IN, 60, RData::HTTPS(pkarr::dns::rdata::HTTPS(svcb)));
+    assert_eq!(resource_record_to_zone_line(&record).unwrap(), "_pubky.example. 60 IN HTTPS 1 homeserver.example. port=6287");
+}
+
+// Test federated profiles are validated before any homeserver is contacted
+#[test]
+fn test_create_federated_profile_validation() {
+    let secret_key = hex::encode(generate_test_keypair().secret_key());
+    let homeservers = vec![generate_test_keypair().public_key().to_string()];
+
+    let result = create_federated_profile("not hex".to_string(), "{}".to_string(), homeservers.clone());
+    assert_eq!(result[0], "error");
+
+    let result = create_federated_profile(secret_key.clone(), "[]".to_string(), homeservers);
+    assert_eq!(result[1], "Profile must be a JSON object");
+
+    let result = create_federated_profile(secret_key, "{}".to_string(), vec![]);
+    assert_eq!(result[1], "At least one homeserver is required");
+}
+
+// Test zone file text parses into records, round tripping through the zone line renderer
+#[test]
+fn test_records_from_zonefile() {
+    let zonefile = r#"
+$TTL 300
+; the homeserver
+_pubky        HTTPS 0 homeserver.example. port=6287 alpn="h2"
+@      60 IN  A     127.0.0.1
+              TXT   "hello world" "say \"hi\""
+mail   IN 120 MX    10 mx.example.
+"#;
+    let records = records_from_zonefile(zonefile).unwrap();
+    let lines: Vec<String> = records.iter().map(|record| resource_record_to_zone_line(record).unwrap()).collect();
+    assert_eq!(lines, vec![
+        r#"_pubky. 300 IN HTTPS 0 homeserver.example. alpn="h2" port=6287"#,
+        "@. 60 IN A 127.0.0.1",
+        r#"@. 300 IN TXT "hello world" "say \"hi\"""#,
+        "mail. 120 IN MX 10 mx.example.",
+    ]);
+
+    let error = records_from_zonefile("ok A 127.0.0.1\nbad A not-an-ip").err().unwrap();
+    assert!(error.starts_with("Line 2: "));
+    assert!(records_from_zonefile("x 60 IN LOC 1 2 3").err().unwrap().contains("Unsupported record type"));
+}
+
+// Test list item counts reject invalid URLs
+#[test]
+fn test_get_list_item_count_validation() {
+    let result = get_list_item_count("not a url".to_string());
+    assert_eq!(result[0], "error");
+    assert_eq!(result[1], "Failed to parse URL");
+
+    let result = get_list_item_count("ftp://example.com/pub/".to_string());
+    assert_eq!(result[0], "error");
+}
+
+// Test counting the entries of a directory
+#[test]
+fn test_get_list_item_count() {
+    let (keypair, secret_key, homeserver) = get_test_setup();
+    let sign_up_result = sign_up(secret_key, homeserver);
+    assert_eq!(sign_up_result[0], "success");
+
+    let directory = format!("pubky://{}/pub/count-{}/", keypair.public_key(), generate_test_keypair().public_key());
+    assert_eq!(put(format!("{}first", directory), "1".to_string())[0], "success");
+    assert_eq!(put(format!("{}second", directory), "2".to_string())[0], "success");
+
+    let result = get_list_item_count(directory);
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["count"], 2);
+    assert!(json["approximate"].is_boolean());
+}
+
+// Test capabilities convert to structured objects
+#[test]
+fn test_capability_to_json() {
+    let capability = pubky_common::capabilities::Capability::try_from("/pub/app/:rw").unwrap();
+    assert_eq!(capability_to_json(&capability), serde_json::json!({ "path": "/pub/app/", "read": true, "write": true }));
+
+    let capability = pubky_common::capabilities::Capability::try_from("/pub/app/:r").unwrap();
+    assert_eq!(capability_to_json(&capability), serde_json::json!({ "path": "/pub/app/", "read": true, "write": false }));
+}
+
+// Test public record exports reject invalid keys
+#[test]
+fn test_export_public_records_invalid_key() {
+    let result = export_public_records("not a key".to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].starts_with("Invalid public key"));
+}
+
+// Collects export progress events of one public key
+struct ExportProgressListener {
+    public_key: String,
+    events: std::sync::Arc<std::sync::Mutex<Vec<serde_json::Value>>>,
+}
+
+impl EventListener for ExportProgressListener {
+    fn on_event_occurred(&self, event_data: String) {
+        if let Ok(event) = serde_json::from_str::<serde_json::Value>(&event_data) {
+            if event["event"] == "export_progress" && event["public_key"] == self.public_key.as_str() {
+                self.events.lock().unwrap().push(event);
+            }
+        }
+    }
+}
+
+// Test exported files carry their content and round trip through import_public_records
+#[test]
+fn test_export_and_import_public_records() {
+    let (_, _, homeserver) = get_test_setup();
+    // A fresh key, so the export only holds the files written here
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let public_key = keypair.public_key().to_string();
+    let sign_up_result = sign_up(secret_key.clone(), homeserver);
+    assert_eq!(sign_up_result[0], "success");
+
+    let first_url = format!("pubky://{}/pub/export.test/first", public_key);
+    let second_url = format!("pubky://{}/pub/export.test/second", public_key);
+    assert_eq!(put(first_url.clone(), "first content".to_string())[0], "success");
+    assert_eq!(put(second_url.clone(), "second content".to_string())[0], "success");
+
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    set_event_listener(Box::new(ExportProgressListener { public_key: public_key.clone(), events: events.clone() }));
+    let result = export_public_records(public_key.clone());
+    remove_event_listener();
+    assert_eq!(result[0], "success");
+
+    let exported: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    let exported_records = exported.as_array().unwrap();
+    assert_eq!(exported_records.len(), 2);
+    for (url, content) in [(&first_url, "first content"), (&second_url, "second content")] {
+        let record = exported_records.iter().find(|record| record["url"] == url.as_str()).unwrap();
+        let content_base64 = record["content_base64"].as_str().unwrap();
+        assert_eq!(base64::Engine::decode(&base64::engine::general_purpose::STANDARD, content_base64).unwrap(), content.as_bytes());
+        assert!(record.get("content_type").is_some());
+        assert!(record["fetched_at"].as_u64().unwrap() > 0);
+    }
+    let events = events.lock().unwrap().clone();
+    let last_event = events.last().unwrap();
+    assert_eq!(last_event["fetched"], 2);
+    assert_eq!(last_event["total"], 2);
+
+    // Restore deleted files from the export
+    assert_eq!(delete_file(first_url.clone())[0], "success");
+    assert_eq!(delete_file(second_url.clone())[0], "success");
+    let result = import_public_records(secret_key, result[1].clone());
+    assert_eq!(result[0], "success");
+    let summary: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(summary, serde_json::json!({ "imported": 2, "skipped": 0, "failed": 0 }));
+    assert_eq!(get(first_url)[1], "first content");
+    assert_eq!(get(second_url)[1], "second content");
+}
+
+// Test publish_and_confirm rejects an invalid secret key before publishing
+#[test]
+fn test_publish_and_confirm_invalid_key() {
+    let result = publish_and_confirm("name".to_string(), "content".to_string(), "not hex".to_string(), 1);
+    assert_eq!(result[0], "error");
+    assert_eq!(result[1], "Failed to decode secret key");
+}
+
+// Test publish_and_confirm waits until the published packet resolves
+#[test]
+fn test_publish_and_confirm() {
+    let keypair = generate_test_keypair();
+    let secret_key = hex::encode(keypair.secret_key());
+    let result = publish_and_confirm("confirm".to_string(), "confirmed content".to_string(), secret_key, 30);
+    assert_eq!(result[0], "success");
+    let json: serde_json::Value = serde_json::from_str(&result[1]).unwrap();
+    assert_eq!(json["public_key"], keypair.public_key().to_string());
+    assert_eq!(json["confirmed"], true);
+}
+
+// Test imports refuse records of another key before writing anything
+#[test]
+fn test_import_public_records_foreign_key() {
+    let keypair = generate_test_keypair();
+    let other = generate_test_keypair();
+    let records = serde_json::json!([
+        { "url": format!("pubky://{}/pub/example.com/a", keypair.public_key()), "content_type": null, "content_base64": "YQ==", "fetched_at": 0 },
+        { "url": format!("pubky://{}/pub/example.com/b", other.public_key()), "content_type": null, "content_base64": "Yg==", "fetched_at": 0 },
+    ]);
+    let result = import_public_records(hex::encode(keypair.secret_key()), records.to_string());
+    assert_eq!(result[0], "error");
+    assert!(result[1].ends_with(&format!("does not belong to {}", keypair.public_key())));
+
+    let result = import_public_records(hex::encode(keypair.secret_key()), "{}".to_string());
+    assert_eq!(result[0], "error");
+}